@@ -0,0 +1,10 @@
+-- Diesel migration to create relay_jobs table
+
+-- Relayed transactions still pending when the server stopped, checked again at startup
+CREATE TABLE IF NOT EXISTS relay_jobs (
+    tx_hash TEXT PRIMARY KEY, -- Hash of the relayed transaction
+    method TEXT NOT NULL, -- Contract method called by the transaction
+    status TEXT NOT NULL DEFAULT 'pending', -- pending / confirmed / reverted
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- Persisted at
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- Last status change
+);
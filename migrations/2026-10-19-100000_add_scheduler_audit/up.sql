@@ -0,0 +1,15 @@
+-- Diesel migration to create scheduler_audit table
+
+-- Actions taken by the poll scheduler (ending / finalizing polls)
+CREATE TABLE IF NOT EXISTS scheduler_audit (
+    id SERIAL PRIMARY KEY,
+    poll_id TEXT NOT NULL, -- ID of the poll
+    action TEXT NOT NULL, -- end_poll / finalize_poll
+    outcome TEXT NOT NULL, -- success / failed / abandoned
+    attempt INTEGER NOT NULL, -- Attempt number for this action
+    tx_hash TEXT, -- Hash of the relayed transaction, if mined
+    error TEXT, -- Reason of the failure
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- Created at
+);
+
+CREATE INDEX IF NOT EXISTS scheduler_audit_poll_id_idx ON scheduler_audit (poll_id);
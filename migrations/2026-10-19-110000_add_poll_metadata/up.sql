@@ -0,0 +1,8 @@
+-- Diesel migration to create poll_metadata table
+
+-- Rich metadata of the polls, addressed by the hash committed in their on-chain description
+CREATE TABLE IF NOT EXISTS poll_metadata (
+    hash TEXT PRIMARY KEY, -- keccak256 of the content (0x prefixed hex)
+    content TEXT NOT NULL, -- JSON document, stored as hashed
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- Created at
+);
@@ -0,0 +1,21 @@
+-- Diesel migration to create poll_drafts table
+
+-- Polls being prepared by their owner, created on-chain once published
+CREATE TABLE IF NOT EXISTS poll_drafts (
+    id SERIAL PRIMARY KEY,
+    owner TEXT NOT NULL, -- Address of the author (JWT subject)
+    name TEXT NOT NULL, -- Name of the poll
+    description TEXT NOT NULL, -- Plain description of the poll
+    options TEXT[] NOT NULL, -- Options of the poll
+    start_time BIGINT NOT NULL, -- Unix seconds
+    end_time BIGINT NOT NULL, -- Unix seconds
+    metadata TEXT, -- Off-chain metadata (JSON)
+    status TEXT NOT NULL, -- draft / publishing / published
+    approved_by TEXT, -- Address of the administrator who approved the draft
+    poll_id TEXT, -- ID of the poll once published
+    tx_hash TEXT, -- Hash of the create_poll transaction
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- Created at
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- Last edited at
+);
+
+CREATE INDEX IF NOT EXISTS poll_drafts_owner_idx ON poll_drafts (owner);
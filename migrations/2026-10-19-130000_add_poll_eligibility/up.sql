@@ -0,0 +1,9 @@
+-- Diesel migration to create poll_eligibility table
+
+-- Addresses allowed to vote in polls restricted by their owner
+CREATE TABLE IF NOT EXISTS poll_eligibility (
+    poll_id TEXT NOT NULL, -- ID of the poll
+    address TEXT NOT NULL, -- Checksummed address of the voter
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- Uploaded at
+    PRIMARY KEY (poll_id, address)
+);
@@ -0,0 +1,9 @@
+-- Diesel migration to create merkle_allowlists table
+
+-- Addresses of the Merkle allowlists, committed by their root in the metadata of polls
+CREATE TABLE IF NOT EXISTS merkle_allowlists (
+    root TEXT NOT NULL, -- Merkle root of the list (0x prefixed hex)
+    address TEXT NOT NULL, -- Checksummed address of the voter
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- Uploaded at
+    PRIMARY KEY (root, address)
+);
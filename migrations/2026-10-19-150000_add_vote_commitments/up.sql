@@ -0,0 +1,13 @@
+-- Diesel migration to create vote_commitments table
+
+-- Votes committed in commit-reveal polls, relayed once revealed
+CREATE TABLE IF NOT EXISTS vote_commitments (
+    poll_id TEXT NOT NULL, -- On-chain id of the poll
+    voter TEXT NOT NULL, -- Checksummed address of the voter
+    commitment TEXT NOT NULL, -- keccak256 of the vote and its salt (0x prefixed hex)
+    status TEXT NOT NULL DEFAULT 'committed', -- committed, revealing or revealed
+    tx_hash TEXT, -- Transaction relaying the revealed vote
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- Committed at
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- Last change of the commitment or status
+    PRIMARY KEY (poll_id, voter)
+);
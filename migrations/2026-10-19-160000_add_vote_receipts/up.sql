@@ -0,0 +1,15 @@
+-- Diesel migration to create vote_receipts table
+
+-- Signed receipts of the relayed votes, looked up by their id
+CREATE TABLE IF NOT EXISTS vote_receipts (
+    id TEXT PRIMARY KEY, -- Signed digest of the receipt (0x prefixed hex)
+    poll_id TEXT NOT NULL, -- On-chain id of the poll
+    voter TEXT NOT NULL, -- Checksummed address of the voter
+    option_hash TEXT NOT NULL, -- keccak256 of the option voted for
+    tx_hash TEXT NOT NULL, -- Transaction relaying the vote
+    block_number BIGINT NOT NULL, -- Block of the transaction
+    log_index BIGINT NOT NULL, -- Index of the VoteCast event in the block
+    signer TEXT NOT NULL, -- Address of the server key that signed the receipt
+    signature TEXT NOT NULL, -- EIP-191 signature of the id (0x prefixed hex)
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- Issued at
+);
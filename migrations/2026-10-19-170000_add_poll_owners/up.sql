@@ -0,0 +1,9 @@
+-- Diesel migration to create poll_owners table
+
+-- Creators of the polls relayed by this server. On-chain, the relay wallet owns them all
+CREATE TABLE IF NOT EXISTS poll_owners (
+    poll_id TEXT PRIMARY KEY, -- On-chain id of the poll
+    owner TEXT NOT NULL, -- Checksummed address of the user who created the poll
+    tx_hash TEXT NOT NULL, -- Transaction creating the poll
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP -- Recorded at
+);
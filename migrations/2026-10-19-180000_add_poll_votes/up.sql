@@ -0,0 +1,13 @@
+-- Diesel migration to create poll_votes table
+
+-- Votes relayed by this server, one per voter and poll. The row is created before the
+-- vote is relayed, so that concurrent requests cannot relay it twice
+CREATE TABLE IF NOT EXISTS poll_votes (
+    poll_id TEXT NOT NULL, -- On-chain id of the poll
+    voter TEXT NOT NULL, -- Checksummed address of the voter
+    status TEXT NOT NULL DEFAULT 'voting', -- voting (being relayed) or voted
+    tx_hash TEXT, -- Transaction relaying the vote, once sent
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- Creation time
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, -- Last status change
+    PRIMARY KEY (poll_id, voter)
+);
@@ -1,8 +1,14 @@
+use alloy::primitives::Address;
 use jsonwebtoken::{
-    decode, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
+    decode, encode, errors::Error as JwtError, Algorithm, DecodingKey, EncodingKey, Header,
+    TokenData, Validation,
 };
+use log::warn;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::routes::auth::signin::{Identity, TokenPair};
 
@@ -13,6 +19,45 @@ pub struct Claims {
     pub exp: usize,  // Expiration time (in seconds since epoch)
 }
 
+impl Claims {
+    /// Address of the authenticated user
+    pub fn address(&self) -> Option<Address> {
+        self.sub.parse().ok()
+    }
+
+    /// Whether the user is listed in ADMIN_ADDRESSES
+    pub fn is_admin(&self) -> bool {
+        self.address()
+            .is_some_and(|address| admins().contains(&address))
+    }
+
+    /// Whether the user may manage a resource owned by `owner`
+    pub fn is_owner_or_admin(&self, owner: Address) -> bool {
+        self.address() == Some(owner) || self.is_admin()
+    }
+}
+
+// Administrators, read once from the comma separated ADMIN_ADDRESSES setting
+static ADMINS: OnceLock<Vec<Address>> = OnceLock::new();
+
+fn admins() -> &'static Vec<Address> {
+    ADMINS.get_or_init(|| {
+        std::env::var("ADMIN_ADDRESSES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .filter_map(|address| match address.parse() {
+                Ok(address) => Some(address),
+                Err(_) => {
+                    warn!("Ignoring invalid admin address: {}", address);
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
 #[derive(Clone)]
 pub struct JwtManager {
     access_secret: String,
@@ -32,44 +77,34 @@ impl JwtManager {
         }
     }
 
-    pub fn new_access_token_from_refresh(&self, refresh_token: &str) -> Option<String> {
-        let decoded_token = self.decode_token(refresh_token, true).ok()?;
+    pub fn new_access_token_from_refresh(&self, refresh_token: &str) -> Result<String, JwtError> {
+        let decoded_token = self.decode_token(refresh_token, true)?;
 
         // Create a new access token from the claims extracted from the refresh token
         let mut claims = decoded_token.claims.clone();
 
-        // Update the expiration time
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs() as usize;
-
         // Compute the new expiration time
-        let new_exp = now + self.access_token_exp;
+        let new_exp = now() + self.access_token_exp;
 
         // Update the expiration time in the claims
         claims.exp = new_exp;
 
         // Generate the new access token
-        Some(self.generate_token(claims, &self.access_secret))
+        self.generate_token(claims, &self.access_secret)
     }
 
     // Generate a single JWT token
-    pub fn generate_token(&self, claims: Claims, secret: &str) -> String {
+    pub fn generate_token(&self, claims: Claims, secret: &str) -> Result<String, JwtError> {
         encode(
             &Header::default(),
             &claims,
             &EncodingKey::from_secret(secret.as_ref()),
         )
-        .expect("Failed to generate token")
     }
 
     // Generate a pair of JWT tokens (access + refresh)
-    pub fn generate_token_pair(&self, identity: Identity) -> TokenPair {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("Time went backwards")
-            .as_secs() as usize;
+    pub fn generate_token_pair(&self, identity: Identity) -> Result<TokenPair, JwtError> {
+        let now = now();
 
         // Claims for access token
         let access_claims = Claims {
@@ -86,15 +121,15 @@ impl JwtManager {
         };
 
         // Generate access token
-        let token = self.generate_token(access_claims, &self.access_secret);
+        let token = self.generate_token(access_claims, &self.access_secret)?;
 
         // Generate refresh token
-        let refresh_token = self.generate_token(refresh_claims, &self.refresh_secret);
+        let refresh_token = self.generate_token(refresh_claims, &self.refresh_secret)?;
 
-        TokenPair {
+        Ok(TokenPair {
             token,
             refresh_token,
-        }
+        })
     }
 
     /// Decode token with our custom validation routine
@@ -102,7 +137,26 @@ impl JwtManager {
         &self,
         token: &str,
         is_refresh: bool,
-    ) -> Result<TokenData<Claims>, jsonwebtoken::errors::Error> {
+    ) -> Result<TokenData<Claims>, JwtError> {
+        self.decode_with_validation(token, is_refresh, true)
+    }
+
+    /// Decode token with our custom validation routine, but accept expired tokens. Only use
+    /// this to identify the owner of a token that is about to be revoked!
+    pub fn decode_expired_token(
+        &self,
+        token: &str,
+        is_refresh: bool,
+    ) -> Result<TokenData<Claims>, JwtError> {
+        self.decode_with_validation(token, is_refresh, false)
+    }
+
+    fn decode_with_validation(
+        &self,
+        token: &str,
+        is_refresh: bool,
+        validate_exp: bool,
+    ) -> Result<TokenData<Claims>, JwtError> {
         let secret = if is_refresh {
             &self.refresh_secret
         } else {
@@ -113,6 +167,7 @@ impl JwtManager {
         let mut validation = Validation::new(Algorithm::HS256);
         validation.set_audience(&["VoteChain"]);
         validation.set_required_spec_claims(&["sub", "exp", "aud"]);
+        validation.validate_exp = validate_exp;
 
         // Decode the token, and pass validation rules
         decode::<Claims>(
@@ -122,3 +177,12 @@ impl JwtManager {
         )
     }
 }
+
+// Current time in seconds since epoch. A clock set before the epoch yields 0 (tokens are
+// then simply considered expired) instead of crashing the worker
+fn now() -> usize {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as usize)
+        .unwrap_or_default()
+}
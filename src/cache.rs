@@ -0,0 +1,491 @@
+use std::{
+    future::Future,
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    http::header::{ETag, EntityTag, IfNoneMatch},
+    HttpMessage, HttpRequest, HttpResponse, ResponseError,
+};
+use alloy::{
+    hex,
+    primitives::{keccak256, Address, TxHash, U256},
+};
+use log::{debug, info, warn};
+use lru::LruCache;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    config,
+    contracts::{
+        errors::ContractError,
+        votechain::{Poll, PollResults, RelayedVote},
+    },
+    errors::ApiErrorResponse,
+};
+
+// Default lifetime of the entries of polls that may still change
+const DEFAULT_ACTIVE_TTL_SECS: u64 = 5;
+
+// Defaults of the in-process backend: entries kept, and interval between sweeps of the
+// expired ones
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+const DEFAULT_SWEEP_INTERVAL_SECS: u64 = 60;
+
+// Prefix of the keys stored in Redis
+const REDIS_PREFIX: &str = "votechain:";
+
+/// Data that can be cached, forever once it can no longer change
+pub trait Cacheable: Serialize + DeserializeOwned {
+    fn is_final(&self) -> bool;
+}
+
+impl Cacheable for Poll {
+    fn is_final(&self) -> bool {
+        self.is_ended && !self.winner.is_empty()
+    }
+}
+
+// Balances are only read at snapshot blocks, which never change
+impl Cacheable for U256 {
+    fn is_final(&self) -> bool {
+        true
+    }
+}
+
+impl Cacheable for PollResults {
+    fn is_final(&self) -> bool {
+        self.winner.is_some()
+    }
+}
+
+// A vote found in a mined transaction may still be dropped by a reorg, and a transaction
+// without one may still be pending: both are kept for the active lifetime only
+impl Cacheable for Option<RelayedVote> {
+    fn is_final(&self) -> bool {
+        false
+    }
+}
+
+/// Kinds of cached data, keyed by poll id, by transaction for relayed votes or, for
+/// balances, by token / owner / block.
+/// Entries that may still change are also keyed by the block of the last contract event
+/// seen, see `PollCache::observe_block`
+#[derive(Clone, Copy)]
+pub enum CacheKey {
+    Poll(U256),
+    Results(U256),
+    RelayedVote(TxHash),
+    Balance {
+        token: Address,
+        owner: Address,
+        block: u64,
+    },
+}
+
+impl CacheKey {
+    fn as_string(&self) -> String {
+        match self {
+            CacheKey::Poll(id) => format!("poll:{}", id),
+            CacheKey::Results(id) => format!("results:{}", id),
+            CacheKey::RelayedVote(tx_hash) => format!("vote:{}", tx_hash),
+            CacheKey::Balance {
+                token,
+                owner,
+                block,
+            } => format!("balance:{}:{}:{}", token, block, owner),
+        }
+    }
+}
+
+// Entry of the in-process backend, stored as JSON like in Redis
+struct Entry {
+    value: String,
+    expires: Option<Instant>,
+}
+
+enum Backend {
+    Memory(Mutex<LruCache<String, Entry>>), // Least recently used entries evicted first
+    Redis(ConnectionManager),
+}
+
+/// Read-through cache of poll data. Entries of finalized polls never expire, the others
+/// live CACHE_ACTIVE_TTL_SECS and are invalidated when an event of their poll is seen.
+/// Backend errors are only logged: the data is then read from the contract
+pub struct PollCache {
+    backend: Backend,
+    active_ttl: Duration,
+    head: AtomicU64, // Block of the last contract event seen
+}
+
+impl PollCache {
+    /// Cache stored in Redis when CACHE_REDIS_URL is set, in memory otherwise (up to
+    /// CACHE_MAX_ENTRIES entries)
+    pub async fn new() -> Self {
+        let active_ttl = Duration::from_secs(config::parse_or(
+            "CACHE_ACTIVE_TTL_SECS",
+            DEFAULT_ACTIVE_TTL_SECS,
+        ));
+        let max_entries = config::parse_or("CACHE_MAX_ENTRIES", DEFAULT_MAX_ENTRIES);
+
+        let url = std::env::var("CACHE_REDIS_URL").unwrap_or_default();
+        if !url.is_empty() {
+            match connect(&url).await {
+                Ok(connection) => {
+                    info!("Caching poll data in Redis");
+                    return Self::with_backend(Backend::Redis(connection), active_ttl);
+                }
+                Err(e) => warn!(
+                    "Unable to connect to CACHE_REDIS_URL ({}). Caching in memory.",
+                    e
+                ),
+            }
+        }
+        Self::memory(active_ttl, max_entries)
+    }
+
+    fn memory(active_ttl: Duration, max_entries: usize) -> Self {
+        let capacity = NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN);
+        let entries = Mutex::new(LruCache::new(capacity));
+        Self::with_backend(Backend::Memory(entries), active_ttl)
+    }
+
+    fn with_backend(backend: Backend, active_ttl: Duration) -> Self {
+        Self {
+            backend,
+            active_ttl,
+            head: AtomicU64::new(0),
+        }
+    }
+
+    /// Return the cached value, or load it and cache it
+    pub async fn get_or_load<T, F, Fut>(&self, key: CacheKey, load: F) -> Result<T, ContractError>
+    where
+        T: Cacheable,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, ContractError>>,
+    {
+        // Final values are stored under the key alone, the others at the current block
+        let key = key.as_string();
+        let at_head = self.at_head(&key);
+        for key in [&key, &at_head] {
+            if let Some(value) = self.get(key).await {
+                if let Ok(value) = serde_json::from_str(&value) {
+                    return Ok(value);
+                }
+            }
+        }
+
+        let value = load().await?;
+        if let Ok(json) = serde_json::to_string(&value) {
+            match value.is_final() {
+                true => self.set(&key, json, None).await,
+                false => self.set(&at_head, json, Some(self.active_ttl)).await,
+            }
+        }
+        Ok(value)
+    }
+
+    /// Record a block with an event of the contract. The state of the contract only
+    /// changes with its events: entries that may change are keyed by the block of the last
+    /// one, so that the entries read before it are no longer used
+    pub fn observe_block(&self, block: u64) {
+        self.head.fetch_max(block, Ordering::SeqCst);
+    }
+
+    // Key of a value that may still change, read after the last event seen
+    fn at_head(&self, key: &str) -> String {
+        format!("{}@{}", key, self.head.load(Ordering::SeqCst))
+    }
+
+    /// Drop the expired entries of the in-process backend every CACHE_SWEEP_INTERVAL_SECS
+    /// (Redis expires them itself)
+    pub async fn sweep(&self) {
+        let Backend::Memory(entries) = &self.backend else {
+            return;
+        };
+        let interval = Duration::from_secs(
+            config::parse_or("CACHE_SWEEP_INTERVAL_SECS", DEFAULT_SWEEP_INTERVAL_SECS).max(1),
+        );
+
+        loop {
+            tokio::time::sleep(interval).await;
+            let swept = sweep_expired(entries, Instant::now());
+            if swept > 0 {
+                debug!("Swept {} expired cache entries", swept);
+            }
+        }
+    }
+
+    /// Drop every entry of a poll, after a change was observed on chain. Routes relaying a
+    /// transaction call it once the transaction is confirmed, and the event watcher calls it
+    /// again when the log arrives: a read in between may have cached the previous state
+    /// from a node behind, and other replicas only learn about the change from the event
+    pub async fn invalidate(&self, poll_id: U256) {
+        let keys = [CacheKey::Poll(poll_id), CacheKey::Results(poll_id)]
+            .map(|k| k.as_string())
+            .map(|key| [self.at_head(&key), key]);
+        let keys = keys.as_flattened();
+
+        match &self.backend {
+            Backend::Memory(entries) => {
+                let mut entries = entries.lock().unwrap_or_else(PoisonError::into_inner);
+                for key in keys {
+                    entries.pop(key);
+                }
+            }
+            Backend::Redis(connection) => {
+                let keys: Vec<_> = keys
+                    .iter()
+                    .map(|key| format!("{}{}", REDIS_PREFIX, key))
+                    .collect();
+                let result: redis::RedisResult<()> = connection.clone().del(&keys).await;
+                if let Err(e) = result {
+                    warn!("Unable to invalidate cached poll {}: {}", poll_id, e);
+                }
+            }
+        }
+    }
+
+    async fn get(&self, key: &str) -> Option<String> {
+        match &self.backend {
+            Backend::Memory(entries) => {
+                let mut entries = entries.lock().unwrap_or_else(PoisonError::into_inner);
+                match entries.get(key) {
+                    Some(entry) if entry.expires.is_some_and(|at| at <= Instant::now()) => {
+                        entries.pop(key);
+                        None
+                    }
+                    Some(entry) => Some(entry.value.clone()),
+                    None => None,
+                }
+            }
+            Backend::Redis(connection) => {
+                let key = format!("{}{}", REDIS_PREFIX, key);
+                connection
+                    .clone()
+                    .get(&key)
+                    .await
+                    .inspect_err(|e| warn!("Unable to read cached {}: {}", key, e))
+                    .ok()
+                    .flatten()
+            }
+        }
+    }
+
+    async fn set(&self, key: &str, value: String, ttl: Option<Duration>) {
+        match &self.backend {
+            Backend::Memory(entries) => {
+                let entry = Entry {
+                    value,
+                    expires: ttl.map(|ttl| Instant::now() + ttl),
+                };
+                entries
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .put(key.to_owned(), entry);
+            }
+            Backend::Redis(connection) => {
+                let key = format!("{}{}", REDIS_PREFIX, key);
+                let mut connection = connection.clone();
+                let result: redis::RedisResult<()> = match ttl {
+                    Some(ttl) => connection.set_ex(&key, value, ttl.as_secs().max(1)).await,
+                    None => connection.set(&key, value).await,
+                };
+                if let Err(e) = result {
+                    warn!("Unable to cache {}: {}", key, e);
+                }
+            }
+        }
+    }
+}
+
+// Remove the entries expired at `now`, returning how many
+fn sweep_expired(entries: &Mutex<LruCache<String, Entry>>, now: Instant) -> usize {
+    let mut entries = entries.lock().unwrap_or_else(PoisonError::into_inner);
+    let expired: Vec<String> = entries
+        .iter()
+        .filter(|(_, entry)| entry.expires.is_some_and(|at| at <= now))
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in &expired {
+        entries.pop(key);
+    }
+    expired.len()
+}
+
+async fn connect(url: &str) -> redis::RedisResult<ConnectionManager> {
+    let client = redis::Client::open(url)?;
+    ConnectionManager::new(client).await
+}
+
+/// Respond with `body` as JSON and its ETag, or with 304 when the client already has it
+pub fn json_with_etag<T: Serialize>(req: &HttpRequest, body: &T) -> HttpResponse {
+    let json = match serde_json::to_vec(body) {
+        Ok(json) => json,
+        Err(_) => return ApiErrorResponse::InternalServerError.error_response(),
+    };
+
+    // Strong validator derived from the content
+    let tag = EntityTag::new_strong(hex::encode(&keccak256(&json)[..16]));
+
+    let fresh = match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(&tag)),
+        None => false,
+    };
+
+    if fresh {
+        HttpResponse::NotModified()
+            .insert_header(ETag(tag))
+            .finish()
+    } else {
+        HttpResponse::Ok()
+            .insert_header(ETag(tag))
+            .content_type("application/json")
+            .body(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+    use crate::timestamp::Timestamp;
+
+    fn poll(id: u64, finalized: bool) -> Poll {
+        Poll {
+            id: U256::from(id),
+            name: "Lunch".to_owned(),
+            description: String::new(),
+            start_time: Timestamp::from_secs(1_000).unwrap(),
+            end_time: Timestamp::from_secs(2_000).unwrap(),
+            winner: if finalized { "Pizza" } else { "" }.to_owned(),
+            is_ended: finalized,
+            owner: Address::ZERO,
+            metadata_hash: None,
+        }
+    }
+
+    // Read a poll through the cache, counting the loads
+    async fn read(cache: &PollCache, id: u64, finalized: bool, loads: &AtomicUsize) {
+        cache
+            .get_or_load(CacheKey::Poll(U256::from(id)), || async {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Ok(poll(id, finalized))
+            })
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn active_entries_expire_and_final_ones_do_not() {
+        let cache = PollCache::memory(Duration::from_millis(50), 100);
+        let (active, finalized) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        for _ in 0..2 {
+            read(&cache, 1, false, &active).await;
+            read(&cache, 2, true, &finalized).await;
+        }
+        assert_eq!(active.load(Ordering::SeqCst), 1);
+        assert_eq!(finalized.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        read(&cache, 1, false, &active).await;
+        read(&cache, 2, true, &finalized).await;
+        assert_eq!(active.load(Ordering::SeqCst), 2);
+        assert_eq!(finalized.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn invalidation_drops_only_the_entries_of_the_poll() {
+        let cache = PollCache::memory(Duration::from_secs(60), 100);
+        let (first, second) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        read(&cache, 1, false, &first).await;
+        read(&cache, 2, false, &second).await;
+
+        cache.invalidate(U256::from(1)).await;
+        read(&cache, 1, false, &first).await;
+        read(&cache, 2, false, &second).await;
+        assert_eq!(first.load(Ordering::SeqCst), 2);
+        assert_eq!(second.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn active_entries_are_keyed_by_the_last_event_block() {
+        let cache = PollCache::memory(Duration::from_secs(60), 100);
+        let (active, finalized) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        read(&cache, 1, false, &active).await;
+        read(&cache, 2, true, &finalized).await;
+
+        // An event of any poll may have changed the active ones, not the final ones
+        cache.observe_block(10);
+        read(&cache, 1, false, &active).await;
+        read(&cache, 2, true, &finalized).await;
+        assert_eq!(active.load(Ordering::SeqCst), 2);
+        assert_eq!(finalized.load(Ordering::SeqCst), 1);
+
+        // Older blocks, seen late, do not go back
+        cache.observe_block(5);
+        read(&cache, 1, false, &active).await;
+        assert_eq!(active.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn memory_is_bounded_and_swept() {
+        let cache = PollCache::memory(Duration::from_millis(50), 2);
+        let loads = AtomicUsize::new(0);
+        read(&cache, 1, true, &loads).await;
+        read(&cache, 2, true, &loads).await;
+        read(&cache, 1, true, &loads).await; // Poll 2 is now the least recently used
+        read(&cache, 3, false, &loads).await;
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+        read(&cache, 1, true, &loads).await;
+        read(&cache, 2, true, &loads).await;
+        assert_eq!(loads.load(Ordering::SeqCst), 4);
+
+        let Backend::Memory(entries) = &cache.backend else {
+            unreachable!()
+        };
+        let later = Instant::now() + Duration::from_millis(60);
+        cache
+            .set("poll:4@0", "{}".to_owned(), Some(Duration::from_millis(50)))
+            .await;
+        assert_eq!(entries.lock().unwrap().len(), 2);
+        assert_eq!(sweep_expired(entries, later), 1);
+        assert_eq!(entries.lock().unwrap().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn relayed_votes_are_not_cached_for_good() {
+        let cache = PollCache::memory(Duration::from_millis(50), 100);
+        let loads = AtomicUsize::new(0);
+        let read = || {
+            cache.get_or_load(CacheKey::RelayedVote(TxHash::repeat_byte(7)), || async {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Ok(Some(RelayedVote {
+                    transaction_hash: TxHash::repeat_byte(7),
+                    block_number: 42,
+                    log_index: 0,
+                    poll_id: U256::from(1),
+                    option: "Pizza".to_owned(),
+                    contract_receipt: None,
+                }))
+            })
+        };
+        read().await.unwrap();
+        read().await.unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        // A reorg may drop the vote from its block
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        read().await.unwrap();
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+}
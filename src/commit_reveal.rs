@@ -0,0 +1,154 @@
+use alloy::primitives::{keccak256, Address, B256, U256};
+use serde::Serialize;
+
+use crate::{
+    contracts::votechain::Poll,
+    errors::ApiErrorResponse,
+    metadata::{CommitReveal, PollView},
+};
+
+/// Phase of a commit-reveal poll
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Pending, // Before the start time
+    Commit,  // Votes are committed as hashes
+    Reveal,  // Committed votes are revealed and relayed
+    Closed,  // After the end time, unrevealed votes are not counted
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Pending => "pending",
+            Phase::Commit => "commit",
+            Phase::Reveal => "reveal",
+            Phase::Closed => "closed",
+        }
+    }
+}
+
+/// Commit-reveal settings committed in the metadata of a poll. Fails when the metadata is
+/// committed but unavailable, so that such a poll never accepts plain votes
+pub fn settings(poll: &PollView) -> Result<Option<&CommitReveal>, ApiErrorResponse> {
+    Ok(poll
+        .committed_metadata()?
+        .and_then(|metadata| metadata.commit_reveal.as_ref()))
+}
+
+/// Phase of a commit-reveal poll at `now` (chain clock)
+pub fn phase(settings: &CommitReveal, poll: &Poll, now: u64) -> Phase {
+    if poll.is_ended || now >= poll.end_time.as_secs() {
+        Phase::Closed
+    } else if now < poll.start_time.as_secs() {
+        Phase::Pending
+    } else if now < settings.commit_end_time.as_secs() {
+        Phase::Commit
+    } else {
+        Phase::Reveal
+    }
+}
+
+/// Error refusing a request made in the wrong phase: closed polls are reported as such,
+/// open ones with their current phase
+pub fn refusal(phase: Phase) -> ApiErrorResponse {
+    match phase {
+        Phase::Pending | Phase::Closed => ApiErrorResponse::PollClosed,
+        Phase::Commit | Phase::Reveal => ApiErrorResponse::WrongPhase {
+            phase: phase.as_str(),
+        },
+    }
+}
+
+/// Commitment to a vote, `keccak256(abi.encodePacked(poll_id, voter, option, salt))`. The
+/// poll and the voter are included so that a commitment cannot be replayed by someone else
+pub fn commitment(poll_id: U256, voter: Address, option: &str, salt: B256) -> B256 {
+    keccak256(
+        [
+            &poll_id.to_be_bytes::<32>()[..],
+            voter.as_slice(),
+            option.as_bytes(),
+            salt.as_slice(),
+        ]
+        .concat(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timestamp::Timestamp;
+
+    const START: u64 = 1_700_000_000;
+    const COMMIT_END: u64 = START + 100;
+    const END: u64 = START + 200;
+
+    fn timestamp(secs: u64) -> Timestamp {
+        Timestamp::from_secs(secs).unwrap()
+    }
+
+    fn poll(is_ended: bool) -> Poll {
+        Poll {
+            id: U256::from(1),
+            name: "Poll".into(),
+            description: String::new(),
+            start_time: timestamp(START),
+            end_time: timestamp(END),
+            winner: String::new(),
+            is_ended,
+            owner: Address::ZERO,
+            metadata_hash: None,
+        }
+    }
+
+    fn settings() -> CommitReveal {
+        CommitReveal {
+            commit_end_time: timestamp(COMMIT_END),
+        }
+    }
+
+    #[test]
+    fn phase_boundaries() {
+        let (settings, poll) = (settings(), poll(false));
+        assert!(phase(&settings, &poll, START - 1) == Phase::Pending);
+        assert!(phase(&settings, &poll, START) == Phase::Commit);
+        assert!(phase(&settings, &poll, COMMIT_END - 1) == Phase::Commit);
+        assert!(phase(&settings, &poll, COMMIT_END) == Phase::Reveal);
+        assert!(phase(&settings, &poll, END - 1) == Phase::Reveal);
+        assert!(phase(&settings, &poll, END) == Phase::Closed);
+    }
+
+    #[test]
+    fn ended_poll_is_closed() {
+        assert!(phase(&settings(), &poll(true), START) == Phase::Closed);
+    }
+
+    #[test]
+    fn commitment_binds_every_field() {
+        let (id, voter, salt) = (U256::from(1), Address::repeat_byte(1), B256::repeat_byte(1));
+        let reference = commitment(id, voter, "Yes", salt);
+        assert_eq!(reference, commitment(id, voter, "Yes", salt));
+        assert_ne!(reference, commitment(U256::from(2), voter, "Yes", salt));
+        assert_ne!(
+            reference,
+            commitment(id, Address::repeat_byte(2), "Yes", salt)
+        );
+        assert_ne!(reference, commitment(id, voter, "No", salt));
+        assert_ne!(
+            reference,
+            commitment(id, voter, "Yes", B256::repeat_byte(2))
+        );
+    }
+
+    #[test]
+    fn commitment_matches_abi_encode_packed() {
+        let (id, voter, salt) = (
+            U256::from(7),
+            Address::repeat_byte(0xab),
+            B256::repeat_byte(0xcd),
+        );
+        let packed =
+            alloy::sol_types::SolValue::abi_encode_packed(&(id, voter, "Yes".to_string(), salt));
+        assert_eq!(commitment(id, voter, "Yes", salt), keccak256(packed));
+    }
+}
@@ -1,5 +1,5 @@
 use dotenv::from_path;
-use std::env;
+use std::{env, str::FromStr};
 
 const KEYS: [&str; 14] = [
     "POSTGRES_HOST",
@@ -42,3 +42,18 @@ pub fn load_env() -> bool {
 
     ok
 }
+
+/// Read an optional boolean setting. Missing or unparsable values are treated as false
+pub fn flag(key: &str) -> bool {
+    env::var(key)
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Read an optional setting, falling back to `default` when missing or unparsable
+pub fn parse_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
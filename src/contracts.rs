@@ -1 +1,6 @@
+pub mod errors;
+pub mod multicall;
+#[cfg(test)]
+pub mod testing;
+pub mod token;
 pub mod votechain;
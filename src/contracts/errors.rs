@@ -0,0 +1,176 @@
+use alloy::{
+    primitives::TxHash,
+    sol_types::{self, GenericRevertReason, RevertReason},
+    transports::{RpcError, TransportError},
+};
+use derive_more::{Display, Error};
+
+use crate::errors::ApiErrorResponse;
+
+// Prefix used by nodes when the revert reason is only available in the message
+const REVERT_MESSAGE_PREFIX: &str = "execution reverted: ";
+
+// Typed error of a revert reason
+pub type RevertError = fn(&str) -> ContractError;
+
+// Exact `require` messages of the VoteChain contract (`contracts/abi/votechain.json`),
+// update them together. Reasons missing from this table are reported as `Reverted`
+pub const VOTECHAIN_REVERTS: &[(&str, RevertError)] = &[
+    ("Poll does not exist", |_| ContractError::UnknownPoll),
+    ("Voting has not started yet", |reason| {
+        ContractError::PollClosed(reason.to_owned())
+    }),
+    ("Voting has ended", |reason| {
+        ContractError::PollClosed(reason.to_owned())
+    }),
+    ("Poll is already marked as ended", |reason| {
+        ContractError::PollClosed(reason.to_owned())
+    }),
+    ("You have already voted", |_| ContractError::AlreadyVoted),
+    ("Not the poll owner", |_| ContractError::Unauthorized),
+];
+
+/// Reasons why an interaction with a contract failed
+#[derive(Debug, Display, Error)]
+pub enum ContractError {
+    #[display["poll does not exist"]]
+    UnknownPoll,
+
+    #[display["poll is not accepting votes: {_0}"]]
+    PollClosed(#[error(not(source))] String),
+
+    #[display["vote already cast"]]
+    AlreadyVoted,
+
+    #[display["caller is not allowed to perform this action"]]
+    Unauthorized,
+
+    #[display["contract reverted: {_0}"]]
+    Reverted(#[error(not(source))] String),
+
+    #[display["unexpected contract response: {_0}"]]
+    InvalidResponse(#[error(not(source))] String),
+
+    #[display["RPC node error: {_0}"]]
+    Rpc(#[error(not(source))] String),
+
+    #[display["server is shutting down, not relaying new transactions"]]
+    ShuttingDown,
+
+    #[display["RPC node is on chain {_0}, not CHAIN_ID: relaying is disabled"]]
+    WrongChain(#[error(not(source))] u64),
+
+    #[display["transaction {tx_hash} sent but not confirmed: {reason}"]]
+    Unconfirmed { tx_hash: TxHash, reason: String },
+}
+
+impl ContractError {
+    /// Map a revert reason emitted by the contract into a typed error, matching the exact
+    /// messages of `VOTECHAIN_REVERTS`
+    pub fn from_revert_reason(reason: &str) -> Self {
+        VOTECHAIN_REVERTS
+            .iter()
+            .find(|(message, _)| *message == reason)
+            .map(|(_, error)| error(reason))
+            .unwrap_or_else(|| ContractError::Reverted(reason.to_owned()))
+    }
+
+    /// Typed error of `Error(string)` / `Panic(uint256)` revert data, `None` when it cannot
+    /// be decoded
+    pub fn from_revert_data(data: &[u8]) -> Option<Self> {
+        Some(match GenericRevertReason::decode(data)? {
+            // Matched on the message alone, alloy displays it as "revert: {reason}"
+            RevertReason::ContractError(sol_types::ContractError::Revert(revert)) => {
+                Self::from_revert_reason(revert.reason())
+            }
+            // Reverts without data, e.g. `require` without message
+            RevertReason::RawString(reason) if reason.is_empty() => return None,
+            reason => Self::Reverted(reason.to_string()),
+        })
+    }
+}
+
+impl From<alloy::contract::Error> for ContractError {
+    fn from(err: alloy::contract::Error) -> Self {
+        match err {
+            alloy::contract::Error::TransportError(RpcError::ErrorResp(payload)) => {
+                // Decode `Error(string)` / `Panic(uint256)` revert data when available
+                if let Some(error) = payload
+                    .as_revert_data()
+                    .and_then(|data| ContractError::from_revert_data(&data))
+                {
+                    return error;
+                }
+
+                // Some nodes only report the reason inside the error message
+                match payload.message.strip_prefix(REVERT_MESSAGE_PREFIX) {
+                    Some(reason) => ContractError::from_revert_reason(reason),
+                    None => ContractError::Rpc(payload.message.to_string()),
+                }
+            }
+            alloy::contract::Error::TransportError(e) => ContractError::Rpc(e.to_string()),
+            alloy::contract::Error::PendingTransactionError(e) => ContractError::Rpc(e.to_string()),
+            e => ContractError::InvalidResponse(e.to_string()),
+        }
+    }
+}
+
+impl From<TransportError> for ContractError {
+    fn from(err: TransportError) -> Self {
+        alloy::contract::Error::from(err).into()
+    }
+}
+
+impl From<&ContractError> for ApiErrorResponse {
+    fn from(err: &ContractError) -> Self {
+        match err {
+            ContractError::UnknownPoll => ApiErrorResponse::NotFound,
+            ContractError::PollClosed(_) => ApiErrorResponse::PollClosed,
+            ContractError::AlreadyVoted => ApiErrorResponse::AlreadyVoted,
+            ContractError::Unauthorized => ApiErrorResponse::Forbidden,
+            ContractError::Reverted(reason) => ApiErrorResponse::ContractReverted {
+                reason: reason.clone(),
+            },
+            ContractError::InvalidResponse(_) => ApiErrorResponse::InternalServerError,
+            ContractError::Rpc(_)
+            | ContractError::ShuttingDown
+            | ContractError::WrongChain(_)
+            | ContractError::Unconfirmed { .. } => ApiErrorResponse::ServiceUnavailable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::sol_types::{Panic, PanicKind, Revert, SolError};
+
+    use super::*;
+
+    #[test]
+    fn revert_data_is_matched_on_its_message() {
+        let data = Revert::from("Poll does not exist").abi_encode();
+        assert!(matches!(
+            ContractError::from_revert_data(&data),
+            Some(ContractError::UnknownPoll)
+        ));
+
+        let data = Revert::from("Voting has ended").abi_encode();
+        assert!(matches!(
+            ContractError::from_revert_data(&data),
+            Some(ContractError::PollClosed(reason)) if reason == "Voting has ended"
+        ));
+
+        // Unknown reasons and panics are reported as they are
+        let data = Revert::from("Out of gas money").abi_encode();
+        assert!(matches!(
+            ContractError::from_revert_data(&data),
+            Some(ContractError::Reverted(reason)) if reason == "Out of gas money"
+        ));
+        let data = Panic::from(PanicKind::DivisionByZero).abi_encode();
+        assert!(matches!(
+            ContractError::from_revert_data(&data),
+            Some(ContractError::Reverted(_))
+        ));
+        assert!(ContractError::from_revert_data(&[]).is_none());
+    }
+}
@@ -0,0 +1,252 @@
+use alloy::{
+    primitives::{address, Address, Bytes},
+    providers::{Provider, RootProvider},
+    rpc::types::{BlockId, TransactionRequest},
+    sol,
+    sol_types::SolCall,
+};
+use log::{info, warn};
+use tokio::sync::OnceCell;
+use tracing::{instrument, Span};
+
+use super::errors::ContractError;
+use crate::{metrics, rpc::FailoverTransport};
+
+/// Address of Multicall3 on most chains (deterministic deployment)
+pub const DEFAULT_ADDRESS: Address = address!("cA11bde05977b3631167028862bE2a173976CA11");
+
+/// Default number of calls aggregated in a single `aggregate3`
+pub const DEFAULT_BATCH_SIZE: usize = 100;
+
+sol! {
+    #[sol(rpc)]
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Call3Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate3(Call3[] calldata calls) external payable returns (Call3Result[] memory returnData);
+    }
+}
+
+/// Aggregates contract reads into few `eth_call`s through Multicall3, falling back to
+/// sequential calls on chains where it is not deployed
+pub struct Multicall {
+    address: Address,
+    batch_size: usize,
+    deployed: OnceCell<bool>, // Checked on first use
+}
+
+impl Multicall {
+    pub fn new(address: Address, batch_size: usize) -> Self {
+        Self {
+            address,
+            batch_size: batch_size.max(1),
+            deployed: OnceCell::new(),
+        }
+    }
+
+    // Whether Multicall3 is deployed (errors are not cached, the check runs again)
+    async fn is_deployed(
+        &self,
+        provider: &RootProvider<FailoverTransport>,
+    ) -> Result<bool, ContractError> {
+        self.deployed
+            .get_or_try_init(|| async {
+                let code =
+                    metrics::observe_rpc("get_code_at", provider.get_code_at(self.address)).await?;
+                if code.is_empty() {
+                    warn!(
+                        "Multicall3 not deployed at {}, contract reads will not be batched",
+                        self.address
+                    );
+                } else {
+                    info!(
+                        "Batching contract reads with Multicall3 at {}",
+                        self.address
+                    );
+                }
+                Ok::<_, ContractError>(!code.is_empty())
+            })
+            .await
+            .copied()
+    }
+
+    /// Call a view function of `target` with several arguments, at the same block
+    #[instrument(name = "contract.multicall", skip(self, provider, calls), fields(method, calls = calls.len()))]
+    pub async fn read<C: SolCall>(
+        &self,
+        provider: &RootProvider<FailoverTransport>,
+        method: &'static str,
+        target: Address,
+        calls: Vec<C>,
+        block: BlockId,
+    ) -> Result<Vec<C::Return>, ContractError> {
+        Span::current().record("method", method);
+
+        let outputs = if self.is_deployed(provider).await? {
+            self.aggregate(provider, target, &calls, block).await?
+        } else {
+            sequential(provider, method, target, &calls, block).await?
+        };
+
+        outputs
+            .iter()
+            .map(|output| {
+                C::abi_decode_returns(output, true).map_err(|e| {
+                    ContractError::InvalidResponse(format!("unable to decode {}: {}", method, e))
+                })
+            })
+            .collect()
+    }
+
+    // Send the calls through `aggregate3`, in chunks of `batch_size`
+    async fn aggregate<C: SolCall>(
+        &self,
+        provider: &RootProvider<FailoverTransport>,
+        target: Address,
+        calls: &[C],
+        block: BlockId,
+    ) -> Result<Vec<Bytes>, ContractError> {
+        let multicall = IMulticall3::new(self.address, provider.clone());
+        let mut outputs = Vec::with_capacity(calls.len());
+
+        for chunk in calls.chunks(self.batch_size) {
+            let call3s = chunk
+                .iter()
+                .map(|call| IMulticall3::Call3 {
+                    target,
+                    allowFailure: true, // to report the revert reason of each call
+                    callData: call.abi_encode().into(),
+                })
+                .collect();
+
+            let results = metrics::observe_rpc(
+                "aggregate3",
+                multicall.aggregate3(call3s).block(block).call(),
+            )
+            .await?
+            .returnData;
+
+            for result in results {
+                if !result.success {
+                    return Err(ContractError::from_revert_data(&result.returnData)
+                        .unwrap_or_else(|| ContractError::Reverted("call failed".into())));
+                }
+                outputs.push(result.returnData);
+            }
+        }
+
+        Ok(outputs)
+    }
+}
+
+// Fallback: one `eth_call` per call
+async fn sequential<C: SolCall>(
+    provider: &RootProvider<FailoverTransport>,
+    method: &'static str,
+    target: Address,
+    calls: &[C],
+    block: BlockId,
+) -> Result<Vec<Bytes>, ContractError> {
+    let mut outputs = Vec::with_capacity(calls.len());
+    for call in calls {
+        let tx = TransactionRequest::default()
+            .to(target)
+            .input(call.abi_encode().into());
+        outputs.push(metrics::observe_rpc(method, provider.call(&tx).block(block)).await?);
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::U256,
+        sol_types::{Revert, SolError},
+    };
+
+    use super::*;
+    use crate::{
+        contracts::testing::{MockChain, CONTRACT, MULTICALL},
+        VOTECHAIN,
+    };
+
+    // Nonces are used when even, polls above 9 do not exist
+    fn nonces(input: &[u8]) -> Result<Vec<u8>, Vec<u8>> {
+        let call = VOTECHAIN::usedNoncesCall::abi_decode(input, true).unwrap();
+        if call._1 > U256::from(9) {
+            return Err(Revert::from("Poll does not exist").abi_encode());
+        }
+        let used = call._2 % U256::from(2) == U256::ZERO;
+        Ok(VOTECHAIN::usedNoncesCall::abi_encode_returns(&(used,)))
+    }
+
+    fn calls(poll_id: u64, count: u64) -> Vec<VOTECHAIN::usedNoncesCall> {
+        (0..count)
+            .map(|nonce| VOTECHAIN::usedNoncesCall {
+                _0: Address::repeat_byte(1),
+                _1: U256::from(poll_id),
+                _2: U256::from(nonce),
+            })
+            .collect()
+    }
+
+    async fn read(chain: &MockChain, poll_id: u64) -> Result<Vec<bool>, ContractError> {
+        let multicall = Multicall::new(MULTICALL, 2);
+        let block = BlockId::number(7);
+        let outputs = multicall
+            .read(
+                &chain.provider(),
+                "usedNonces",
+                CONTRACT,
+                calls(poll_id, 5),
+                block,
+            )
+            .await?;
+        Ok(outputs.into_iter().map(|output| output._0).collect())
+    }
+
+    #[actix_web::test]
+    async fn aggregates_calls_in_batches() {
+        let chain = MockChain::new(7, nonces);
+        let used = read(&chain, 1).await.unwrap();
+        assert_eq!(used, vec![true, false, true, false, true]);
+
+        // 5 calls in batches of 2, all at the requested block
+        let targets: Vec<_> = chain.calls().into_iter().map(|(to, _)| to).collect();
+        assert_eq!(targets, vec![MULTICALL; 3]);
+        assert!(chain.calls().iter().all(|(_, block)| block == "0x7"));
+    }
+
+    #[actix_web::test]
+    async fn falls_back_to_sequential_calls() {
+        let chain = MockChain::new(7, nonces).without_multicall();
+        let used = read(&chain, 1).await.unwrap();
+        assert_eq!(used, vec![true, false, true, false, true]);
+
+        let targets: Vec<_> = chain.calls().into_iter().map(|(to, _)| to).collect();
+        assert_eq!(targets, vec![CONTRACT; 5]);
+        assert!(chain.calls().iter().all(|(_, block)| block == "0x7"));
+    }
+
+    #[actix_web::test]
+    async fn reports_reverts_on_both_paths() {
+        for chain in [
+            MockChain::new(7, nonces),
+            MockChain::new(7, nonces).without_multicall(),
+        ] {
+            assert!(matches!(
+                read(&chain, 10).await,
+                Err(ContractError::UnknownPoll)
+            ));
+        }
+    }
+}
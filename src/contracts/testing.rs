@@ -0,0 +1,237 @@
+//! In-process chain answering the reads of a contract, to test the reading code without
+//! a node
+
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use alloy::{
+    network::EthereumWallet,
+    primitives::{address, hex, Address, Bytes, LogData, B256},
+    providers::{ProviderBuilder, RootProvider},
+    rpc::{
+        client::RpcClient,
+        json_rpc::{RequestPacket, ResponsePacket},
+    },
+    signers::local::PrivateKeySigner,
+    sol_types::SolCall,
+    transports::{Transport, TransportError, TransportErrorKind, TransportFut},
+};
+use serde_json::{json, Value};
+use tower::Service;
+
+use super::{
+    multicall::{IMulticall3, Multicall},
+    votechain::VotechainContract,
+};
+use crate::{rpc::FailoverTransport, VOTECHAIN};
+
+/// Address of the contract on the mock chain
+pub const CONTRACT: Address = address!("00000000000000000000000000000000000000c0");
+
+/// Address of Multicall3 on the mock chain
+pub const MULTICALL: Address = address!("00000000000000000000000000000000000000ca");
+
+// Answers a call of the contract with its return data, or its revert data
+type Handler = dyn Fn(&[u8]) -> Result<Vec<u8>, Vec<u8>> + Send + Sync;
+
+/// Chain at a fixed block, with the contract answered by a handler. Multicall3 is deployed
+/// unless disabled: it runs the calls it aggregates against the handler, as the real one
+/// would. Every `eth_call` is recorded with its target and its block
+#[derive(Clone)]
+pub struct MockChain {
+    block: u64,
+    multicall: bool,
+    handler: Arc<Handler>,
+    calls: Arc<Mutex<Vec<(Address, Value)>>>,
+    logs: Arc<Vec<(Address, LogData)>>,
+}
+
+impl MockChain {
+    pub fn new(
+        block: u64,
+        handler: impl Fn(&[u8]) -> Result<Vec<u8>, Vec<u8>> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            block,
+            multicall: true,
+            handler: Arc::new(handler),
+            calls: Arc::default(),
+            logs: Arc::default(),
+        }
+    }
+
+    /// Chain where every transaction is mined successfully at its block, emitting `logs`
+    pub fn with_logs(mut self, logs: Vec<(Address, LogData)>) -> Self {
+        self.logs = Arc::new(logs);
+        self
+    }
+
+    /// Chain without Multicall3
+    pub fn without_multicall(mut self) -> Self {
+        self.multicall = false;
+        self
+    }
+
+    /// Target and block of every `eth_call` so far
+    pub fn calls(&self) -> Vec<(Address, Value)> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Read-only provider of this chain
+    pub fn provider(&self) -> RootProvider<FailoverTransport> {
+        RootProvider::new(RpcClient::new(self.transport(), false))
+    }
+
+    /// VoteChain contract read through this chain
+    pub fn votechain(&self) -> VotechainContract {
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(EthereumWallet::from(PrivateKeySigner::random()))
+            .on_client(RpcClient::new(self.transport(), false));
+
+        VotechainContract::new(
+            VOTECHAIN::new(CONTRACT, provider),
+            VOTECHAIN::new(CONTRACT, self.provider()),
+            None,
+            0,
+            Multicall::new(MULTICALL, 100),
+        )
+    }
+
+    fn transport(&self) -> FailoverTransport {
+        FailoverTransport::single(self.clone().boxed())
+    }
+
+    // Result of a request, or its JSON-RPC error
+    fn answer(&self, method: &str, params: Value) -> Result<Value, Value> {
+        match method {
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_blockNumber" => Ok(json!(format!("0x{:x}", self.block))),
+            "eth_getCode" => {
+                let deployed = match params[0].as_str().and_then(|a| a.parse().ok()) {
+                    Some(CONTRACT) => true,
+                    Some(MULTICALL) => self.multicall,
+                    _ => false,
+                };
+                Ok(json!(if deployed { "0x6080" } else { "0x" }))
+            }
+            "eth_call" => {
+                let tx = &params[0];
+                let to: Address = tx["to"]
+                    .as_str()
+                    .and_then(|a| a.parse().ok())
+                    .unwrap_or_default();
+                let input = tx["input"].as_str().or(tx["data"].as_str()).unwrap_or("0x");
+                let input = hex::decode(input).unwrap_or_default();
+                self.calls.lock().unwrap().push((to, params[1].clone()));
+
+                let output = match to {
+                    CONTRACT => (self.handler)(&input),
+                    MULTICALL if self.multicall => Ok(self.aggregate3(&input)),
+                    _ => Ok(Vec::new()),
+                };
+                match output {
+                    Ok(output) => Ok(json!(Bytes::from(output))),
+                    Err(revert) => Err(json!({
+                        "code": 3,
+                        "message": "execution reverted",
+                        "data": Bytes::from(revert),
+                    })),
+                }
+            }
+            "eth_getTransactionReceipt" => Ok(self.receipt(&params[0])),
+            _ => Err(json!({"code": -32601, "message": "method not found"})),
+        }
+    }
+
+    // Receipt of a transaction mined at the block of the chain, with its logs
+    fn receipt(&self, tx_hash: &Value) -> Value {
+        let block_hash = B256::repeat_byte(0xbb);
+        let block_number = format!("0x{:x}", self.block);
+        let logs: Vec<_> = self
+            .logs
+            .iter()
+            .enumerate()
+            .map(|(i, (address, data))| {
+                json!({
+                    "address": address,
+                    "topics": data.topics(),
+                    "data": data.data,
+                    "blockHash": block_hash,
+                    "blockNumber": block_number,
+                    "transactionHash": tx_hash,
+                    "transactionIndex": "0x0",
+                    "logIndex": format!("0x{:x}", i),
+                    "removed": false,
+                })
+            })
+            .collect();
+        json!({
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": block_hash,
+            "blockNumber": block_number,
+            "from": Address::ZERO,
+            "to": CONTRACT,
+            "contractAddress": null,
+            "cumulativeGasUsed": "0x5208",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x1",
+            "logs": logs,
+            "logsBloom": Bytes::from(vec![0; 256]),
+            "type": "0x2",
+            "status": "0x1",
+        })
+    }
+
+    // Run the calls of an `aggregate3` against the handler
+    fn aggregate3(&self, input: &[u8]) -> Vec<u8> {
+        let calls = IMulticall3::aggregate3Call::abi_decode(input, true)
+            .expect("invalid aggregate3 call")
+            .calls;
+        let results: Vec<_> = calls
+            .into_iter()
+            .map(|call| {
+                let output = match call.target {
+                    CONTRACT => (self.handler)(&call.callData),
+                    _ => Ok(Vec::new()),
+                };
+                let success = output.is_ok();
+                IMulticall3::Call3Result {
+                    success,
+                    returnData: output.unwrap_or_else(|revert| revert).into(),
+                }
+            })
+            .collect();
+        IMulticall3::aggregate3Call::abi_encode_returns(&(results,))
+    }
+}
+
+impl Service<RequestPacket> for MockChain {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let RequestPacket::Single(req) = req else {
+            return Box::pin(async {
+                Err(TransportErrorKind::custom_str("batches are not supported"))
+            });
+        };
+        let params = req
+            .params()
+            .and_then(|params| serde_json::from_str(params.get()).ok())
+            .unwrap_or(Value::Null);
+        let body = match self.answer(req.method(), params) {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": req.id(), "result": result}),
+            Err(error) => json!({"jsonrpc": "2.0", "id": req.id(), "error": error}),
+        };
+        Box::pin(async move { Ok(serde_json::from_value(body).unwrap()) })
+    }
+}
@@ -0,0 +1,35 @@
+use alloy::{
+    primitives::{Address, U256},
+    providers::RootProvider,
+    rpc::types::BlockId,
+    sol,
+};
+use tracing::instrument;
+
+use super::errors::ContractError;
+use crate::{metrics, rpc::FailoverTransport};
+
+sol! {
+    #[sol(rpc)]
+    interface IBalanceOf {
+        // Same signature in ERC-20 (amount) and ERC-721 (number of tokens)
+        function balanceOf(address owner) external view returns (uint256);
+    }
+}
+
+/// Balance of `owner` in an ERC-20 / ERC-721 `token`, at a past block
+#[instrument(name = "contract.balance_of", skip(provider))]
+pub async fn balance_of(
+    provider: &RootProvider<FailoverTransport>,
+    token: Address,
+    owner: Address,
+    block: u64,
+) -> Result<U256, ContractError> {
+    let token = IBalanceOf::new(token, provider.clone());
+    let balance = metrics::observe_rpc(
+        "balance_of",
+        token.balanceOf(owner).block(BlockId::number(block)).call(),
+    )
+    .await?;
+    Ok(balance._0)
+}
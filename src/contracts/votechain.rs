@@ -1,58 +1,158 @@
-use alloy::primitives::{address, ruint::aliases::U256};
-use log::info;
-use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
 
-use crate::{VotechainContractInstance, VOTECHAIN::pollsReturn};
+use alloy::{
+    consensus::Transaction as _,
+    network::Ethereum,
+    primitives::{ruint::aliases::U256, Address, TxHash, B256},
+    providers::{PendingTransactionBuilder, Provider, WalletProvider},
+    pubsub::Subscription,
+    rpc::types::{
+        BlockId, BlockNumberOrTag, BlockTransactionsKind, Filter, Log, TransactionReceipt,
+    },
+    sol_types::{SolCall, SolEvent},
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tracing::{instrument, Span};
+
+use super::{errors::ContractError, multicall::Multicall, token};
+use crate::{
+    metadata, metrics,
+    relay::{RelayGuard, RelayTracker},
+    rpc::{FailoverTransport, WsEndpoint},
+    timestamp::Timestamp,
+    VotechainContractInstance, VotechainReaderInstance, VOTECHAIN,
+    VOTECHAIN::pollsReturn,
+};
 
 pub struct VotechainContract {
     contract: VotechainContractInstance,
+    reader: VotechainReaderInstance, // Critical reads, may require a quorum of nodes
+    ws: Option<WsEndpoint>,          // Subscriptions, when RPC_WS_URL is set
+    relay: RelayTracker,             // Relayed transactions not confirmed yet
+    deploy_block: u64,               // First block searched for the contract events
+    options: Mutex<HashMap<U256, Vec<String>>>, // Options of the polls, immutable once created
+    multicall: Multicall,            // Batches reads of the contract
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Poll {
+    pub id: U256,
     pub name: String,
     pub description: String,
     // pub options: Vec<String>,
-    pub start_time: U256,
-    pub end_time: U256,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
     pub winner: String,
     pub is_ended: bool,
+    pub owner: Address,
+    pub metadata_hash: Option<B256>, // Hash of the off-chain metadata, committed in the description
 }
 
-// Implement conversion from `pollsReturn` to `Poll`
-impl From<pollsReturn> for Poll {
-    fn from(poll: pollsReturn) -> Self {
-        Self {
+// Implement conversion from `pollsReturn` to `Poll`. Times that do not fit a timestamp
+// are reported instead of truncated
+impl TryFrom<pollsReturn> for Poll {
+    type Error = ContractError;
+
+    fn try_from(poll: pollsReturn) -> Result<Self, Self::Error> {
+        let timestamp = |name: &str, value: U256| {
+            Timestamp::try_from(value).map_err(|e| {
+                ContractError::InvalidResponse(format!("{} of poll {}: {}", name, poll.id, e))
+            })
+        };
+
+        let (description, metadata_hash) = metadata::split(&poll.description);
+
+        Ok(Self {
+            id: poll.id,
+            start_time: timestamp("start_time", poll.start_time)?,
+            end_time: timestamp("end_time", poll.end_time)?,
             name: poll.name,
-            description: poll.description,
+            description,
             // options: poll.options,
-            start_time: poll.start_time,
-            end_time: poll.end_time,
             winner: poll.winner,
             is_ended: poll.is_ended,
-        }
+            owner: poll.owner,
+            metadata_hash,
+        })
     }
 }
 
+/// Number of votes received by an option
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Tally {
+    pub option: String,
+    pub votes: U256,
+}
+
+/// Tallies of a poll read at a single block
+#[derive(Serialize, Deserialize)]
+pub struct PollResults {
+    pub block_number: u64,
+    pub poll: Poll,
+    pub tallies: Vec<Tally>,
+    pub winner: Option<String>, // Set once the poll is finalized
+}
+
+/// Vote relayed on-chain, located by its `VoteCast` event
+#[derive(Serialize, Deserialize)]
+pub struct RelayedVote {
+    pub transaction_hash: TxHash,
+    pub block_number: u64,
+    pub log_index: u64, // Index of the event in the block
+    pub poll_id: U256,
+    pub option: String,
+    #[serde(default)]
+    pub contract_receipt: Option<String>, // Of the `VoteReceiptSent` event of the vote
+}
+
 #[derive(Serialize)]
 pub struct PollRecipt {
-    poll_id: U256,
+    pub poll_id: U256,
+    pub transaction_hash: TxHash,
 }
 
 impl VotechainContract {
-    pub fn new(instance: VotechainContractInstance) -> Self {
+    pub fn new(
+        instance: VotechainContractInstance,
+        reader: VotechainReaderInstance,
+        ws: Option<WsEndpoint>,
+        deploy_block: u64,
+        multicall: Multicall,
+    ) -> Self {
         // Create a new instance of the contract
-        Self { contract: instance }
+        Self {
+            contract: instance,
+            reader,
+            ws,
+            relay: RelayTracker::default(),
+            deploy_block,
+            options: Mutex::new(HashMap::new()),
+            multicall,
+        }
     }
 
+    /// Tracker of the relayed transactions, drained on shutdown
+    pub fn relay(&self) -> &RelayTracker {
+        &self.relay
+    }
+
+    #[instrument(
+        name = "contract.create_poll",
+        skip_all,
+        fields(contract = %self.contract.address(), from = %self.relay_address(), poll_id, tx_hash)
+    )]
     pub async fn create_poll(
         &self,
         name: String,
         description: String,
         options: Vec<String>,
-        start_time: U256,
-        end_time: U256,
-    ) -> Result<PollRecipt, String> {
+        start_time: Timestamp,
+        end_time: Timestamp,
+    ) -> Result<PollRecipt, ContractError> {
         // Print some debug info about the poll
         info!(
             "Poll data: {:?}",
@@ -66,108 +166,676 @@ impl VotechainContract {
         );
 
         info!("Creating poll: {}", name);
-        let poll_id = self
-            .contract
-            .create_poll(name, description, options, start_time, end_time)
-            .call()
-            .await
-            .expect("Failed to create poll")
-            ._0;
-        info!("Resulting ID: {}", poll_id.clone());
-
-        // .into_transaction_request();
-        // Use our provider to send the transaction
-        //
-        // // Get wallet information from the config file
-        // let srv_wallet_key = std::env::var("RELAY_WALLET_PRIVATE_KEY").unwrap();
-        //
-        // // Create signer based on private key
-        // let signer: PrivateKeySigner = srv_wallet_key.parse().expect("Invalid private key");
-        // let wallet = EthereumWallet::new(signer);
-        //
-        // // create link with available contracts + connect to blockchain
-        // let rpc_url = Url::parse(&std::env::var("RPC_URL").unwrap()).unwrap();
-        //
-        // // create provider + link the wallet for signing
-        // let provider = ProviderBuilder::new()
-        //     .with_recommended_fillers()
-        //     .wallet(wallet)
-        //     .on_http(rpc_url);
-        //
-        // let tx = provider.fill(tx).await.expect("Failed to fill transaction");
-        //
-        // let result = provider
-        //     .send_tx_envelope(tx.as_envelope().expect("Failed to create envelope").clone())
-        //     .await;
+        let guard = self.relay.begin()?;
+        let pending = metrics::observe_rpc(
+            "create_poll",
+            self.contract
+                .create_poll(
+                    name,
+                    description,
+                    options,
+                    start_time.to_u256(),
+                    end_time.to_u256(),
+                )
+                .send(),
+        )
+        .await;
+        let receipt = confirm("create_poll", guard, pending).await?;
+
+        // Extract the ID of the new poll from the emitted event
+        let poll_id = created_poll_id(&receipt)
+            .ok_or_else(|| ContractError::InvalidResponse("missing PollCreated event".into()))?;
+        info!("Resulting ID: {}", poll_id);
+        Span::current().record("poll_id", poll_id.to_string());
 
         Ok(PollRecipt {
-            poll_id, // transaction_hash: tx_address.to_string(),
+            poll_id,
+            transaction_hash: receipt.transaction_hash,
         })
     }
 
-    pub async fn get_poll(&self, id: U256) -> Result<Poll, String> {
+    /// Poll at the current head. The block is pinned so that quorum reads compare the same
+    /// state on every node
+    pub async fn get_poll(&self, id: U256) -> Result<Poll, ContractError> {
+        let block = BlockId::number(self.block_number().await?);
+        self.get_poll_at(id, block).await
+    }
+
+    #[instrument(name = "contract.polls", skip(self), fields(contract = %self.contract.address(), poll_id = %id))]
+    async fn get_poll_at(&self, id: U256, block: BlockId) -> Result<Poll, ContractError> {
+        let poll = metrics::observe_rpc("polls", self.reader.polls(id).block(block).call()).await?;
+
+        // Unknown ids of a mapping are returned as zeroed structs
+        if poll.owner == Address::ZERO && poll.name.is_empty() {
+            return Err(ContractError::UnknownPoll);
+        }
+
+        poll.try_into()
+    }
+
+    #[instrument(
+        name = "contract.cast_vote",
+        skip(self, option),
+        fields(contract = %self.contract.address(), from = %self.relay_address(), poll_id = %poll_id, tx_hash)
+    )]
+    pub async fn cast_vote(
+        &self,
+        poll_id: U256,
+        option: String,
+    ) -> Result<RelayedVote, ContractError> {
+        let guard = self.relay.begin()?;
+        let pending =
+            metrics::observe_rpc("cast_vote", self.contract.cast_vote(poll_id, option).send())
+                .await;
+        let receipt = confirm("cast_vote", guard, pending).await?;
+        self.relayed_vote(&receipt)
+            .ok_or_else(|| ContractError::InvalidResponse("missing VoteCast event".into()))
+    }
+
+    /// Vote cast by a mined transaction, `None` when it is unknown, failed or cast no vote
+    /// in this contract. Receipts are not read with a quorum: nodes that have not seen the
+    /// block yet would disagree
+    #[instrument(name = "rpc.get_transaction_receipt", skip(self), fields(tx_hash = %tx_hash))]
+    pub async fn get_relayed_vote(
+        &self,
+        tx_hash: TxHash,
+    ) -> Result<Option<RelayedVote>, ContractError> {
+        let receipt = metrics::observe_rpc(
+            "get_transaction_receipt",
+            self.contract.provider().get_transaction_receipt(tx_hash),
+        )
+        .await?;
+        Ok(receipt
+            .filter(|receipt| receipt.status())
+            .and_then(|receipt| self.relayed_vote(&receipt)))
+    }
+
+    // Position and content of the `VoteCast` event emitted by this contract in a receipt,
+    // with the receipt of the `VoteReceiptSent` event emitted for the same poll
+    fn relayed_vote(&self, receipt: &TransactionReceipt) -> Option<RelayedVote> {
+        let logs = || {
+            receipt
+                .inner
+                .logs()
+                .iter()
+                .filter(|log| log.address() == *self.contract.address())
+        };
+        let mut vote = logs().find_map(|log| {
+            let vote = log.log_decode::<VOTECHAIN::VoteCast>().ok()?;
+            Some(RelayedVote {
+                transaction_hash: receipt.transaction_hash,
+                block_number: log.block_number.or(receipt.block_number)?,
+                log_index: log.log_index?,
+                poll_id: vote.inner.data.poll_id,
+                option: vote.inner.data.option,
+                contract_receipt: None,
+            })
+        })?;
+        vote.contract_receipt = logs().find_map(|log| {
+            let sent = log
+                .log_decode::<VOTECHAIN::VoteReceiptSent>()
+                .ok()?
+                .inner
+                .data;
+            (sent.poll_id == vote.poll_id).then_some(sent.receipt)
+        });
+        Some(vote)
+    }
+
+    /// Options of a poll. They are not stored by the contract, so they are decoded from the
+    /// `create_poll` transaction that emitted the `PollCreated` event of the poll
+    #[instrument(name = "contract.get_poll_options", skip(self), fields(contract = %self.contract.address(), poll_id = %poll_id))]
+    pub async fn get_poll_options(&self, poll_id: U256) -> Result<Vec<String>, ContractError> {
+        if let Some(options) = self
+            .options
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&poll_id)
+        {
+            return Ok(options.clone());
+        }
+
+        let provider = self.reader.provider();
+        let filter = Filter::new()
+            .address(*self.contract.address())
+            .event_signature(VOTECHAIN::PollCreated::SIGNATURE_HASH)
+            .topic1(B256::from(poll_id))
+            .from_block(self.deploy_block);
+        let logs = metrics::observe_rpc("get_logs", provider.get_logs(&filter)).await?;
+        let tx_hash = logs
+            .first()
+            .and_then(|log| log.transaction_hash)
+            .ok_or(ContractError::UnknownPoll)?;
+
+        let tx = metrics::observe_rpc(
+            "get_transaction_by_hash",
+            provider.get_transaction_by_hash(tx_hash),
+        )
+        .await?
+        .ok_or_else(|| {
+            ContractError::InvalidResponse(format!("transaction {} not found", tx_hash))
+        })?;
+
+        // NOTE: polls created through another contract (e.g. a multisig) cannot be decoded
+        let call = VOTECHAIN::create_pollCall::abi_decode(tx.input(), true).map_err(|e| {
+            ContractError::InvalidResponse(format!("unable to decode poll options: {}", e))
+        })?;
+
+        self.options
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(poll_id, call._options.clone());
+        Ok(call._options)
+    }
+
+    /// Votes received by every option of a poll
+    pub async fn get_tallies(&self, poll_id: U256) -> Result<Vec<Tally>, ContractError> {
+        let block = self.block_number().await?;
+        self.get_tallies_at(poll_id, block).await
+    }
+
+    // Votes received by every option of a poll at a given block, read in a single multicall
+    async fn get_tallies_at(&self, poll_id: U256, block: u64) -> Result<Vec<Tally>, ContractError> {
+        let options = self.get_poll_options(poll_id).await?;
+        let calls = options
+            .iter()
+            .map(|option| VOTECHAIN::get_votesCall {
+                poll_id,
+                option: option.clone(),
+            })
+            .collect();
+        let votes = self
+            .multicall
+            .read(
+                self.reader.provider(),
+                "get_votes",
+                *self.reader.address(),
+                calls,
+                BlockId::number(block),
+            )
+            .await?;
+
+        Ok(options
+            .into_iter()
+            .zip(votes)
+            .map(|(option, votes)| Tally {
+                option,
+                votes: votes._0,
+            })
+            .collect())
+    }
+
+    /// Whether each nonce of `voter` in a poll was used, all read at the latest block and
+    /// aggregated in a few calls
+    #[instrument(name = "contract.used_nonces", skip(self, nonces), fields(contract = %self.contract.address(), poll_id = %poll_id, count = nonces.len()))]
+    pub async fn used_nonces(
+        &self,
+        voter: Address,
+        poll_id: U256,
+        nonces: &[U256],
+    ) -> Result<Vec<bool>, ContractError> {
+        let block = BlockId::number(self.block_number().await?);
+        let calls = nonces
+            .iter()
+            .map(|nonce| VOTECHAIN::usedNoncesCall {
+                _0: voter,
+                _1: poll_id,
+                _2: *nonce,
+            })
+            .collect();
+        let used = self
+            .multicall
+            .read(
+                self.reader.provider(),
+                "usedNonces",
+                *self.reader.address(),
+                calls,
+                block,
+            )
+            .await?;
+        Ok(used.into_iter().map(|used| used._0).collect())
+    }
+
+    /// State, tallies and winner (once finalized) of a poll, all read at the same block
+    #[instrument(name = "contract.get_results", skip(self), fields(contract = %self.contract.address(), poll_id = %poll_id, block))]
+    pub async fn get_results(&self, poll_id: U256) -> Result<PollResults, ContractError> {
+        let block_number = self.block_number().await?;
+        Span::current().record("block", block_number);
+
         let poll = self
-            .contract
-            .polls(id)
-            .call()
-            .await
-            .expect("Failed to get poll");
-        Ok(poll.into())
+            .get_poll_at(poll_id, BlockId::number(block_number))
+            .await?;
+        let tallies = self.get_tallies_at(poll_id, block_number).await?;
+
+        let winner = if poll.is_ended && !poll.winner.is_empty() {
+            let winner = metrics::observe_rpc(
+                "get_winner",
+                self.reader
+                    .get_winner(poll_id)
+                    .block(BlockId::number(block_number))
+                    .call(),
+            )
+            .await?;
+            Some(winner._0)
+        } else {
+            None
+        };
+
+        Ok(PollResults {
+            block_number,
+            poll,
+            tallies,
+            winner,
+        })
+    }
+
+    /// Events emitted by the contract between two blocks (inclusive)
+    #[instrument(name = "rpc.get_logs", skip(self), fields(contract = %self.contract.address()))]
+    pub async fn get_events(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<Log>, ContractError> {
+        let filter = Filter::new()
+            .address(*self.contract.address())
+            .from_block(from_block)
+            .to_block(to_block);
+        Ok(metrics::observe_rpc("get_logs", self.contract.provider().get_logs(&filter)).await?)
     }
 
-    pub async fn cast_vote(&self, poll_id: U256, option: String) -> Result<(), String> {
-        let result = self.contract.cast_vote(poll_id, option).call().await;
+    /// Subscribe to the events emitted by the contract, `None` without WebSocket endpoint.
+    /// A failed subscription drops the connection, the next one connects again
+    pub async fn subscribe_events(&self) -> Option<Result<Subscription<Log>, ContractError>> {
+        let ws = self.ws.as_ref()?;
+        let filter = Filter::new().address(*self.contract.address());
+        let subscription = match ws.provider().await {
+            Ok(provider) => provider.subscribe_logs(&filter).await,
+            Err(e) => Err(e),
+        };
+        if subscription.is_err() {
+            ws.reset().await;
+        }
+        Some(subscription.map_err(ContractError::from))
+    }
 
-        match result {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e.to_string()),
+    /// Drop the WebSocket connection once its subscription closed, the next subscription
+    /// connects again
+    pub async fn reset_subscriptions(&self) {
+        if let Some(ws) = &self.ws {
+            ws.reset().await;
         }
     }
 
-    pub async fn get_available_polls(&self) -> Result<Vec<Poll>, String> {
+    /// Token balance of `owner` at a snapshot block (ERC-20 / ERC-721 `balanceOf`)
+    pub async fn token_balance(
+        &self,
+        token: Address,
+        owner: Address,
+        block: u64,
+    ) -> Result<U256, ContractError> {
+        token::balance_of(self.reader.provider(), token, owner, block).await
+    }
+
+    /// Number of the latest block
+    #[instrument(name = "rpc.get_block_number", skip(self))]
+    pub async fn block_number(&self) -> Result<u64, ContractError> {
+        let provider = self.contract.provider();
+        Ok(metrics::observe_rpc("get_block_number", provider.get_block_number()).await?)
+    }
+
+    /// Close a poll to new votes
+    #[instrument(
+        name = "contract.end_poll",
+        skip(self),
+        fields(contract = %self.contract.address(), from = %self.relay_address(), poll_id = %poll_id, tx_hash)
+    )]
+    pub async fn end_poll(&self, poll_id: U256) -> Result<TxHash, ContractError> {
+        let guard = self.relay.begin()?;
+        let pending =
+            metrics::observe_rpc("end_poll", self.contract.end_poll(poll_id).send()).await;
+        let receipt = confirm("end_poll", guard, pending).await?;
+        Ok(receipt.transaction_hash)
+    }
+
+    /// Compute and store the winner of an ended poll
+    #[instrument(
+        name = "contract.finalize_poll",
+        skip(self),
+        fields(contract = %self.contract.address(), from = %self.relay_address(), poll_id = %poll_id, tx_hash)
+    )]
+    pub async fn finalize_poll(&self, poll_id: U256) -> Result<TxHash, ContractError> {
+        let guard = self.relay.begin()?;
+        let pending =
+            metrics::observe_rpc("finalize_poll", self.contract.finalize_poll(poll_id).send())
+                .await;
+        let receipt = confirm("finalize_poll", guard, pending).await?;
+        Ok(receipt.transaction_hash)
+    }
+
+    /// Winning option of a finalized poll
+    #[instrument(name = "contract.get_winner", skip(self), fields(contract = %self.contract.address(), poll_id = %poll_id))]
+    pub async fn get_winner(&self, poll_id: U256) -> Result<String, ContractError> {
+        let winner =
+            metrics::observe_rpc("get_winner", self.reader.get_winner(poll_id).call()).await?;
+        Ok(winner._0)
+    }
+
+    /// Address of the relay wallet, signing and paying for all relayed transactions
+    pub fn relay_address(&self) -> Address {
+        self.contract.provider().default_signer_address()
+    }
+
+    /// Current balance of the relay wallet, paying for all relayed transactions
+    #[instrument(name = "contract.relay_balance", skip(self), fields(address = %self.relay_address()))]
+    pub async fn relay_balance(&self) -> Result<U256, ContractError> {
+        let provider = self.contract.provider();
+        let balance =
+            metrics::observe_rpc("get_balance", provider.get_balance(self.relay_address())).await?;
+        Ok(balance)
+    }
+
+    /// Chain id reported by the RPC node
+    #[instrument(name = "rpc.get_chain_id", skip(self))]
+    pub async fn chain_id(&self) -> Result<u64, ContractError> {
+        let provider = self.contract.provider();
+        Ok(metrics::observe_rpc("get_chain_id", provider.get_chain_id()).await?)
+    }
+
+    /// Number and timestamp (seconds since epoch) of the latest block
+    #[instrument(name = "rpc.get_latest_block", skip(self))]
+    pub async fn latest_block(&self) -> Result<(u64, u64), ContractError> {
+        let provider = self.contract.provider();
+        let block = metrics::observe_rpc(
+            "get_block_by_number",
+            provider.get_block_by_number(BlockNumberOrTag::Latest, BlockTransactionsKind::Hashes),
+        )
+        .await?
+        .ok_or_else(|| ContractError::InvalidResponse("latest block not found".into()))?;
+
+        Ok((block.header.number, block.header.timestamp))
+    }
+
+    /// Whether a WebSocket endpoint is configured for subscriptions
+    pub fn has_subscriptions(&self) -> bool {
+        self.ws.is_some()
+    }
+
+    /// Latest block number seen by the WebSocket endpoint
+    #[instrument(name = "rpc.ws_block_number", skip(self))]
+    pub async fn ws_block_number(&self) -> Result<u64, ContractError> {
+        let ws = self
+            .ws
+            .as_ref()
+            .ok_or_else(|| ContractError::Rpc("no WebSocket endpoint configured".into()))?;
+        let provider = ws.provider().await?;
+        let result = metrics::observe_rpc("ws_get_block_number", provider.get_block_number()).await;
+        if result.is_err() {
+            ws.reset().await;
+        }
+        Ok(result?)
+    }
+
+    /// Outcome of a mined transaction (true on success), `None` when not mined yet
+    #[instrument(name = "rpc.get_transaction_receipt", skip(self), fields(tx_hash = %tx_hash))]
+    pub async fn transaction_status(&self, tx_hash: TxHash) -> Result<Option<bool>, ContractError> {
+        let provider = self.contract.provider();
+        let receipt = metrics::observe_rpc(
+            "get_transaction_receipt",
+            provider.get_transaction_receipt(tx_hash),
+        )
+        .await?;
+        Ok(receipt.map(|receipt| receipt.status()))
+    }
+
+    /// Poll created by a mined transaction, `None` when it is unknown, failed or created
+    /// no poll
+    #[instrument(name = "rpc.get_transaction_receipt", skip(self), fields(tx_hash = %tx_hash))]
+    pub async fn get_created_poll(&self, tx_hash: TxHash) -> Result<Option<U256>, ContractError> {
+        let receipt = metrics::observe_rpc(
+            "get_transaction_receipt",
+            self.contract.provider().get_transaction_receipt(tx_hash),
+        )
+        .await?;
+        Ok(receipt
+            .filter(|receipt| receipt.status())
+            .and_then(|receipt| created_poll_id(&receipt)))
+    }
+
+    /// Whether contract code is deployed at the configured address
+    #[instrument(name = "rpc.get_code_at", skip(self), fields(contract = %self.contract.address()))]
+    pub async fn is_deployed(&self) -> Result<bool, ContractError> {
+        let provider = self.contract.provider();
+        let code = metrics::observe_rpc(
+            "get_code_at",
+            provider.get_code_at(*self.contract.address()),
+        )
+        .await?;
+        Ok(!code.is_empty())
+    }
+
+    #[instrument(name = "contract.get_available_polls", skip(self), fields(contract = %self.contract.address(), count))]
+    pub async fn get_available_polls(&self) -> Result<Vec<Poll>, ContractError> {
+        // Read the count and the polls at the same block, polls created meanwhile are skipped
+        let block = BlockId::number(self.block_number().await?);
+
         // Get total number of polls
-        let wrapped_count = self
-            .contract
-            .poll_count()
-            .call()
-            .await
-            .expect("Failed to get poll count")
-            ._0;
+        let wrapped_count =
+            metrics::observe_rpc("poll_count", self.reader.poll_count().block(block).call())
+                .await?
+                ._0;
 
         info!("Wrapped count: {:?}", wrapped_count);
 
         // convert to integer number
-        let count: u64 = wrapped_count
-            .try_into()
-            .expect("Failed to convert U256 to integer. Value is too large!");
+        let count: u64 = wrapped_count.try_into().map_err(|_| {
+            ContractError::InvalidResponse(format!("poll count too large: {}", wrapped_count))
+        })?;
 
         info!("Total number of polls: {}", count);
+        Span::current().record("count", count);
 
-        // Initialize vector to store poll data
-        let mut polls = Vec::new();
+        // Get the data of all available polls, aggregated in a few calls
+        let calls = (0..count)
+            .map(|i| VOTECHAIN::pollsCall { _0: U256::from(i) })
+            .collect();
+        let polls = self
+            .multicall
+            .read(
+                self.reader.provider(),
+                "polls",
+                *self.reader.address(),
+                calls,
+                block,
+            )
+            .await?;
 
-        // Iterate over all available polls
-        for i in 0..count {
-            // Get poll data
-            let poll = self
-                .contract
-                .polls(i.try_into().expect("Invaid cast"))
-                .call()
-                .await;
-            // Check if poll data is valid
-            match poll {
-                Ok(poll) => {
-                    // Append poll data to vector
-                    polls.push(poll.into());
-                }
-                Err(e) => {
-                    // Return error if poll data is invalid
-                    return Err(e.to_string());
-                }
+        // Return vector of poll data. A poll whose times cannot be represented must not hide
+        // all the others, it is left out
+        Ok(polls
+            .into_iter()
+            .filter_map(|poll| {
+                Poll::try_from(poll)
+                    .inspect_err(|e| warn!("Skipping poll from the list: {}", e))
+                    .ok()
+            })
+            .collect())
+    }
+}
+
+// Id of the poll announced by the `PollCreated` event of a receipt
+fn created_poll_id(receipt: &TransactionReceipt) -> Option<U256> {
+    receipt
+        .inner
+        .logs()
+        .iter()
+        .find_map(|log| log.log_decode::<VOTECHAIN::PollCreated>().ok())
+        .map(|log| log.inner.data.poll_id)
+}
+
+// Wait for a relayed transaction to be mined and record its outcome. The guard keeps the
+// transaction tracked until then
+async fn confirm(
+    method: &'static str,
+    mut guard: RelayGuard<'_>,
+    pending: Result<PendingTransactionBuilder<FailoverTransport, Ethereum>, alloy::contract::Error>,
+) -> Result<TransactionReceipt, ContractError> {
+    let receipt = match pending {
+        Ok(pending) => {
+            let tx_hash = *pending.tx_hash();
+            guard.submitted(tx_hash, method);
+            // Sent: the transaction may still be mined, callers must not assume it failed.
+            // Shutdown stops waiting after its drain timeout, callers then record it
+            let receipt = tokio::select! {
+                receipt = pending.get_receipt() => receipt.map_err(|e| e.to_string()),
+                _ = guard.abandoned() => Err("server shut down before it was mined".to_owned()),
+            };
+            receipt.map_err(|reason| {
+                metrics::record_relay_failure(method);
+                ContractError::Unconfirmed { tx_hash, reason }
+            })?
+        }
+        Err(e) => {
+            metrics::record_relay_failure(method);
+            return Err(e.into());
+        }
+    };
+
+    Span::current().record("tx_hash", receipt.transaction_hash.to_string());
+    metrics::record_relay(method, &receipt);
+    if !receipt.status() {
+        warn!(
+            "Relayed transaction {} ({}) reverted",
+            receipt.transaction_hash, method
+        );
+        return Err(ContractError::Reverted("transaction reverted".into()));
+    }
+    Ok(receipt)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::contracts::testing::{MockChain, CONTRACT, MULTICALL};
+
+    // Ended and finalized poll 1, with 3 votes for "Pizza" and 1 for "Sushi"
+    fn finalized_poll(input: &[u8]) -> Result<Vec<u8>, Vec<u8>> {
+        let selector: [u8; 4] = input[..4].try_into().unwrap();
+        Ok(match selector {
+            VOTECHAIN::pollsCall::SELECTOR => VOTECHAIN::pollsCall::abi_encode_returns(&(
+                U256::from(1),
+                "Lunch".to_owned(),
+                String::new(),
+                U256::from(1_000),
+                U256::from(2_000),
+                "Pizza".to_owned(),
+                true,
+                Address::repeat_byte(1),
+            )),
+            VOTECHAIN::get_votesCall::SELECTOR => {
+                let call = VOTECHAIN::get_votesCall::abi_decode(input, true).unwrap();
+                let votes = if call.option == "Pizza" { 3 } else { 1 };
+                VOTECHAIN::get_votesCall::abi_encode_returns(&(U256::from(votes),))
+            }
+            VOTECHAIN::get_winnerCall::SELECTOR => {
+                VOTECHAIN::get_winnerCall::abi_encode_returns(&("Pizza".to_owned(),))
+            }
+            _ => return Err(Vec::new()),
+        })
+    }
+
+    fn contract(chain: &MockChain) -> VotechainContract {
+        let contract = chain.votechain();
+        contract
+            .options
+            .lock()
+            .unwrap()
+            .insert(U256::from(1), vec!["Pizza".to_owned(), "Sushi".to_owned()]);
+        contract
+    }
+
+    #[actix_web::test]
+    async fn results_are_read_at_a_single_block() {
+        for (chain, batched) in [
+            (MockChain::new(42, finalized_poll), true),
+            (
+                MockChain::new(42, finalized_poll).without_multicall(),
+                false,
+            ),
+        ] {
+            let results = contract(&chain).get_results(U256::from(1)).await.unwrap();
+            assert_eq!(results.block_number, 42);
+            assert_eq!(results.winner.as_deref(), Some("Pizza"));
+            let votes: Vec<_> = results.tallies.iter().map(|t| t.votes).collect();
+            assert_eq!(votes, vec![U256::from(3), U256::from(1)]);
+
+            // The poll, the votes and the winner are all read at the block returned
+            let calls = chain.calls();
+            assert_eq!(calls.iter().any(|(to, _)| *to == MULTICALL), batched);
+            for (to, block) in calls {
+                assert!(to == CONTRACT || to == MULTICALL);
+                assert_eq!(block, json!("0x2a"));
             }
         }
+    }
+
+    #[actix_web::test]
+    async fn used_nonces_are_read_in_a_batch() {
+        let nonces = |input: &[u8]| {
+            let call = VOTECHAIN::usedNoncesCall::abi_decode(input, true).unwrap();
+            let used = call._0 == Address::repeat_byte(1) && call._2 == U256::from(3);
+            Ok(VOTECHAIN::usedNoncesCall::abi_encode_returns(&(used,)))
+        };
+        let chain = MockChain::new(42, nonces);
+        let used = chain
+            .votechain()
+            .used_nonces(
+                Address::repeat_byte(1),
+                U256::from(1),
+                &[U256::from(2), U256::from(3)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(used, vec![false, true]);
+        assert_eq!(chain.calls(), vec![(MULTICALL, json!("0x2a"))]);
+    }
+
+    #[actix_web::test]
+    async fn relayed_votes_carry_the_receipt_of_the_contract() {
+        let vote = |poll_id: u64| VOTECHAIN::VoteCast {
+            poll_id: U256::from(poll_id),
+            voter: Address::repeat_byte(1),
+            option: "Pizza".to_owned(),
+        };
+        let sent = |poll_id: u64| VOTECHAIN::VoteReceiptSent {
+            voter: Address::repeat_byte(1),
+            poll_id: U256::from(poll_id),
+            receipt: format!("receipt of poll {}", poll_id),
+        };
+        let tx_hash = TxHash::repeat_byte(7);
+
+        let chain = MockChain::new(42, finalized_poll).with_logs(vec![
+            (CONTRACT, sent(2).encode_log_data()),
+            (CONTRACT, vote(1).encode_log_data()),
+            (CONTRACT, sent(1).encode_log_data()),
+        ]);
+        let relayed = chain.votechain().get_relayed_vote(tx_hash).await.unwrap();
+        let relayed = relayed.unwrap();
+        assert_eq!((relayed.block_number, relayed.log_index), (42, 1));
+        assert_eq!(relayed.option, "Pizza");
+        assert_eq!(
+            relayed.contract_receipt.as_deref(),
+            Some("receipt of poll 1")
+        );
 
-        // Return vector of poll data
-        Ok(polls)
+        // Events of other contracts or polls are not taken for the receipt of the vote
+        let chain = MockChain::new(42, finalized_poll).with_logs(vec![
+            (Address::repeat_byte(9), sent(1).encode_log_data()),
+            (CONTRACT, vote(1).encode_log_data()),
+            (CONTRACT, sent(2).encode_log_data()),
+        ]);
+        let relayed = chain.votechain().get_relayed_vote(tx_hash).await.unwrap();
+        assert_eq!(relayed.unwrap().contract_receipt, None);
     }
 }
@@ -1,9 +1,105 @@
+use diesel::connection::{Instrumentation, InstrumentationEvent};
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
-use std::env;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool};
+use std::{env, time::Duration};
+use tracing::{field, info_span, Span};
 
-pub fn establish_connection() -> PgConnection {
+use crate::{
+    config,
+    errors::{ApiErrorResponse, AppError},
+};
+
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+// Defaults of the optional pool settings
+const DEFAULT_POOL_SIZE: u32 = 10;
+const DEFAULT_CONNECTION_TIMEOUT_SECS: u64 = 3;
+
+/// Create the database connection pool. No connection is opened here: the pool connects
+/// lazily and replaces broken connections, so the server can start while Postgres is down
+pub fn create_pool() -> DbPool {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env");
-    PgConnection::establish(&database_url)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
+    let manager = ConnectionManager::<PgConnection>::new(database_url);
+
+    Pool::builder()
+        .max_size(config::parse_or("DATABASE_POOL_SIZE", DEFAULT_POOL_SIZE))
+        .min_idle(Some(0))
+        .connection_timeout(Duration::from_secs(config::parse_or(
+            "DATABASE_CONNECTION_TIMEOUT_SECS",
+            DEFAULT_CONNECTION_TIMEOUT_SECS,
+        )))
+        // Validate connections before handing them out, dropped ones are re-established
+        .test_on_check_out(true)
+        .connection_customizer(Box::new(TraceQueries))
+        .build_unchecked(manager)
+}
+
+/// Run queries on a pooled connection, off the async runtime. The queries are traced
+/// under the span of the caller
+pub async fn query<T, E, F>(pool: &DbPool, f: F) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    E: Into<AppError> + Send + 'static,
+    F: FnOnce(&mut PgConnection) -> Result<T, E> + Send + 'static,
+{
+    let pool = pool.clone();
+    let span = Span::current();
+    actix_web::web::block(move || {
+        let _entered = span.enter();
+        let mut conn = pool.get()?;
+        f(&mut conn).map_err(Into::into)
+    })
+    .await
+    // The closure panicked or the thread pool is gone
+    .map_err(|_| AppError::from(ApiErrorResponse::InternalServerError))?
+}
+
+// Attach the query tracer to every connection opened by the pool
+#[derive(Debug)]
+struct TraceQueries;
+
+impl CustomizeConnection<PgConnection, diesel::r2d2::Error> for TraceQueries {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), diesel::r2d2::Error> {
+        conn.set_instrumentation(QueryTracer::default());
+        Ok(())
+    }
+}
+
+/// Diesel instrumentation creating a span for each query, as child of the current span
+#[derive(Default)]
+struct QueryTracer {
+    // Spans of the queries being executed (nested queries are possible in transactions)
+    spans: Vec<Span>,
+}
+
+impl Instrumentation for QueryTracer {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { query, .. } => {
+                // NOTE: bind parameters are stripped, they may contain tokens
+                let sql = query.to_string();
+                let statement = sql.split(" -- binds:").next().unwrap_or_default();
+
+                self.spans.push(info_span!(
+                    "db_query",
+                    otel.kind = "client",
+                    otel.status_code = field::Empty,
+                    db.system = "postgresql",
+                    db.statement = statement,
+                    error = field::Empty,
+                ));
+            }
+            InstrumentationEvent::FinishQuery { error, .. } => {
+                // Dropping the span closes it
+                if let Some(span) = self.spans.pop() {
+                    if let Some(e) = error {
+                        span.record("otel.status_code", "ERROR");
+                        span.record("error", e.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
 }
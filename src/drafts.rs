@@ -0,0 +1,246 @@
+use std::time::SystemTime;
+
+use diesel::PgConnection;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::Claims,
+    config,
+    errors::{ApiErrorResponse, AppError},
+    metadata::PollMetadata,
+    models::poll_drafts::{NewPollDraft, PollDraft, PollDraftUpdate, DRAFT},
+    timestamp::Timestamp,
+    validation::NewPoll,
+};
+
+/// Whether drafts must be approved by an administrator other than their author before
+/// being published (DRAFTS_REQUIRE_APPROVAL)
+pub fn approval_required() -> bool {
+    config::flag("DRAFTS_REQUIRE_APPROVAL")
+}
+
+/// Content of a draft, sent to create or edit it. It is only validated when previewed or
+/// published, so that incomplete drafts can be saved
+#[derive(Deserialize)]
+pub struct DraftRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub options: Vec<String>,
+    pub start_time: Timestamp, // RFC 3339 or unix seconds
+    pub end_time: Timestamp,
+    pub metadata: Option<PollMetadata>,
+}
+
+impl DraftRequest {
+    pub fn into_new(self, author: String) -> NewPollDraft {
+        let now = SystemTime::now();
+        NewPollDraft {
+            owner: author,
+            metadata: self.metadata_json(),
+            name: self.name,
+            description: self.description,
+            options: self.options,
+            start_time: self.start_time.as_secs() as i64,
+            end_time: self.end_time.as_secs() as i64,
+            status: DRAFT.to_owned(),
+            created_at: Some(now),
+            updated_at: Some(now),
+        }
+    }
+
+    pub fn into_update(self) -> PollDraftUpdate {
+        PollDraftUpdate {
+            metadata: self.metadata_json(),
+            name: self.name,
+            description: self.description,
+            options: self.options,
+            start_time: self.start_time.as_secs() as i64,
+            end_time: self.end_time.as_secs() as i64,
+            approved_by: None,
+            updated_at: Some(SystemTime::now()),
+        }
+    }
+
+    fn metadata_json(&self) -> Option<String> {
+        // Serialization of plain structs cannot fail
+        self.metadata
+            .as_ref()
+            .map(|metadata| serde_json::to_string(metadata).unwrap_or_default())
+    }
+}
+
+/// Draft as returned by the API
+#[derive(Serialize)]
+pub struct Draft {
+    pub id: i32,
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub options: Vec<String>,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    pub metadata: Option<PollMetadata>,
+    pub status: String,
+    pub approved_by: Option<String>,
+    pub poll_id: Option<String>, // Once published
+    pub transaction_hash: Option<String>,
+    pub created_at: Option<Timestamp>,
+    pub updated_at: Option<Timestamp>,
+}
+
+impl TryFrom<PollDraft> for Draft {
+    type Error = AppError;
+
+    // Stored values were checked when saved, failures mean the row was altered
+    fn try_from(draft: PollDraft) -> Result<Self, Self::Error> {
+        let timestamp = |secs: i64| {
+            u64::try_from(secs)
+                .ok()
+                .and_then(|secs| Timestamp::from_secs(secs).ok())
+                .ok_or(ApiErrorResponse::InternalServerError)
+        };
+        let metadata = match &draft.metadata {
+            Some(json) => Some(
+                serde_json::from_str(json).map_err(|_| ApiErrorResponse::InternalServerError)?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            id: draft.id,
+            start_time: timestamp(draft.start_time)?,
+            end_time: timestamp(draft.end_time)?,
+            metadata,
+            created_at: draft.created_at.and_then(|t| t.try_into().ok()),
+            updated_at: draft.updated_at.and_then(|t| t.try_into().ok()),
+            owner: draft.owner,
+            name: draft.name,
+            description: draft.description,
+            options: draft.options,
+            status: draft.status,
+            approved_by: draft.approved_by,
+            poll_id: draft.poll_id,
+            transaction_hash: draft.tx_hash,
+        })
+    }
+}
+
+impl Draft {
+    /// Poll that publishing the draft would create
+    pub fn as_new_poll(&self) -> NewPoll<'_> {
+        NewPoll {
+            name: &self.name,
+            description: &self.description,
+            options: &self.options,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            metadata: self.metadata.as_ref(),
+        }
+    }
+
+    pub fn is_editable(&self) -> bool {
+        self.status == DRAFT
+    }
+}
+
+/// Address of the caller, which owns the drafts it creates
+pub fn author(claims: &Claims) -> Result<String, AppError> {
+    claims
+        .address()
+        .map(|address| address.to_string())
+        .ok_or(ApiErrorResponse::Forbidden.into())
+}
+
+/// Draft visible to the caller: its author, or an administrator
+pub fn get_visible(
+    conn: &mut PgConnection,
+    draft_id: i32,
+    claims: &Claims,
+) -> Result<Draft, AppError> {
+    let draft: Draft = PollDraft::get(conn, draft_id)?.try_into()?;
+    if !is_author(&draft, claims) && !claims.is_admin() {
+        return Err(ApiErrorResponse::Forbidden.into());
+    }
+    Ok(draft)
+}
+
+/// Draft editable by the caller: its author only, until it is published
+pub fn get_editable(
+    conn: &mut PgConnection,
+    draft_id: i32,
+    claims: &Claims,
+) -> Result<Draft, AppError> {
+    let draft: Draft = PollDraft::get(conn, draft_id)?.try_into()?;
+    if !is_author(&draft, claims) {
+        return Err(ApiErrorResponse::Forbidden.into());
+    }
+    if !draft.is_editable() {
+        return Err(ApiErrorResponse::DraftPublished.into());
+    }
+    Ok(draft)
+}
+
+pub fn is_author(draft: &Draft, claims: &Claims) -> bool {
+    claims
+        .address()
+        .is_some_and(|address| address.to_string() == draft.owner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::poll_drafts::{PUBLISHED, PUBLISHING};
+
+    fn request() -> DraftRequest {
+        serde_json::from_value(serde_json::json!({
+            "name": "Lunch",
+            "options": ["Pizza", "Sushi"],
+            "start_time": 1_000,
+            "end_time": 2_000,
+        }))
+        .unwrap()
+    }
+
+    fn draft(status: &str, approved_by: Option<&str>) -> Draft {
+        let new = request().into_new("0xauthor".to_owned());
+        Draft::try_from(PollDraft {
+            id: 1,
+            owner: new.owner,
+            name: new.name,
+            description: new.description,
+            options: new.options,
+            start_time: new.start_time,
+            end_time: new.end_time,
+            metadata: new.metadata,
+            status: status.to_owned(),
+            approved_by: approved_by.map(str::to_owned),
+            poll_id: None,
+            tx_hash: None,
+            created_at: new.created_at,
+            updated_at: new.updated_at,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn new_drafts_start_editable() {
+        let new = request().into_new("0xauthor".to_owned());
+        assert_eq!(new.status, DRAFT);
+        assert!(draft(&new.status, None).is_editable());
+    }
+
+    #[test]
+    fn only_drafts_can_be_edited() {
+        assert!(draft(DRAFT, Some("0xreviewer")).is_editable());
+        assert!(!draft(PUBLISHING, Some("0xreviewer")).is_editable());
+        assert!(!draft(PUBLISHED, Some("0xreviewer")).is_editable());
+    }
+
+    #[test]
+    fn edits_withdraw_the_approval() {
+        let update = request().into_update();
+        assert_eq!(update.approved_by, None);
+        assert_eq!(update.options, ["Pizza", "Sushi"]);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+
+use alloy::primitives::{Address, B256};
+use diesel::PgConnection;
+use serde::Serialize;
+
+use crate::{
+    cache::CacheKey,
+    db,
+    errors::{ApiErrorResponse, AppError, FieldError},
+    merkle,
+    metadata::{PollView, TokenGate},
+    models::{merkle_allowlists::AllowlistEntry, poll_eligibility::EligibleVoter},
+    AppState,
+};
+
+// Invalid lines reported at most, the rest of the file is not worth listing
+const MAX_REPORTED_ERRORS: usize = 20;
+
+/// Whether an address may vote in a poll, and why
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Eligibility {
+    Open,                // The poll has no restriction
+    Listed,              // The address is in the allowlists of the poll
+    NotListed,           // The address is missing from an allowlist
+    Holder,              // The address held enough tokens at the snapshot block
+    InsufficientBalance, // The address did not hold enough tokens at the snapshot block
+}
+
+impl Eligibility {
+    pub fn is_eligible(self) -> bool {
+        matches!(
+            self,
+            Eligibility::Open | Eligibility::Listed | Eligibility::Holder
+        )
+    }
+}
+
+/// Eligibility of `voter` in a poll. Polls may be restricted by the allowlist uploaded by
+/// their owner, by a Merkle allowlist and by a token gate committed in their metadata. All
+/// the restrictions set apply
+pub async fn check(
+    app_state: &AppState,
+    poll: &PollView,
+    voter: Address,
+) -> Result<Eligibility, AppError> {
+    check_with(app_state, poll, voter, move |conn, root| {
+        Ok(AllowlistEntry::contains(
+            conn,
+            &root.to_string(),
+            &voter.to_string(),
+        )?)
+    })
+    .await
+}
+
+/// Same as `check` before relaying a vote, except that membership of the Merkle allowlist
+/// must be shown by the proof sent with the vote (see `GET /poll/{id}/proof/{address}`)
+pub async fn check_vote(
+    app_state: &AppState,
+    poll: &PollView,
+    voter: Address,
+    proof: Option<&[B256]>,
+) -> Result<Eligibility, AppError> {
+    let proof = proof.map(<[B256]>::to_vec);
+    check_with(app_state, poll, voter, move |_, root| {
+        let proof = proof.ok_or_else(|| ApiErrorResponse::ValidationFailed {
+            errors: vec![FieldError::new(
+                "proof",
+                "is required, the poll has a Merkle allowlist",
+            )],
+        })?;
+        Ok(merkle::verify(root, voter, &proof))
+    })
+    .await
+}
+
+// Apply the restrictions of a poll, `in_tree` telling whether the voter is in the Merkle
+// allowlist
+async fn check_with(
+    app_state: &AppState,
+    poll: &PollView,
+    voter: Address,
+    in_tree: impl FnOnce(&mut PgConnection, B256) -> Result<bool, AppError> + Send + 'static,
+) -> Result<Eligibility, AppError> {
+    // Restrictions are committed in the metadata, it must be available to check them
+    let metadata = poll.committed_metadata()?;
+
+    // Allowlists: None when the voter is missing from one of them, otherwise whether any
+    // applies
+    let root = metadata.and_then(|metadata| metadata.allowlist_root);
+    let poll_id = poll.poll.id.to_string();
+    let listed = db::query(&app_state.db, move |conn| {
+        let mut listed = false;
+        if let Some(root) = root {
+            listed = true;
+            if !in_tree(conn, root)? {
+                return Ok(None);
+            }
+        }
+
+        if EligibleVoter::is_restricted(conn, &poll_id)? {
+            listed = true;
+            if !EligibleVoter::is_listed(conn, &poll_id, &voter.to_string())? {
+                return Ok(None);
+            }
+        }
+        Ok::<_, AppError>(Some(listed))
+    })
+    .await?;
+    let Some(listed) = listed else {
+        return Ok(Eligibility::NotListed);
+    };
+
+    // Token gate
+    let gate = metadata.and_then(|metadata| metadata.token_gate.as_ref());
+    if let Some(gate) = gate {
+        if !holds(app_state, gate, voter).await? {
+            return Ok(Eligibility::InsufficientBalance);
+        }
+    }
+
+    Ok(match (listed, gate.is_some()) {
+        (true, _) => Eligibility::Listed,
+        (false, true) => Eligibility::Holder,
+        (false, false) => Eligibility::Open,
+    })
+}
+
+// Whether the voter held the minimum balance at the snapshot block. Balances at a past
+// block never change, so they are cached for good
+async fn holds(app_state: &AppState, gate: &TokenGate, voter: Address) -> Result<bool, AppError> {
+    let contract = &app_state.contracts.votechain;
+    let key = CacheKey::Balance {
+        token: gate.token,
+        owner: voter,
+        block: gate.snapshot_block,
+    };
+
+    let balance = app_state
+        .cache
+        .get_or_load(key, || {
+            contract.token_balance(gate.token, voter, gate.snapshot_block)
+        })
+        .await?;
+    Ok(balance >= gate.min_balance)
+}
+
+/// Merkle root of the voters committed in the metadata of a poll. Fails when the metadata is
+/// committed but unavailable, rather than reporting the poll as open to everyone
+pub fn allowlist_root(poll: &PollView) -> Result<Option<B256>, ApiErrorResponse> {
+    Ok(poll
+        .committed_metadata()?
+        .and_then(|metadata| metadata.allowlist_root))
+}
+
+/// Addresses of an uploaded CSV file: one per line in the first column, after an optional
+/// header. Duplicates are ignored
+pub fn parse_csv(content: &str) -> Result<Vec<Address>, Vec<FieldError>> {
+    let mut addresses = Vec::new();
+    let mut seen = HashSet::new();
+    let mut errors = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let cell = line.split(',').next().unwrap_or_default().trim();
+        let cell = cell.trim_matches('"');
+        if cell.is_empty() || (i == 0 && cell.eq_ignore_ascii_case("address")) {
+            continue;
+        }
+
+        match cell.parse::<Address>() {
+            Ok(address) => {
+                if seen.insert(address) {
+                    addresses.push(address);
+                }
+            }
+            Err(_) if errors.len() < MAX_REPORTED_ERRORS => errors.push(FieldError::new(
+                &format!("line {}", i + 1),
+                format!("`{}` is not an address", cell),
+            )),
+            Err(_) => {}
+        }
+    }
+
+    if addresses.is_empty() && errors.is_empty() {
+        errors.push(FieldError::new("body", "must contain at least one address"));
+    }
+
+    match errors.is_empty() {
+        true => Ok(addresses),
+        false => Err(errors),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::*;
+    use crate::{contracts::votechain::Poll, metadata::PollMetadata, timestamp::Timestamp};
+
+    const ALICE: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+    const BOB: &str = "0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC";
+
+    fn address(value: &str) -> Address {
+        value.parse().unwrap()
+    }
+
+    fn poll(metadata_hash: Option<B256>, metadata: Option<PollMetadata>) -> PollView {
+        PollView {
+            poll: Poll {
+                id: U256::from(1),
+                name: "Lunch".to_owned(),
+                description: String::new(),
+                start_time: Timestamp::from_secs(1_000).unwrap(),
+                end_time: Timestamp::from_secs(2_000).unwrap(),
+                winner: String::new(),
+                is_ended: false,
+                owner: Address::ZERO,
+                metadata_hash,
+            },
+            metadata,
+        }
+    }
+
+    #[test]
+    fn parses_first_column_after_header() {
+        let csv = format!("Address,name\n{},alice\n\n\"{}\",bob\n", ALICE, BOB);
+        assert_eq!(parse_csv(&csv).unwrap(), [address(ALICE), address(BOB)]);
+    }
+
+    #[test]
+    fn ignores_duplicates() {
+        let csv = format!("{}\n{}\n{}\n", ALICE, ALICE.to_lowercase(), BOB);
+        assert_eq!(parse_csv(&csv).unwrap(), [address(ALICE), address(BOB)]);
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        let csv = format!("address\n{}\nnot an address\n0x1234\n", ALICE);
+        let errors = parse_csv(&csv).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["line 3", "line 4"]);
+    }
+
+    #[test]
+    fn reports_at_most_max_errors() {
+        let csv = "nope\n".repeat(MAX_REPORTED_ERRORS + 5);
+        assert_eq!(parse_csv(&csv).unwrap_err().len(), MAX_REPORTED_ERRORS);
+    }
+
+    #[test]
+    fn rejects_empty_list() {
+        let errors = parse_csv("address\n\n").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "body");
+    }
+
+    #[test]
+    fn allowlist_root_is_read_from_the_committed_metadata() {
+        let root = B256::repeat_byte(0xab);
+        let metadata: PollMetadata =
+            serde_json::from_value(serde_json::json!({ "allowlist_root": root })).unwrap();
+        let hash = Some(B256::repeat_byte(0x01));
+
+        assert_eq!(
+            allowlist_root(&poll(hash, Some(metadata))).unwrap(),
+            Some(root)
+        );
+        assert_eq!(allowlist_root(&poll(None, None)).unwrap(), None);
+    }
+
+    #[test]
+    fn allowlist_root_fails_closed_without_committed_metadata() {
+        let poll = poll(Some(B256::repeat_byte(0x01)), None);
+        assert!(allowlist_root(&poll).is_err());
+    }
+}
@@ -1,11 +1,29 @@
 use actix_web::{
-    error,
-    http::{header::ContentType, StatusCode},
-    HttpResponse,
+    error::{self, JsonPayloadError, PathError, QueryPayloadError},
+    http::{header, StatusCode},
+    HttpRequest, HttpResponse,
 };
-use derive_more::{Display, Error};
+use derive_more::{Display, Error, From};
+use log::{error, warn};
+use serde::Serialize;
+use std::sync::OnceLock;
 
-#[derive(Debug, Display, Error)]
+use crate::{config, contracts::errors::ContractError, metrics, middlewares::request_id};
+
+// Media type defined by RFC 7807 for problem details
+const PROBLEM_JSON: &str = "application/problem+json";
+
+// Realm advertised in WWW-Authenticate challenges
+const AUTH_REALM: &str = "VoteChain";
+
+// Whether to keep answering with the legacy 495-499 status codes (see LEGACY_STATUS_CODES)
+static LEGACY_STATUS_CODES: OnceLock<bool> = OnceLock::new();
+
+fn legacy_status_codes() -> bool {
+    *LEGACY_STATUS_CODES.get_or_init(|| config::flag("LEGACY_STATUS_CODES"))
+}
+
+#[derive(Debug, Clone, Display, Error)]
 pub enum ApiErrorResponse {
     #[display["missing token"]]
     NoToken,
@@ -30,25 +48,512 @@ pub enum ApiErrorResponse {
 
     #[display["resource not found"]]
     NotFound,
+
+    #[display["service temporarily unavailable"]]
+    ServiceUnavailable,
+
+    #[display["you are not allowed to perform this action"]]
+    Forbidden,
+
+    #[display["poll is not accepting votes"]]
+    PollClosed,
+
+    #[display["vote already cast for this poll"]]
+    AlreadyVoted,
+
+    #[display["address is not eligible to vote in this poll"]]
+    NotEligible,
+
+    #[display["draft is already published"]]
+    DraftPublished,
+
+    #[display["draft must be approved by another administrator"]]
+    ApprovalRequired,
+
+    #[display["poll is in its {phase} phase"]]
+    WrongPhase {
+        phase: &'static str, // Current phase of a commit-reveal poll
+    },
+
+    #[display["too many requests, retry in {retry_after}s"]]
+    RateLimited {
+        retry_after: u64, // Seconds until the client may try again
+    },
+
+    #[display["transaction reverted: {reason}"]]
+    ContractReverted {
+        #[error(not(source))]
+        reason: String, // Revert reason returned by the contract
+    },
+
+    #[display["request validation failed"]]
+    ValidationFailed {
+        #[error(not(source))]
+        errors: Vec<FieldError>, // Every invalid field, with the reason
+    },
+
+    #[display["invalid request {location}: {reason}"]]
+    InvalidRequest {
+        location: &'static str, // Part of the request that failed (body, path, query)
+        #[error(not(source))]
+        reason: String, // Message produced by the extractor
+    },
+}
+
+/// Invalid field of a request body
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,  // Path of the field, e.g. `options[2]`
+    pub reason: String, // What is wrong with it
+}
+
+impl FieldError {
+    pub fn new(field: &str, reason: impl Into<String>) -> Self {
+        Self {
+            field: field.to_owned(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// RFC 7807 problem details returned as body of every error response
+#[derive(Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String, // URI identifying the problem type
+    pub title: String,      // Short summary of the HTTP status
+    pub status: u16,        // HTTP status code
+    pub detail: String,     // Human readable explanation
+    pub code: &'static str, // Stable machine readable error code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>, // Id of the request that caused the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>, // Extra information about the error
+}
+
+impl ApiErrorResponse {
+    /// Stable error code that clients can branch on. Never change an existing one!
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiErrorResponse::NoToken => "missing_token",
+            ApiErrorResponse::InvalidToken => "invalid_token",
+            ApiErrorResponse::ExpiredToken => "expired_token",
+            ApiErrorResponse::InvalidSignature => "invalid_signature",
+            ApiErrorResponse::MissingBearerToken => "missing_bearer_token",
+            ApiErrorResponse::TokenMismatch => "token_mismatch",
+            ApiErrorResponse::InternalServerError => "internal_error",
+            ApiErrorResponse::NotFound => "not_found",
+            ApiErrorResponse::ServiceUnavailable => "service_unavailable",
+            ApiErrorResponse::Forbidden => "forbidden",
+            ApiErrorResponse::PollClosed => "poll_closed",
+            ApiErrorResponse::AlreadyVoted => "already_voted",
+            ApiErrorResponse::NotEligible => "not_eligible",
+            ApiErrorResponse::DraftPublished => "draft_published",
+            ApiErrorResponse::ApprovalRequired => "approval_required",
+            ApiErrorResponse::WrongPhase { .. } => "wrong_phase",
+            ApiErrorResponse::RateLimited { .. } => "rate_limited",
+            ApiErrorResponse::ContractReverted { .. } => "contract_reverted",
+            ApiErrorResponse::ValidationFailed { .. } => "validation_failed",
+            ApiErrorResponse::InvalidRequest { .. } => "invalid_request",
+        }
+    }
+
+    /// RFC 6750 challenge describing why authentication failed, if relevant
+    fn www_authenticate(&self) -> Option<String> {
+        let (error, description) = match self {
+            ApiErrorResponse::NoToken => return Some(format!("Bearer realm=\"{}\"", AUTH_REALM)),
+            ApiErrorResponse::MissingBearerToken => ("invalid_request", "bearer token expected"),
+            ApiErrorResponse::InvalidToken => ("invalid_token", "token is invalid"),
+            ApiErrorResponse::ExpiredToken => ("invalid_token", "token has expired"),
+            ApiErrorResponse::InvalidSignature => ("invalid_token", "signature is invalid"),
+            _ => return None,
+        };
+
+        Some(format!(
+            "Bearer realm=\"{}\", error=\"{}\", error_description=\"{}\"",
+            AUTH_REALM, error, description
+        ))
+    }
+
+    /// Response rejecting a request to a bearer-protected route, with the challenge telling
+    /// the client exactly why authentication failed. Other routes (e.g. the signature based
+    /// sign in) answer without challenge
+    pub fn bearer_challenge(&self) -> HttpResponse {
+        let mut res = error::ResponseError::error_response(self);
+        if let Some(challenge) = self
+            .www_authenticate()
+            .and_then(|challenge| header::HeaderValue::from_str(&challenge).ok())
+        {
+            res.headers_mut()
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+        res
+    }
+
+    /// Status codes used before the standard taxonomy. Kept only for clients that still
+    /// rely on them, enable with LEGACY_STATUS_CODES=true
+    fn legacy_status_code(&self) -> Option<StatusCode> {
+        let code = match self {
+            ApiErrorResponse::InvalidToken => 403,
+            ApiErrorResponse::ExpiredToken => 498,
+            ApiErrorResponse::InvalidSignature => 495,
+            ApiErrorResponse::MissingBearerToken => 499,
+            ApiErrorResponse::TokenMismatch => 496,
+            _ => return None,
+        };
+        StatusCode::from_u16(code).ok()
+    }
+
+    // Whether this error rejects an authentication attempt
+    fn is_auth_failure(&self) -> bool {
+        matches!(
+            self,
+            ApiErrorResponse::NoToken
+                | ApiErrorResponse::InvalidToken
+                | ApiErrorResponse::ExpiredToken
+                | ApiErrorResponse::InvalidSignature
+                | ApiErrorResponse::MissingBearerToken
+                | ApiErrorResponse::TokenMismatch
+        )
+    }
+
+    /// Additional machine readable information attached to the problem
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            ApiErrorResponse::InvalidRequest { location, .. } => {
+                Some(serde_json::json!({ "location": location }))
+            }
+            ApiErrorResponse::WrongPhase { phase } => Some(serde_json::json!({ "phase": phase })),
+            ApiErrorResponse::RateLimited { retry_after } => {
+                Some(serde_json::json!({ "retry_after": retry_after }))
+            }
+            ApiErrorResponse::ContractReverted { reason } => {
+                Some(serde_json::json!({ "reason": reason }))
+            }
+            ApiErrorResponse::ValidationFailed { errors } => {
+                Some(serde_json::json!({ "errors": errors }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Build the problem details body for this error
+    pub fn problem(&self) -> ProblemDetails {
+        let status = error::ResponseError::status_code(self);
+        let code = self.code();
+
+        ProblemDetails {
+            problem_type: format!("urn:votechain:error:{}", code),
+            title: status
+                .canonical_reason()
+                .unwrap_or("Unknown Error")
+                .to_owned(),
+            status: status.as_u16(),
+            detail: self.to_string(),
+            code,
+            request_id: request_id::current(),
+            details: self.details(),
+        }
+    }
 }
 
 impl error::ResponseError for ApiErrorResponse {
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code())
-            .insert_header(ContentType::html())
-            .body(self.to_string())
+        if self.is_auth_failure() {
+            metrics::AUTH_FAILURES
+                .with_label_values(&[self.code()])
+                .inc();
+        }
+
+        let mut res = HttpResponse::build(self.status_code());
+        res.insert_header((header::CONTENT_TYPE, PROBLEM_JSON));
+        if let ApiErrorResponse::RateLimited { retry_after } = self {
+            res.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+        }
+        res.json(self.problem())
     }
 
     fn status_code(&self) -> StatusCode {
+        self.status(legacy_status_codes())
+    }
+}
+
+impl ApiErrorResponse {
+    // Status code of this error, with or without the legacy codes
+    fn status(&self, legacy: bool) -> StatusCode {
+        if legacy {
+            if let Some(status) = self.legacy_status_code() {
+                return status;
+            }
+        }
+
         match *self {
             ApiErrorResponse::NoToken => StatusCode::UNAUTHORIZED, // 401: Missing authentication token
-            ApiErrorResponse::InvalidToken => StatusCode::FORBIDDEN, // 403: Invalid token provided
-            ApiErrorResponse::ExpiredToken => StatusCode::from_u16(498).unwrap(), // 498: Token expired (special code)
-            ApiErrorResponse::InvalidSignature => StatusCode::from_u16(495).unwrap(), // 495: Invalid signature (special code)
-            ApiErrorResponse::MissingBearerToken => StatusCode::from_u16(499).unwrap(), // 499: Client closed request or missing bearer token (special code)
-            ApiErrorResponse::TokenMismatch => StatusCode::from_u16(496).unwrap(), // 496: Token mismatch (special code)
+            ApiErrorResponse::InvalidToken => StatusCode::UNAUTHORIZED, // 401: Invalid token provided
+            ApiErrorResponse::ExpiredToken => StatusCode::UNAUTHORIZED, // 401: Token expired
+            ApiErrorResponse::InvalidSignature => StatusCode::UNAUTHORIZED, // 401: Signature does not match the account
+            ApiErrorResponse::MissingBearerToken => StatusCode::UNAUTHORIZED, // 401: Authorization is not a bearer token
+            ApiErrorResponse::TokenMismatch => StatusCode::CONFLICT, // 409: Refresh token was replaced by a newer session
             ApiErrorResponse::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR, // 500: Internal server error
             ApiErrorResponse::NotFound => StatusCode::NOT_FOUND, // 404: Resource
+            ApiErrorResponse::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE, // 503: Dependency unavailable
+            ApiErrorResponse::Forbidden => StatusCode::FORBIDDEN, // 403: Authenticated but not allowed
+            ApiErrorResponse::PollClosed => StatusCode::UNPROCESSABLE_ENTITY, // 422: Poll not active
+            ApiErrorResponse::AlreadyVoted => StatusCode::CONFLICT, // 409: Vote already recorded
+            ApiErrorResponse::NotEligible => StatusCode::FORBIDDEN, // 403: Not in the allowlist of the poll
+            ApiErrorResponse::DraftPublished => StatusCode::CONFLICT, // 409: Draft no longer editable
+            ApiErrorResponse::ApprovalRequired => StatusCode::CONFLICT, // 409: Draft not approved yet
+            ApiErrorResponse::WrongPhase { .. } => StatusCode::CONFLICT, // 409: Commit or reveal out of its phase
+            ApiErrorResponse::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS, // 429: Too many requests from the client
+            ApiErrorResponse::ContractReverted { .. } => StatusCode::UNPROCESSABLE_ENTITY, // 422: Rejected by the contract
+            ApiErrorResponse::ValidationFailed { .. } => StatusCode::UNPROCESSABLE_ENTITY, // 422: Well-formed but invalid fields
+            ApiErrorResponse::InvalidRequest { .. } => StatusCode::BAD_REQUEST, // 400: Malformed request
+        }
+    }
+}
+
+/// Unified error type returned by services and routes. Internal failures are logged once
+/// (when rendered) and exposed to clients only through the matching `ApiErrorResponse`
+#[derive(Debug, Display, Error, From)]
+pub enum AppError {
+    #[display["{_0}"]]
+    Api(ApiErrorResponse),
+
+    #[display["contract call failed: {_0}"]]
+    Rpc(ContractError),
+
+    #[display["database error: {_0}"]]
+    Database(diesel::result::Error),
+
+    #[display["database unavailable: {_0}"]]
+    DatabasePool(diesel::r2d2::PoolError),
+
+    #[display["jwt error: {_0}"]]
+    Jwt(jsonwebtoken::errors::Error),
+
+    #[display["validation failed: {_0}"]]
+    #[from(ignore)]
+    Validation(#[error(not(source))] String),
+}
+
+impl AppError {
+    /// Client facing error matching this failure
+    fn api_error(&self) -> ApiErrorResponse {
+        match self {
+            AppError::Api(e) => e.clone(),
+            AppError::Rpc(e) => e.into(),
+            AppError::Database(diesel::result::Error::NotFound) => ApiErrorResponse::NotFound,
+            AppError::Database(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::ClosedConnection,
+                _,
+            )) => ApiErrorResponse::ServiceUnavailable,
+            AppError::Database(_) => ApiErrorResponse::InternalServerError,
+            AppError::DatabasePool(_) => ApiErrorResponse::ServiceUnavailable,
+            AppError::Jwt(e) => match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => ApiErrorResponse::ExpiredToken,
+                jsonwebtoken::errors::ErrorKind::InvalidToken
+                | jsonwebtoken::errors::ErrorKind::InvalidSignature
+                | jsonwebtoken::errors::ErrorKind::InvalidAudience
+                | jsonwebtoken::errors::ErrorKind::InvalidAlgorithm
+                | jsonwebtoken::errors::ErrorKind::MissingRequiredClaim(_)
+                | jsonwebtoken::errors::ErrorKind::Base64(_)
+                | jsonwebtoken::errors::ErrorKind::Json(_)
+                | jsonwebtoken::errors::ErrorKind::Utf8(_) => ApiErrorResponse::InvalidToken,
+                _ => ApiErrorResponse::InternalServerError,
+            },
+            AppError::Validation(reason) => ApiErrorResponse::InvalidRequest {
+                location: "body",
+                reason: reason.clone(),
+            },
+        }
+    }
+}
+
+impl error::ResponseError for AppError {
+    fn error_response(&self) -> HttpResponse {
+        let api_error = self.api_error();
+
+        // Log the underlying cause once, with the request id to correlate it
+        let request_id = request_id::current().unwrap_or_default();
+        if api_error.status_code().is_server_error() {
+            error!("[{}] {}", request_id, self);
+        } else if !matches!(self, AppError::Api(_)) {
+            warn!("[{}] {}", request_id, self);
+        }
+
+        api_error.error_response()
+    }
+
+    fn status_code(&self) -> StatusCode {
+        self.api_error().status_code()
+    }
+}
+
+//
+// Extractor error handlers: make Actix report malformed requests as problem details too
+//
+
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> error::Error {
+    ApiErrorResponse::InvalidRequest {
+        location: "body",
+        reason: err.to_string(),
+    }
+    .into()
+}
+
+pub fn path_error_handler(err: PathError, _req: &HttpRequest) -> error::Error {
+    ApiErrorResponse::InvalidRequest {
+        location: "path",
+        reason: err.to_string(),
+    }
+    .into()
+}
+
+pub fn query_error_handler(err: QueryPayloadError, _req: &HttpRequest) -> error::Error {
+    ApiErrorResponse::InvalidRequest {
+        location: "query",
+        reason: err.to_string(),
+    }
+    .into()
+}
+
+/// Fallback service for routes that do not exist
+pub async fn not_found() -> Result<HttpResponse, ApiErrorResponse> {
+    Err(ApiErrorResponse::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{body, ResponseError};
+
+    // Error, status, legacy status and `error` of the WWW-Authenticate challenge
+    fn cases() -> Vec<(ApiErrorResponse, u16, u16, Option<&'static str>)> {
+        vec![
+            (ApiErrorResponse::NoToken, 401, 401, Some("")),
+            (
+                ApiErrorResponse::InvalidToken,
+                401,
+                403,
+                Some("invalid_token"),
+            ),
+            (
+                ApiErrorResponse::ExpiredToken,
+                401,
+                498,
+                Some("invalid_token"),
+            ),
+            (
+                ApiErrorResponse::InvalidSignature,
+                401,
+                495,
+                Some("invalid_token"),
+            ),
+            (
+                ApiErrorResponse::MissingBearerToken,
+                401,
+                499,
+                Some("invalid_request"),
+            ),
+            (ApiErrorResponse::TokenMismatch, 409, 496, None),
+            (ApiErrorResponse::InternalServerError, 500, 500, None),
+            (ApiErrorResponse::NotFound, 404, 404, None),
+            (ApiErrorResponse::ServiceUnavailable, 503, 503, None),
+            (ApiErrorResponse::Forbidden, 403, 403, None),
+            (ApiErrorResponse::PollClosed, 422, 422, None),
+            (ApiErrorResponse::AlreadyVoted, 409, 409, None),
+            (
+                ApiErrorResponse::WrongPhase { phase: "commit" },
+                409,
+                409,
+                None,
+            ),
+            (
+                ApiErrorResponse::RateLimited { retry_after: 7 },
+                429,
+                429,
+                None,
+            ),
+            (
+                ApiErrorResponse::ValidationFailed { errors: vec![] },
+                422,
+                422,
+                None,
+            ),
+            (
+                ApiErrorResponse::InvalidRequest {
+                    location: "body",
+                    reason: "bad".into(),
+                },
+                400,
+                400,
+                None,
+            ),
+        ]
+    }
+
+    #[test]
+    fn status_codes() {
+        for (error, status, legacy, _) in cases() {
+            assert_eq!(error.status(false).as_u16(), status, "{}", error.code());
+            assert_eq!(error.status(true).as_u16(), legacy, "{}", error.code());
         }
     }
+
+    #[test]
+    fn problem_details() {
+        for (error, status, _, _) in cases() {
+            let problem = error.problem();
+            assert_eq!(
+                problem.problem_type,
+                format!("urn:votechain:error:{}", error.code())
+            );
+            assert_eq!(problem.code, error.code());
+            assert_eq!(problem.status, status);
+            assert_eq!(problem.detail, error.to_string());
+        }
+    }
+
+    #[test]
+    fn bearer_challenges() {
+        for (error, _, _, challenge) in cases() {
+            let res = error.bearer_challenge();
+            let header = res
+                .headers()
+                .get(header::WWW_AUTHENTICATE)
+                .map(|value| value.to_str().unwrap().to_owned());
+            match challenge {
+                None => assert_eq!(header, None, "{}", error.code()),
+                Some("") => assert_eq!(header.as_deref(), Some("Bearer realm=\"VoteChain\"")),
+                Some(reason) => {
+                    let header = header.unwrap();
+                    assert!(header.starts_with("Bearer realm=\"VoteChain\""));
+                    assert!(header.contains(&format!("error=\"{}\"", reason)));
+                }
+            }
+        }
+
+        // Plain error responses never carry a challenge
+        let res = ApiErrorResponse::ExpiredToken.error_response();
+        assert!(res.headers().get(header::WWW_AUTHENTICATE).is_none());
+    }
+
+    #[actix_web::test]
+    async fn problem_json_body() {
+        let res = ApiErrorResponse::RateLimited { retry_after: 7 }.error_response();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            res.headers().get(header::CONTENT_TYPE).unwrap(),
+            PROBLEM_JSON
+        );
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "7");
+
+        let body = body::to_bytes(res.into_body()).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["type"], "urn:votechain:error:rate_limited");
+        assert_eq!(json["status"], 429);
+        assert_eq!(json["code"], "rate_limited");
+        assert_eq!(json["details"]["retry_after"], 7);
+        assert!(json.get("request_id").is_none());
+    }
 }
@@ -0,0 +1,380 @@
+use std::{
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::web;
+use alloy::{
+    primitives::{Address, TxHash, U256},
+    rpc::types::Log,
+    sol_types::SolEvent,
+};
+use futures::StreamExt;
+use log::{debug, info, warn};
+use serde::Serialize;
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::Sleep,
+};
+
+use crate::{
+    auth::Claims,
+    config,
+    contracts::{errors::ContractError, votechain::Tally},
+    AppState, VOTECHAIN,
+};
+
+// Events kept for slow subscribers before they start missing some
+const CHANNEL_CAPACITY: usize = 1024;
+
+// Defaults of the optional event settings
+const DEFAULT_POLL_INTERVAL_MS: u64 = 2000;
+const DEFAULT_MAX_BLOCK_RANGE: u64 = 1000;
+const DEFAULT_RESUBSCRIBE_SECS: u64 = 30;
+
+/// Notification about a poll, pushed to the live subscribers
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PollEvent {
+    VoteCast {
+        poll_id: U256,
+        voter: Address,
+        option: String,
+        block_number: Option<u64>,
+        transaction_hash: Option<TxHash>,
+        tallies: Option<Vec<Tally>>, // Tallies after the vote, if they could be read
+    },
+    PollEnded {
+        poll_id: U256,
+        reason: String,
+        block_number: Option<u64>,
+        transaction_hash: Option<TxHash>,
+    },
+    PollFinalized {
+        poll_id: U256,
+        winner: String,
+        block_number: Option<u64>,
+        transaction_hash: Option<TxHash>,
+        tallies: Option<Vec<Tally>>,
+    },
+}
+
+impl PollEvent {
+    pub fn poll_id(&self) -> U256 {
+        match self {
+            PollEvent::VoteCast { poll_id, .. }
+            | PollEvent::PollEnded { poll_id, .. }
+            | PollEvent::PollFinalized { poll_id, .. } => *poll_id,
+        }
+    }
+
+    /// Name of the event, used as SSE event type
+    pub fn name(&self) -> &'static str {
+        match self {
+            PollEvent::VoteCast { .. } => "vote_cast",
+            PollEvent::PollEnded { .. } => "poll_ended",
+            PollEvent::PollFinalized { .. } => "poll_finalized",
+        }
+    }
+}
+
+/// Fan-out of the poll events observed on chain to the SSE / WebSocket subscribers
+pub struct EventHub {
+    sender: broadcast::Sender<PollEvent>,
+    last_block: AtomicU64, // Last block whose logs were processed, 0 before the first one
+    subscribed: AtomicBool, // Logs are pushed over WebSocket instead of polled
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+            last_block: AtomicU64::new(0),
+            subscribed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl EventHub {
+    /// Receive the events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<PollEvent> {
+        self.sender.subscribe()
+    }
+
+    fn publish(&self, event: PollEvent) {
+        // Sending only fails when nobody is listening
+        let _ = self.sender.send(event);
+    }
+
+    fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Last block whose logs were processed by `watch`, None before the first one. Over
+    /// WebSocket, only blocks containing events of the contract are seen
+    pub fn last_block(&self) -> Option<u64> {
+        match self.last_block.load(Ordering::Relaxed) {
+            0 => None,
+            block => Some(block),
+        }
+    }
+
+    /// Whether logs are received over WebSocket rather than polled
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed.load(Ordering::Relaxed)
+    }
+
+    fn processed(&self, block: u64) {
+        self.last_block.fetch_max(block, Ordering::Relaxed);
+    }
+}
+
+/// Watch the contract events and publish them to the hub. Logs are received from the
+/// WebSocket endpoint when configured, and fetched by polling the RPC nodes otherwise.
+/// When the subscription breaks, logs are polled and the subscription is attempted again
+/// every EVENTS_RESUBSCRIBE_SECS
+pub async fn watch(app_state: web::Data<AppState>) {
+    let contract = &app_state.contracts.votechain;
+    let resubscribe = Duration::from_secs(config::parse_or(
+        "EVENTS_RESUBSCRIBE_SECS",
+        DEFAULT_RESUBSCRIBE_SECS,
+    ));
+
+    loop {
+        let Some(subscription) = contract.subscribe_events().await else {
+            // No WebSocket endpoint, poll forever
+            return poll(&app_state, None).await;
+        };
+
+        match subscription {
+            Ok(subscription) => {
+                info!("Watching contract events over WebSocket");
+                app_state.events.subscribed.store(true, Ordering::Relaxed);
+                let mut stream = subscription.into_stream();
+                while let Some(log) = stream.next().await {
+                    let block = log.block_number;
+                    handle(&app_state, log).await;
+                    if let Some(block) = block {
+                        app_state.events.processed(block);
+                    }
+                }
+                app_state.events.subscribed.store(false, Ordering::Relaxed);
+                contract.reset_subscriptions().await;
+                warn!("Contract event subscription closed. Polling until it is back.");
+            }
+            Err(e) => debug!(
+                "Unable to subscribe to contract events ({}). Polling until it is back.",
+                e
+            ),
+        }
+
+        poll(&app_state, Some(resubscribe)).await;
+    }
+}
+
+// Fetch the new logs every EVENTS_POLL_INTERVAL_MS, for `duration` or forever
+async fn poll(app_state: &web::Data<AppState>, duration: Option<Duration>) {
+    let contract = &app_state.contracts.votechain;
+    let interval = Duration::from_millis(config::parse_or(
+        "EVENTS_POLL_INTERVAL_MS",
+        DEFAULT_POLL_INTERVAL_MS,
+    ));
+    let max_range = config::parse_or("EVENTS_MAX_BLOCK_RANGE", DEFAULT_MAX_BLOCK_RANGE).max(1);
+    let deadline = duration.map(|duration| tokio::time::Instant::now() + duration);
+
+    // Resume after the last processed block, or only push new events from the current head
+    let mut next_block = app_state.events.last_block().map(|block| block + 1);
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        if deadline.is_some_and(|deadline| tokio::time::Instant::now() >= deadline) {
+            return;
+        }
+
+        let result: Result<(), ContractError> = async {
+            let latest = contract.block_number().await?;
+            let from = *next_block.get_or_insert(latest + 1);
+            if from > latest {
+                return Ok(());
+            }
+
+            // Large gaps (e.g. after an outage) are fetched in several ranges
+            let to = latest.min(from + max_range - 1);
+            for log in contract.get_events(from, to).await? {
+                handle(app_state, log).await;
+            }
+            next_block = Some(to + 1);
+            app_state.events.processed(to);
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            debug!("Unable to fetch contract events: {}", e);
+        }
+    }
+}
+
+// Decode a log and publish the matching event
+async fn handle(app_state: &AppState, log: Log) {
+    // Cached data of the poll is outdated. Relayed transactions were already invalidated
+    // on confirmation, this also covers other replicas and reads from lagging nodes
+    if let Some(block) = log.block_number {
+        app_state.cache.observe_block(block);
+    }
+    if let Some(poll_id) = changed_poll(&log) {
+        app_state.cache.invalidate(poll_id).await;
+    }
+
+    let hub = &app_state.events;
+    if !hub.has_subscribers() {
+        return;
+    }
+
+    let block_number = log.block_number;
+    let transaction_hash = log.transaction_hash;
+
+    let event = if let Ok(vote) = log.log_decode::<VOTECHAIN::VoteCast>() {
+        let vote = vote.inner.data;
+        PollEvent::VoteCast {
+            poll_id: vote.poll_id,
+            voter: vote.voter,
+            option: vote.option,
+            block_number,
+            transaction_hash,
+            tallies: tallies(app_state, vote.poll_id).await,
+        }
+    } else if let Ok(ended) = log.log_decode::<VOTECHAIN::PollEnded>() {
+        let ended = ended.inner.data;
+        PollEvent::PollEnded {
+            poll_id: ended.poll_id,
+            reason: ended.reason,
+            block_number,
+            transaction_hash,
+        }
+    } else if let Ok(finalized) = log.log_decode::<VOTECHAIN::PollFinalized>() {
+        let finalized = finalized.inner.data;
+        PollEvent::PollFinalized {
+            poll_id: finalized.poll_id,
+            winner: finalized.winner,
+            block_number,
+            transaction_hash,
+            tallies: tallies(app_state, finalized.poll_id).await,
+        }
+    } else {
+        // Other events are not pushed to clients
+        return;
+    };
+
+    hub.publish(event);
+}
+
+// Poll changed by a vote, or by being ended / finalized
+fn changed_poll(log: &Log) -> Option<U256> {
+    let signature = *log.topic0()?;
+    let changes = [
+        VOTECHAIN::VoteCast::SIGNATURE_HASH,
+        VOTECHAIN::PollEnded::SIGNATURE_HASH,
+        VOTECHAIN::PollFinalized::SIGNATURE_HASH,
+    ];
+
+    // The poll id is the first indexed parameter of these events
+    match changes.contains(&signature) {
+        true => log.topics().get(1).map(|id| U256::from_be_bytes(id.0)),
+        false => None,
+    }
+}
+
+// Current tallies of a poll. Failures only omit them from the event
+async fn tallies(app_state: &AppState, poll_id: U256) -> Option<Vec<Tally>> {
+    match app_state.contracts.votechain.get_tallies(poll_id).await {
+        Ok(tallies) => Some(tallies),
+        Err(e) => {
+            warn!("Unable to read tallies of poll {}: {}", poll_id, e);
+            None
+        }
+    }
+}
+
+/// Events of a single poll for a subscriber, until its access token expires
+pub struct Listener {
+    receiver: broadcast::Receiver<PollEvent>,
+    poll_id: U256,
+    expires: Pin<Box<Sleep>>,
+}
+
+/// What a subscriber gets next
+pub enum Received {
+    Event(PollEvent),
+    Missed(u64), // Events dropped because the subscriber was too slow
+}
+
+impl Listener {
+    pub fn new(app_state: &AppState, poll_id: U256, claims: &Claims) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let remaining = (claims.exp as u64).saturating_sub(now);
+
+        Self {
+            receiver: app_state.events.subscribe(),
+            poll_id,
+            expires: Box::pin(tokio::time::sleep(Duration::from_secs(remaining))),
+        }
+    }
+
+    /// Wait for the next event of the poll. `None` once the token expired (the client must
+    /// reconnect with a fresh one) or the server stops
+    pub async fn next(&mut self) -> Option<Received> {
+        loop {
+            tokio::select! {
+                received = self.receiver.recv() => match received {
+                    Ok(event) if event.poll_id() == self.poll_id => return Some(Received::Event(event)),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => return Some(Received::Missed(missed)),
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = &mut self.expires => return None,
+            }
+        }
+    }
+}
+
+impl Received {
+    /// Name of the message, used as SSE event type
+    pub fn name(&self) -> &'static str {
+        match self {
+            Received::Event(event) => event.name(),
+            Received::Missed(_) => "missed",
+        }
+    }
+
+    /// JSON payload of the message
+    pub fn to_json(&self) -> String {
+        match self {
+            Received::Event(event) => serde_json::to_string(event).unwrap_or_default(),
+            Received::Missed(missed) => {
+                serde_json::json!({ "type": "missed", "count": missed }).to_string()
+            }
+        }
+    }
+}
+
+/// Current state of a poll, sent first to new subscribers
+pub async fn snapshot(app_state: &AppState, poll_id: U256) -> Result<String, ContractError> {
+    let contract = &app_state.contracts.votechain;
+    let poll = contract.get_poll(poll_id).await?;
+    let tallies = contract.get_tallies(poll_id).await.ok();
+
+    Ok(serde_json::json!({
+        "type": "snapshot",
+        "poll_id": poll_id,
+        "is_ended": poll.is_ended,
+        "winner": poll.winner,
+        "tallies": tallies,
+    })
+    .to_string())
+}
@@ -1,17 +1,33 @@
 mod auth;
+mod cache;
+mod commit_reveal;
 mod config;
 mod contracts;
 mod db;
+mod drafts;
+mod eligibility;
 mod errors;
+mod events;
+mod merkle;
+mod metadata;
+mod metrics;
 mod middlewares;
 mod models;
+mod owners;
+mod rate_limit;
+mod receipts;
+mod relay;
+mod retry;
 mod routes;
+mod rpc;
+mod scheduler;
 mod schema;
+mod shutdown;
+mod telemetry;
+mod timestamp;
+mod validation;
 
-use std::{
-    str::FromStr,
-    sync::{Arc, Mutex},
-};
+use std::str::FromStr;
 
 use crate::config::load_env;
 use actix_web::{
@@ -28,19 +44,28 @@ use alloy::{
         },
         Identity, Provider, ProviderBuilder, RootProvider,
     },
+    rpc::client::RpcClient,
     signers::local::{LocalSigner, PrivateKeySigner},
     sol,
-    transports::http::{reqwest::Url, Client, Http},
 };
 use auth::JwtManager;
-use contracts::votechain::VotechainContract;
-use diesel::PgConnection;
-use log::{debug, info};
+use contracts::{
+    multicall::{self, Multicall},
+    votechain::VotechainContract,
+};
+use db::DbPool;
+use log::{debug, error, info, warn};
+use rpc::FailoverTransport;
 
 pub struct AppState {
     jwt_manager: JwtManager,
-    connection: Arc<Mutex<PgConnection>>,
+    db: DbPool,
     contracts: Contracts,
+    events: events::EventHub,
+    cache: cache::PollCache,
+    trees: merkle::TreeCache,
+    receipts: receipts::ReceiptSigner,
+    verify_limiter: rate_limit::RateLimiter,
 }
 
 // Codegen from ABI file to interact with the contract.
@@ -52,7 +77,7 @@ sol!(
 );
 
 pub type VotechainContractInstance = VOTECHAIN::VOTECHAINInstance<
-    Http<Client>,
+    FailoverTransport,
     FillProvider<
         JoinFill<
             JoinFill<
@@ -61,16 +86,26 @@ pub type VotechainContractInstance = VOTECHAIN::VOTECHAINInstance<
             >,
             WalletFiller<EthereumWallet>,
         >,
-        RootProvider<Http<Client>>,
-        Http<Client>,
+        RootProvider<FailoverTransport>,
+        FailoverTransport,
         Ethereum,
     >,
 >;
 
+// Read-only instance, used for critical reads that may require a quorum of RPC nodes
+pub type VotechainReaderInstance =
+    VOTECHAIN::VOTECHAINInstance<FailoverTransport, RootProvider<FailoverTransport>>;
+
 struct Contracts {
     votechain: VotechainContract,
 }
 
+// Default size limit of plain bodies (about 200k addresses in an allowlist)
+const DEFAULT_MAX_UPLOAD_BYTES: usize = 8 * 1024 * 1024;
+
+// Default number of receipts a client may verify per minute
+const DEFAULT_RECEIPT_VERIFY_RATE_LIMIT: u32 = 30;
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Load environment variables from dotenv file
@@ -81,8 +116,8 @@ async fn main() -> std::io::Result<()> {
         std::process::exit(1);
     }
 
-    // initialize logger
-    env_logger::init();
+    // initialize logger + tracing (exported via OTLP when configured)
+    let tracer_provider = telemetry::init();
     debug!("Logger initialized!");
 
     // Create JwtManager to handle JWT stuff
@@ -91,84 +126,275 @@ async fn main() -> std::io::Result<()> {
         std::env::var("JWT_REFRESH_SECRET").unwrap(),
     );
 
-    // Crete connection with database
+    // Number of attempts to reach each dependency before starting in degraded mode
+    let startup_attempts = config::parse_or("STARTUP_RETRY_ATTEMPTS", 5u32);
+
+    // Crete connection pool with database
     info!("Establishing connection with database...");
 
-    // Establish connection with database + wrap in atomic reference to mutex
-    // NOTE: This is needed as we will be sharing the connection across threads
-    let connection = Arc::new(Mutex::new(db::establish_connection()));
+    // NOTE: The pool is shared across threads, connections are (re)established on demand
+    let db = db::create_pool();
+    let db_status = retry::with_backoff("Database connection", startup_attempts, || async {
+        db.get().map(|_| ())
+    })
+    .await;
+    match db_status {
+        Ok(_) => info!("Database connection successful."),
+        Err(e) => warn!(
+            "Database unavailable ({}). Starting in degraded mode, will reconnect on demand.",
+            e
+        ),
+    }
 
     // Get chain id from config file
     let chain_id = std::env::var("CHAIN_ID").unwrap().parse::<u64>().unwrap();
 
-    // Get RPC endpoint from config file
-    let rpc_endpoint = std::env::var("RPC_URL").unwrap();
-    // Now, create address from the endpoint
-    let rpc_address = Url::from_str(&rpc_endpoint).expect("Invalid RPC endpoint");
+    // Get RPC endpoints from config file (comma separated, tried in order of health)
+    let rpc_endpoints =
+        rpc::parse_urls(&std::env::var("RPC_URL").unwrap()).expect("Invalid RPC_URL");
+    if rpc_endpoints.is_empty() {
+        panic!("RPC_URL must contain at least one endpoint");
+    }
+    info!("Using {} RPC endpoint(s)", rpc_endpoints.len());
+    let transport =
+        FailoverTransport::new(rpc_endpoints, config::parse_or("RPC_RETRY_ROUNDS", 2u32));
 
     let signer: PrivateKeySigner =
         LocalSigner::from_str(&std::env::var("RELAY_WALLET_PRIVATE_KEY").unwrap())
             .expect("Invalid private key");
+
+    // Receipts of the votes are signed with RECEIPT_SIGNING_KEY, or the relay key by default
+    let receipt_signer = match std::env::var("RECEIPT_SIGNING_KEY") {
+        Ok(key) if !key.is_empty() => {
+            LocalSigner::from_str(&key).expect("Invalid RECEIPT_SIGNING_KEY")
+        }
+        _ => {
+            warn!(
+                "RECEIPT_SIGNING_KEY is not set, vote receipts are signed with the relay wallet \
+                 key. Set a dedicated key in production."
+            );
+            signer.clone()
+        }
+    };
+    info!("Signing vote receipts as {}", receipt_signer.address());
     let wallet = EthereumWallet::from(signer);
 
     let provider = ProviderBuilder::new()
         .with_recommended_fillers()
         .wallet(wallet)
-        .on_http(rpc_address);
+        .on_client(RpcClient::new(transport.clone(), false));
+
+    // Critical reads must be confirmed by RPC_QUORUM nodes
+    let quorum = config::parse_or("RPC_QUORUM", 1usize);
+    let reader = RootProvider::new(RpcClient::new(transport.with_quorum(quorum), false));
 
     // Ask the chain for its chain id
-    let actual_chain_id = provider
-        .get_chain_id()
-        .await
-        .expect("Failed to get chain id");
-    info!("Connection successful. Got chain id: {}", actual_chain_id);
+    let actual_chain_id = retry::with_backoff("RPC connection", startup_attempts, || async {
+        provider.get_chain_id().await
+    })
+    .await;
 
     // Ensure that the chain Id matches the one in the config
-    if actual_chain_id != chain_id {
-        panic!(
-            "Chain ID mismatch. Expected: {}, Got: {}",
-            chain_id, actual_chain_id
-        );
-    } else {
-        info!("Chain ID matches the one in the config file. Proceeding...");
-    }
+    let rpc_available = match actual_chain_id {
+        Ok(actual_chain_id) if actual_chain_id != chain_id => {
+            return Err(std::io::Error::other(format!(
+                "Chain ID mismatch. Expected: {}, Got: {}",
+                chain_id, actual_chain_id
+            )));
+        }
+        Ok(actual_chain_id) => {
+            info!("Connection successful. Got chain id: {}", actual_chain_id);
+            info!("Chain ID matches the one in the config file. Proceeding...");
+            true
+        }
+        Err(e) => {
+            warn!(
+                "RPC node unavailable ({}). Starting in degraded mode, will keep retrying.",
+                e
+            );
+            false
+        }
+    };
 
     // Load the contract address from the .env
     let contract_address =
         Address::parse_checksummed(std::env::var("VOTECHAIN_CONTRACT_ADDRESS").unwrap(), None)
             .expect("Invalid contract address. Ensure it is a valid hex string with checksum");
 
+    // Optional WebSocket endpoint, used for subscriptions (connected again when it drops)
+    let ws = match std::env::var("RPC_WS_URL") {
+        Ok(url) if !url.is_empty() => {
+            let ws = rpc::WsEndpoint::new(url);
+            if let Err(e) = ws.provider().await {
+                warn!(
+                    "Unable to connect to RPC_WS_URL ({}). Polling events until it is back.",
+                    e
+                );
+            }
+            Some(ws)
+        }
+        _ => None,
+    };
+
     // get the contract instance
     let contract_instance = VOTECHAIN::new(contract_address, provider);
+    let reader_instance = VOTECHAIN::new(contract_address, reader);
     // wrap instance in our contract struct to access useful information
-    let votechain_contract = contracts::votechain::VotechainContract::new(contract_instance);
+    let votechain_contract = contracts::votechain::VotechainContract::new(
+        contract_instance,
+        reader_instance,
+        ws,
+        config::parse_or("VOTECHAIN_DEPLOY_BLOCK", 0u64),
+        Multicall::new(
+            config::parse_or("MULTICALL3_ADDRESS", multicall::DEFAULT_ADDRESS),
+            config::parse_or("MULTICALL_BATCH_SIZE", multicall::DEFAULT_BATCH_SIZE),
+        ),
+    );
 
     // Build application state
     let app_state = web::Data::new(AppState {
         jwt_manager,
-        connection,
+        db,
         contracts: Contracts {
             votechain: votechain_contract,
         },
+        events: events::EventHub::default(),
+        cache: cache::PollCache::new().await,
+        trees: merkle::TreeCache::default(),
+        receipts: receipts::ReceiptSigner::new(receipt_signer),
+        verify_limiter: rate_limit::RateLimiter::new(
+            config::parse_or(
+                "RECEIPT_VERIFY_RATE_LIMIT",
+                DEFAULT_RECEIPT_VERIFY_RATE_LIMIT,
+            ),
+            std::time::Duration::from_secs(60),
+        ),
     });
 
+    // Verify the chain in the background: requests needing the node fail with 503 until it
+    // is back, and if it is on another chain, relays are refused and readiness fails
+    if !rpc_available {
+        actix_web::rt::spawn(verify_chain_id(app_state.clone(), chain_id));
+    }
+
+    // Settle the relayed transactions left unconfirmed, by requests or by a shutdown
+    actix_web::rt::spawn(relay::reconcile_jobs(app_state.clone()));
+
+    // Drop the expired entries of the in-memory cache
+    let cache_state = app_state.clone();
+    actix_web::rt::spawn(async move { cache_state.cache.sweep().await });
+
+    // Push the contract events to the live subscribers
+    actix_web::rt::spawn(events::watch(app_state.clone()));
+
+    // Keep the relay wallet balance gauge up to date
+    actix_web::rt::spawn(metrics::watch_relay_balance(app_state.clone()));
+
+    // End and finalize polls once their end time has passed
+    if config::parse_or("SCHEDULER_ENABLED", true) {
+        actix_web::rt::spawn(scheduler::Scheduler::new(app_state.clone()).run());
+    }
+
+    // Size limit of plain bodies, such as allowlist uploads
+    let max_upload_bytes = config::parse_or("MAX_UPLOAD_BYTES", DEFAULT_MAX_UPLOAD_BYTES);
+
+    // Kept to drain relays on shutdown, then dropped to close database connections
+    let state = app_state.clone();
+
     // Start ActiveX web server
     info!("Starting Actix Web server...");
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
-            .wrap(Logger::default())
+            // Default format, without query strings (they may carry an `access_token`)
+            .wrap(
+                Logger::new(r#"%a "%{request_line}xi" %s %b "%{Referer}i" "%{User-Agent}i" %T"#)
+                    .custom_request_replace("request_line", |req| {
+                        format!("{} {} {:?}", req.method(), req.path(), req.version())
+                    }),
+            )
             .app_data(app_state.clone()) // pass state to entire application
             .wrap(DefaultHeaders::new().add(("X-Server", "VoteChain-API"))) // add default headers
             .wrap(from_fn(crate::middlewares::auth::ensure_auth))
+            .wrap(from_fn(crate::middlewares::trace::trace_requests)) // request spans
+            .wrap(from_fn(crate::middlewares::request_id::assign_request_id)) // tag requests + render errors
+            .wrap(from_fn(crate::middlewares::metrics::track_requests)) // collect request metrics
+            .app_data(web::JsonConfig::default().error_handler(errors::json_error_handler))
+            .app_data(web::PathConfig::default().error_handler(errors::path_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(errors::query_error_handler))
+            .app_data(web::PayloadConfig::new(max_upload_bytes))
             .service(crate::routes::health::route) // health route
+            .service(crate::routes::health::live::route) // liveness probe
+            .service(crate::routes::health::ready::route) // readiness probe (checks dependencies)
+            .service(crate::routes::metrics::route) // prometheus metrics
             .service(crate::routes::auth::signin::route) // sign up / login route
             .service(crate::routes::auth::refresh::route) // token refresh route
             .service(crate::routes::polls::get_poll::route) // Route to get a poll
             .service(crate::routes::polls::get_polls::route) // Route to get all available polls
             .service(crate::routes::polls::create::route) // Route to create a poll in the contract
             .service(crate::routes::polls::cast_vote::route)
+            .service(crate::routes::polls::end::route) // Route to end a poll (owner / admin)
+            .service(crate::routes::polls::finalize::route) // Route to finalize a poll (owner / admin)
+            .service(crate::routes::polls::results::route) // Per-option tallies of a poll
+            .service(crate::routes::polls::metadata::route) // Off-chain metadata of a poll, as hashed
+            .service(crate::routes::polls::eligibility::route) // Whether an address may vote in a poll
+            .service(crate::routes::polls::set_eligibility::route) // Upload the allowlist of a poll (owner / admin)
+            .service(crate::routes::polls::clear_eligibility::route) // Remove the allowlist of a poll (owner / admin)
+            .service(crate::routes::polls::proof::route) // Merkle proof of a voter
+            .service(crate::routes::polls::nonces::route) // Used nonces of a voter, read in a batch
+            .service(crate::routes::polls::commit::route) // Commit a vote (commit-reveal polls)
+            .service(crate::routes::polls::reveal::route) // Reveal and relay a committed vote
+            .service(crate::routes::polls::commitments::route) // Phase and progress of a commit-reveal poll
+            .service(crate::routes::polls::commitment::route) // Commitment status of a voter
+            .service(crate::routes::allowlists::create::route) // Upload a Merkle allowlist
+            .service(crate::routes::allowlists::get::route) // Addresses of a Merkle allowlist
+            .service(crate::routes::polls::events::route) // Live poll events (SSE)
+            .service(crate::routes::polls::websocket::route) // Live poll events (WebSocket)
+            .service(crate::routes::receipts::verify::route) // Check a receipt against the chain and the server key
+            .service(crate::routes::receipts::get::route) // Receipt of a vote (voter / admin)
+            .service(crate::routes::drafts::list::route) // Drafts of the user (all for admins)
+            .service(crate::routes::drafts::create::route) // Save a new draft poll
+            .service(crate::routes::drafts::get::route) // Get a draft
+            .service(crate::routes::drafts::update::route) // Edit a draft (author)
+            .service(crate::routes::drafts::delete::route) // Delete a draft (author)
+            .service(crate::routes::drafts::preview::route) // Poll a draft would create, with its problems
+            .service(crate::routes::drafts::approve::route) // Approve a draft (another admin)
+            .service(crate::routes::drafts::publish::route) // Create the poll of a draft on-chain
+            .default_service(web::to(errors::not_found)) // problem details for unknown routes
     })
     .bind(("127.0.0.1", 1234))?
-    .run()
-    .await
+    .disable_signals() // handled by `shutdown::on_signal`, draining relays first
+    .shutdown_timeout(shutdown::HTTP_SHUTDOWN_TIMEOUT_SECS)
+    .run();
+
+    actix_web::rt::spawn(shutdown::on_signal(server.handle(), state.clone()));
+    let result = server.await;
+
+    // Close the idle database connections (busy ones were released with the workers)
+    drop(state);
+    info!("Database connections closed.");
+
+    // Flush spans that are still buffered (metrics are scraped, nothing to flush)
+    telemetry::shutdown(tracer_provider);
+    info!("Shutdown complete.");
+
+    result
+}
+
+// Keep asking the RPC node for its chain id until it answers, then ensure it is the
+// configured one
+async fn verify_chain_id(app_state: web::Data<AppState>, chain_id: u64) {
+    let contract = &app_state.contracts.votechain;
+    match retry::with_backoff("RPC connection", u32::MAX, || contract.chain_id()).await {
+        Ok(actual) if actual == chain_id => {
+            info!("RPC node is back. Chain ID {} matches the config.", actual);
+        }
+        Ok(actual) => {
+            error!(
+                "Chain ID mismatch. Expected: {}, Got: {}. Relaying is disabled until restart.",
+                chain_id, actual
+            );
+            contract.relay().refuse_chain(actual);
+        }
+        Err(e) => error!("Unable to reach the RPC node: {}", e),
+    }
 }
@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use alloy::primitives::{keccak256, Address, B256};
+
+// Trees kept in memory at most, the oldest are rebuilt from Postgres when needed again
+const MAX_CACHED_TREES: usize = 64;
+
+/// Merkle tree of voter addresses, compatible with OpenZeppelin's `MerkleProof`: leaves are
+/// `keccak256(abi.encodePacked(address))`, sorted, and pairs are hashed in sorted order.
+/// A node without sibling is moved up unchanged
+pub struct MerkleTree {
+    layers: Vec<Vec<B256>>, // Leaves first, root last
+}
+
+impl MerkleTree {
+    pub fn new(addresses: &[Address]) -> Self {
+        let mut leaves: Vec<B256> = addresses.iter().map(|address| leaf(*address)).collect();
+        leaves.sort();
+        leaves.dedup();
+
+        let mut layers = vec![leaves];
+        while let Some(layer) = layers.last().filter(|layer| layer.len() > 1) {
+            let next = layer
+                .chunks(2)
+                .map(|pair| match *pair {
+                    [left, right] => hash_pair(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Self { layers }
+    }
+
+    /// Root of the tree, zero when empty
+    pub fn root(&self) -> B256 {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Sibling hashes from the leaf of `address` up to the root, if it is in the tree
+    pub fn proof(&self, address: Address) -> Option<Vec<B256>> {
+        let mut index = self.layers.first()?.binary_search(&leaf(address)).ok()?;
+
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+/// Trees of the uploaded allowlists, by root. An allowlist never changes once uploaded, so
+/// its tree is built once instead of on every proof request
+#[derive(Default)]
+pub struct TreeCache {
+    trees: Mutex<HashMap<B256, Arc<MerkleTree>>>,
+}
+
+impl TreeCache {
+    pub fn get(&self, root: B256) -> Option<Arc<MerkleTree>> {
+        self.trees
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&root)
+            .cloned()
+    }
+
+    pub fn insert(&self, tree: Arc<MerkleTree>) {
+        let mut trees = self.trees.lock().unwrap_or_else(PoisonError::into_inner);
+        // Bounded: start over rather than tracking the usage of each tree
+        if trees.len() >= MAX_CACHED_TREES {
+            trees.clear();
+        }
+        trees.insert(tree.root(), tree);
+    }
+}
+
+/// Whether `proof` shows that `address` is a leaf of the tree with this `root`
+pub fn verify(root: B256, address: Address, proof: &[B256]) -> bool {
+    proof
+        .iter()
+        .fold(leaf(address), |node, sibling| hash_pair(node, *sibling))
+        == root
+}
+
+fn leaf(address: Address) -> B256 {
+    keccak256(address)
+}
+
+fn hash_pair(a: B256, b: B256) -> B256 {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    keccak256([first.as_slice(), second.as_slice()].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addresses(count: u8) -> Vec<Address> {
+        (1..=count).map(Address::repeat_byte).collect()
+    }
+
+    // Every member proves its inclusion, and the proof only holds for that member
+    fn assert_round_trips(tree: &MerkleTree, members: &[Address]) {
+        for member in members {
+            let proof = tree.proof(*member).unwrap();
+            assert!(verify(tree.root(), *member, &proof));
+            assert!(!verify(tree.root(), Address::repeat_byte(0xff), &proof));
+        }
+    }
+
+    #[test]
+    fn single_leaf() {
+        let members = addresses(1);
+        let tree = MerkleTree::new(&members);
+        assert_eq!(tree.root(), leaf(members[0]));
+        assert_eq!(tree.proof(members[0]).unwrap(), Vec::<B256>::new());
+        assert_round_trips(&tree, &members);
+    }
+
+    #[test]
+    fn even_and_odd_counts() {
+        for count in [2, 3, 5, 8] {
+            let members = addresses(count);
+            assert_round_trips(&MerkleTree::new(&members), &members);
+        }
+    }
+
+    #[test]
+    fn order_and_duplicates_do_not_change_root() {
+        let members = addresses(5);
+        let mut shuffled = members.clone();
+        shuffled.reverse();
+        shuffled.push(members[0]);
+        assert_eq!(
+            MerkleTree::new(&members).root(),
+            MerkleTree::new(&shuffled).root()
+        );
+    }
+
+    #[test]
+    fn non_member_has_no_proof() {
+        let tree = MerkleTree::new(&addresses(3));
+        let outsider = Address::repeat_byte(0xff);
+        assert!(tree.proof(outsider).is_none());
+        assert!(!verify(tree.root(), outsider, &[]));
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = MerkleTree::new(&[]);
+        assert_eq!(tree.root(), B256::ZERO);
+        assert!(tree.proof(Address::repeat_byte(1)).is_none());
+    }
+
+    #[test]
+    fn cache_is_bounded() {
+        let cache = TreeCache::default();
+        let first = Arc::new(MerkleTree::new(&addresses(1)));
+        cache.insert(first.clone());
+        assert!(cache.get(first.root()).is_some());
+
+        for i in 2..=MAX_CACHED_TREES as u8 + 1 {
+            cache.insert(Arc::new(MerkleTree::new(&addresses(i))));
+        }
+        assert!(cache.get(first.root()).is_none());
+    }
+}
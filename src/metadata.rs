@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{keccak256, Address, B256, U256};
+use diesel::{PgConnection, QueryResult};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    contracts::votechain::Poll, errors::ApiErrorResponse,
+    models::poll_metadata::PollMetadataRecord, timestamp::Timestamp,
+};
+
+// Last line of the on-chain description committing to the metadata of the poll
+pub const COMMITMENT_PREFIX: &str = "metadata:keccak256:";
+
+/// Characters added to the description by `commit`: a line break, the prefix and the hash
+/// (0x prefixed hex)
+pub const COMMITMENT_LENGTH: usize = 1 + COMMITMENT_PREFIX.len() + 66;
+
+/// Off-chain content of a poll, stored in Postgres. Its hash is committed on-chain
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PollMetadata {
+    pub body: Option<String>,        // Markdown
+    pub cover_image: Option<String>, // URL
+    #[serde(default)]
+    pub options: Vec<OptionMetadata>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub links: Vec<Link>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowlist_root: Option<B256>, // Merkle root of the voters, see `POST /allowlists`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_gate: Option<TokenGate>, // Voting restricted to the holders of a token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_reveal: Option<CommitReveal>, // Votes committed first, revealed after the deadline
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OptionMetadata {
+    pub option: String, // Option of the poll described
+    pub description: Option<String>,
+    pub image: Option<String>, // URL
+}
+
+/// Minimum balance of an ERC-20 / ERC-721 token required to vote, at a snapshot block
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenGate {
+    pub token: Address,
+    pub min_balance: U256, // In the smallest unit of the token
+    pub snapshot_block: u64,
+}
+
+/// Commit-reveal voting: votes are committed as salted hashes until `commit_end_time`, then
+/// revealed and relayed until the end time of the poll
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommitReveal {
+    pub commit_end_time: Timestamp,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Link {
+    pub label: String,
+    pub url: String,
+}
+
+impl PollMetadata {
+    /// Document to store, and its hash
+    pub fn to_record(&self) -> PollMetadataRecord {
+        // Serialization of plain structs cannot fail
+        let content = serde_json::to_string(self).unwrap_or_default();
+        PollMetadataRecord {
+            hash: keccak256(&content).to_string(),
+            content,
+            created_at: Some(std::time::SystemTime::now()),
+        }
+    }
+}
+
+/// Description stored on-chain: the plain description, followed by the metadata hash
+pub fn commit(description: &str, hash: &str) -> String {
+    match description.is_empty() {
+        true => format!("{}{}", COMMITMENT_PREFIX, hash),
+        false => format!("{}\n{}{}", description, COMMITMENT_PREFIX, hash),
+    }
+}
+
+/// Split an on-chain description into the plain description and the committed hash
+pub fn split(description: &str) -> (String, Option<B256>) {
+    let (text, last_line) = match description.rsplit_once('\n') {
+        Some((text, last_line)) => (text, last_line),
+        None => ("", description),
+    };
+
+    match last_line
+        .strip_prefix(COMMITMENT_PREFIX)
+        .and_then(|hash| hash.parse().ok())
+    {
+        Some(hash) => (text.to_owned(), Some(hash)),
+        None => (description.to_owned(), None),
+    }
+}
+
+/// Poll as returned by the API: on-chain data merged with its metadata
+#[derive(Serialize)]
+pub struct PollView {
+    #[serde(flatten)]
+    pub poll: Poll,
+    pub metadata: Option<PollMetadata>,
+}
+
+impl PollView {
+    /// Metadata the poll commits to. Fails when it is committed but missing or altered:
+    /// restrictions it sets (allowlist, token gate, commit-reveal) must not be skipped
+    pub fn committed_metadata(&self) -> Result<Option<&PollMetadata>, ApiErrorResponse> {
+        match (self.poll.metadata_hash, &self.metadata) {
+            (Some(hash), None) => {
+                error!(
+                    "Metadata {} of poll {} is unavailable, refusing to check its restrictions",
+                    hash, self.poll.id
+                );
+                Err(ApiErrorResponse::InternalServerError)
+            }
+            (_, metadata) => Ok(metadata.as_ref()),
+        }
+    }
+}
+
+/// Attach its metadata to a poll. Metadata that is missing or does not match the committed
+/// hash is left out
+pub fn attach(conn: &mut PgConnection, poll: Poll) -> QueryResult<PollView> {
+    let record = match poll.metadata_hash {
+        Some(hash) => PollMetadataRecord::find(conn, &hash.to_string())?,
+        None => None,
+    };
+
+    let metadata = record.and_then(|record| verify(&poll, &record));
+    Ok(PollView { poll, metadata })
+}
+
+/// Same as `attach` for several polls, read at once
+pub fn merge(conn: &mut PgConnection, polls: Vec<Poll>) -> QueryResult<Vec<PollView>> {
+    let hashes: Vec<String> = polls
+        .iter()
+        .filter_map(|poll| poll.metadata_hash)
+        .map(|hash| hash.to_string())
+        .collect();
+
+    let records: HashMap<String, PollMetadataRecord> = match hashes.is_empty() {
+        true => HashMap::new(),
+        false => PollMetadataRecord::find_all(conn, &hashes)?
+            .into_iter()
+            .map(|record| (record.hash.clone(), record))
+            .collect(),
+    };
+
+    Ok(polls
+        .into_iter()
+        .map(|poll| {
+            let metadata = poll
+                .metadata_hash
+                .and_then(|hash| records.get(&hash.to_string()))
+                .and_then(|record| verify(&poll, record));
+            PollView { poll, metadata }
+        })
+        .collect())
+}
+
+// Decode the stored content, if it still matches its hash
+fn verify(poll: &Poll, record: &PollMetadataRecord) -> Option<PollMetadata> {
+    if keccak256(&record.content).to_string() != record.hash {
+        warn!(
+            "Metadata of poll {} does not match its hash {}",
+            poll.id, record.hash
+        );
+        return None;
+    }
+
+    serde_json::from_str(&record.content)
+        .inspect_err(|e| warn!("Unable to decode metadata of poll {}: {}", poll.id, e))
+        .ok()
+}
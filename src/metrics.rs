@@ -0,0 +1,220 @@
+use std::{
+    future::IntoFuture,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use actix_web::web;
+use alloy::{primitives::utils::format_units, rpc::types::TransactionReceipt};
+use log::warn;
+use prometheus::{
+    register_counter_vec, register_gauge, register_histogram_vec, register_int_counter_vec,
+    register_int_gauge_vec, CounterVec, Encoder, Gauge, HistogramVec, IntCounterVec, IntGaugeVec,
+    TextEncoder,
+};
+
+use crate::{config, AppState};
+
+//
+// HTTP
+//
+
+pub static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "votechain_http_requests_total",
+        "Number of HTTP requests served, by route and status",
+        &["method", "route", "status"]
+    )
+    .expect("Failed to register votechain_http_requests_total")
+});
+
+pub static HTTP_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "votechain_http_request_duration_seconds",
+        "Latency of HTTP requests, by route and status",
+        &["method", "route", "status"]
+    )
+    .expect("Failed to register votechain_http_request_duration_seconds")
+});
+
+pub static AUTH_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "votechain_auth_failures_total",
+        "Number of rejected authentication attempts, by error code",
+        &["code"]
+    )
+    .expect("Failed to register votechain_auth_failures_total")
+});
+
+//
+// RPC + relay
+//
+
+pub static RPC_CALLS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "votechain_rpc_calls_total",
+        "Number of contract calls sent to the RPC node, by method and outcome",
+        &["method", "outcome"]
+    )
+    .expect("Failed to register votechain_rpc_calls_total")
+});
+
+pub static RPC_CALL_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "votechain_rpc_call_duration_seconds",
+        "Latency of contract calls sent to the RPC node, by method",
+        &["method"]
+    )
+    .expect("Failed to register votechain_rpc_call_duration_seconds")
+});
+
+pub static RPC_ENDPOINT_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "votechain_rpc_endpoint_failures_total",
+        "Number of transport failures of each RPC node, by host",
+        &["endpoint"]
+    )
+    .expect("Failed to register votechain_rpc_endpoint_failures_total")
+});
+
+pub static RPC_ENDPOINT_SCORE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "votechain_rpc_endpoint_score",
+        "Health score of each RPC node (latency + failure penalty, lower is better), by host",
+        &["endpoint"]
+    )
+    .expect("Failed to register votechain_rpc_endpoint_score")
+});
+
+pub static RELAYED_TRANSACTIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "votechain_relayed_transactions_total",
+        "Number of transactions relayed by the server wallet, by method and outcome",
+        &["method", "outcome"]
+    )
+    .expect("Failed to register votechain_relayed_transactions_total")
+});
+
+pub static RELAY_GAS_USED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "votechain_relay_gas_used_total",
+        "Gas units consumed by relayed transactions, by method",
+        &["method"]
+    )
+    .expect("Failed to register votechain_relay_gas_used_total")
+});
+
+pub static RELAY_FEES: LazyLock<CounterVec> = LazyLock::new(|| {
+    register_counter_vec!(
+        "votechain_relay_fees_eth_total",
+        "Fees paid by the relay wallet for relayed transactions (in ETH), by method",
+        &["method"]
+    )
+    .expect("Failed to register votechain_relay_fees_eth_total")
+});
+
+pub static RELAY_WALLET_BALANCE: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "votechain_relay_wallet_balance_eth",
+        "Balance of the relay wallet (in ETH), refreshed every RELAY_BALANCE_REFRESH_SECS"
+    )
+    .expect("Failed to register votechain_relay_wallet_balance_eth")
+});
+
+// Default refresh interval of the relay wallet balance
+const DEFAULT_RELAY_BALANCE_REFRESH_SECS: u64 = 60;
+
+/// Refresh the relay wallet balance every RELAY_BALANCE_REFRESH_SECS, so that scrapes
+/// never wait for (nor multiply) RPC reads
+pub async fn watch_relay_balance(app_state: web::Data<AppState>) {
+    let interval = config::parse_or(
+        "RELAY_BALANCE_REFRESH_SECS",
+        DEFAULT_RELAY_BALANCE_REFRESH_SECS,
+    );
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+    loop {
+        ticker.tick().await;
+        match app_state.contracts.votechain.relay_balance().await {
+            Ok(balance) => {
+                if let Ok(eth) = format_units(balance, "ether") {
+                    RELAY_WALLET_BALANCE.set(eth.parse().unwrap_or_default());
+                }
+            }
+            // Keep the last known value, the RPC call is already counted as failed
+            Err(e) => warn!("Unable to refresh relay wallet balance: {}", e),
+        }
+    }
+}
+
+//
+// Database
+//
+
+pub static DB_CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "votechain_db_connections",
+        "Database pool connections, by state (idle / in_use / max), refreshed on scrape",
+        &["state"]
+    )
+    .expect("Failed to register votechain_db_connections")
+});
+
+/// Time an RPC call and record its outcome
+pub async fn observe_rpc<T, E, F>(method: &'static str, call: F) -> Result<T, E>
+where
+    F: IntoFuture<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let result = call.await;
+
+    let outcome = if result.is_ok() { "success" } else { "error" };
+    RPC_CALLS.with_label_values(&[method, outcome]).inc();
+    RPC_CALL_DURATION
+        .with_label_values(&[method])
+        .observe(start.elapsed().as_secs_f64());
+
+    result
+}
+
+/// Record the outcome and the cost of a relayed transaction
+pub fn record_relay(method: &'static str, receipt: &TransactionReceipt) {
+    let outcome = if receipt.status() {
+        "success"
+    } else {
+        "reverted"
+    };
+    RELAYED_TRANSACTIONS
+        .with_label_values(&[method, outcome])
+        .inc();
+
+    RELAY_GAS_USED
+        .with_label_values(&[method])
+        .inc_by(receipt.gas_used as u64);
+
+    // Fee in wei -> ETH (precision loss is fine for monitoring)
+    let fee = receipt.gas_used.saturating_mul(receipt.effective_gas_price);
+    if let Ok(eth) = format_units(fee, "ether") {
+        RELAY_FEES
+            .with_label_values(&[method])
+            .inc_by(eth.parse().unwrap_or_default());
+    }
+}
+
+/// Record a relayed transaction that could not be submitted or mined
+pub fn record_relay_failure(method: &'static str) {
+    RELAYED_TRANSACTIONS
+        .with_label_values(&[method, "failed"])
+        .inc();
+}
+
+/// Render all registered metrics in the Prometheus text format
+pub fn render() -> Result<String, prometheus::Error> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// Media type of `render`, as expected by Prometheus
+pub fn content_type() -> String {
+    format!("{}; charset=utf-8", TextEncoder::new().format_type())
+}
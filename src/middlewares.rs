@@ -1 +1,4 @@
 pub mod auth;
+pub mod metrics;
+pub mod request_id;
+pub mod trace;
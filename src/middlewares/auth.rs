@@ -1,14 +1,47 @@
 use actix_web::{
     body::MessageBody,
     dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
     middleware::Next,
-    web, Error,
+    web, Error, HttpMessage,
 };
 use log::{debug, warn};
+use serde::Deserialize;
 
 use crate::{errors::ApiErrorResponse, AppState};
 
-const UNPROTECTED_PATHS: [&str; 3] = ["/auth/signin", "/auth/refresh", "/health"];
+const UNPROTECTED_PATHS: [&str; 7] = [
+    "/auth/signin",
+    "/auth/refresh",
+    "/health",
+    "/health/live",
+    "/health/ready",
+    "/metrics",
+    "/receipts/verify",
+];
+
+#[derive(Deserialize)]
+struct AccessTokenQuery {
+    access_token: String,
+}
+
+// Whether the request opens an event stream (SSE) or a WebSocket
+fn is_streaming(req: &ServiceRequest) -> bool {
+    let header = |name: &str| {
+        req.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    header("Accept").contains("text/event-stream") || header("Upgrade") == "websocket"
+}
+
+// Error answered with the bearer challenge, only sent by the routes protected by this middleware
+fn reject(e: ApiErrorResponse) -> Error {
+    let response = e.bearer_challenge();
+    InternalError::from_response(e, response).into()
+}
 
 pub async fn ensure_auth(
     data: web::Data<AppState>,
@@ -21,10 +54,19 @@ pub async fn ensure_auth(
         return next.call(req).await;
     }
 
-    // Extract the bearer token from the Authorization header
-    let token = match req.headers().get("Authorization") {
-        Some(t) => t.to_str().unwrap_or(""),
-        None => "",
+    // Extract the bearer token from the Authorization header. Browsers cannot set headers
+    // on EventSource / WebSocket requests, those may pass it as `access_token` (RFC 6750)
+    let query_token = if is_streaming(&req) {
+        web::Query::<AccessTokenQuery>::from_query(req.query_string())
+            .ok()
+            .map(|query| format!("Bearer {}", query.into_inner().access_token))
+    } else {
+        None
+    };
+    let token = match (req.headers().get("Authorization"), &query_token) {
+        (Some(t), _) => t.to_str().unwrap_or(""),
+        (None, Some(t)) => t.as_str(),
+        (None, None) => "",
     };
 
     // Check if the token is empty
@@ -33,7 +75,7 @@ pub async fn ensure_auth(
             "Received request without token to protected path: {}. Rejecting...",
             req.path()
         );
-        return Err(ApiErrorResponse::NoToken.into());
+        return Err(reject(ApiErrorResponse::NoToken));
     }
 
     // Check if the token is a bearer token
@@ -42,7 +84,7 @@ pub async fn ensure_auth(
             "Received request with invalid token format to protected path: {}. Rejecting...",
             req.path()
         );
-        return Err(ApiErrorResponse::MissingBearerToken.into());
+        return Err(reject(ApiErrorResponse::MissingBearerToken));
     }
 
     // Check if the token is valid
@@ -50,27 +92,32 @@ pub async fn ensure_auth(
         .jwt_manager
         .decode_token(token.trim_start_matches("Bearer "), false);
 
-    if decoded_token.is_err() {
+    if let Err(e) = decoded_token {
         warn!(
             "Received request with invalid token to protected path: {}. Error: {:?}",
             req.path(),
-            decoded_token
+            e
         );
 
         // Return the correct error
-        match decoded_token.unwrap_err().kind() {
+        match e.kind() {
             jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
                 // signal user that the token has expired and needs
                 // to refresh it
-                return Err(ApiErrorResponse::ExpiredToken.into());
+                return Err(reject(ApiErrorResponse::ExpiredToken));
             }
             _ => {
                 // signal user that the token is invalid
-                return Err(ApiErrorResponse::InvalidToken.into());
+                return Err(reject(ApiErrorResponse::InvalidToken));
             }
         };
     }
 
+    // Expose the identity of the caller to the routes (see `web::ReqData<Claims>`)
+    if let Ok(token) = decoded_token {
+        req.extensions_mut().insert(token.claims);
+    }
+
     // continue processing the request
     next.call(req).await
 }
@@ -0,0 +1,59 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    middleware::Next,
+    Error,
+};
+use uuid::Uuid;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// Longest request id accepted from upstream proxies
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    // Id of the request being processed by the current task
+    static REQUEST_ID: String;
+}
+
+/// Returns the id of the request currently being served, if any
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+pub async fn assign_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    // Reuse the id forwarded by a proxy (if sane), otherwise generate a new one
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_owned)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    // Keep a handle to the request to render errors raised by inner middlewares
+    let http_req = req.request().clone();
+
+    REQUEST_ID
+        .scope(id.clone(), async move {
+            // NOTE: errors must be rendered while the id is still in scope, otherwise the
+            // problem details body would not carry it
+            let mut res = match next.call(req).await {
+                Ok(res) => res.map_into_boxed_body(),
+                Err(err) => ServiceResponse::from_err(err, http_req),
+            };
+
+            // Echo the id back so clients can reference it when reporting issues
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+
+            Ok(res)
+        })
+        .await
+}
@@ -0,0 +1,65 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::HeaderMap,
+    middleware::Next,
+    Error,
+};
+use opentelemetry::{global, propagation::Extractor};
+use tracing::{field, info_span, Instrument};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::middlewares::request_id;
+
+// Read W3C trace context headers from the incoming request
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    // Continue the trace started by the caller, if any
+    let parent_context =
+        global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(req.headers())));
+
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_owned());
+
+    let span = info_span!(
+        "http_request",
+        otel.name = %format!("{} {}", req.method(), route),
+        otel.kind = "server",
+        otel.status_code = field::Empty,
+        http.request.method = %req.method(),
+        http.route = %route,
+        url.path = %req.path(),
+        http.response.status_code = field::Empty,
+        request_id = request_id::current().unwrap_or_default(),
+    );
+    span.set_parent(parent_context);
+
+    // Every span created while serving the request (DB, RPC) is a child of this one
+    let result = next.call(req).instrument(span.clone()).await;
+
+    let status = match &result {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    span.record("http.response.status_code", status.as_u16());
+    if status.is_server_error() {
+        span.record("otel.status_code", "ERROR");
+    }
+
+    result.map(ServiceResponse::map_into_boxed_body)
+}
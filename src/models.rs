@@ -1 +1,11 @@
+pub mod merkle_allowlists;
+pub mod poll_drafts;
+pub mod poll_eligibility;
+pub mod poll_metadata;
+pub mod poll_owners;
+pub mod poll_votes;
+pub mod relay_jobs;
+pub mod scheduler_audit;
 pub mod users;
+pub mod vote_commitments;
+pub mod vote_receipts;
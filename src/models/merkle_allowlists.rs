@@ -0,0 +1,59 @@
+use crate::schema::merkle_allowlists::dsl::*;
+use diesel::prelude::*;
+
+// Rows inserted per statement, below the limit of bind parameters
+const INSERT_CHUNK_SIZE: usize = 10_000;
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::merkle_allowlists)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AllowlistEntry {
+    pub root: String,
+    pub address: String,
+    pub created_at: Option<std::time::SystemTime>,
+}
+
+impl AllowlistEntry {
+    /// Store the addresses of a list (identical lists share their root)
+    pub fn save_all(conn: &mut PgConnection, entries: &[AllowlistEntry]) -> QueryResult<usize> {
+        // Query the database
+        conn.transaction(|conn| {
+            let mut inserted = 0;
+            for chunk in entries.chunks(INSERT_CHUNK_SIZE) {
+                inserted += diesel::insert_into(merkle_allowlists)
+                    .values(chunk)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+            }
+            Ok(inserted)
+        })
+    }
+
+    pub fn exists(conn: &mut PgConnection, list_root: &str) -> QueryResult<bool> {
+        // Query the database
+        diesel::select(diesel::dsl::exists(
+            merkle_allowlists.filter(root.eq(list_root)),
+        ))
+        .get_result(conn)
+    }
+
+    pub fn contains(conn: &mut PgConnection, list_root: &str, voter: &str) -> QueryResult<bool> {
+        // Query the database
+        diesel::select(diesel::dsl::exists(
+            merkle_allowlists
+                .filter(root.eq(list_root))
+                .filter(address.eq(voter)),
+        ))
+        .get_result(conn)
+    }
+
+    /// Addresses of a list, to rebuild its tree
+    pub fn addresses(conn: &mut PgConnection, list_root: &str) -> QueryResult<Vec<String>> {
+        // Query the database
+        merkle_allowlists
+            .filter(root.eq(list_root))
+            .select(address)
+            .order(address.asc())
+            .load::<String>(conn)
+    }
+}
@@ -0,0 +1,233 @@
+use crate::schema::poll_drafts::dsl::*;
+use diesel::{pg::Pg, prelude::*, query_builder::QueryFragment, query_dsl::methods::ExecuteDsl};
+
+/// Statuses of a draft: editable, being created on-chain, or created
+pub const DRAFT: &str = "draft";
+pub const PUBLISHING: &str = "publishing";
+pub const PUBLISHED: &str = "published";
+
+#[derive(Queryable)]
+#[diesel(table_name = crate::schema::poll_drafts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PollDraft {
+    pub id: i32,
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub options: Vec<String>,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub metadata: Option<String>,
+    pub status: String,
+    pub approved_by: Option<String>,
+    pub poll_id: Option<String>,
+    pub tx_hash: Option<String>,
+    pub created_at: Option<std::time::SystemTime>,
+    pub updated_at: Option<std::time::SystemTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::poll_drafts)]
+pub struct NewPollDraft {
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub options: Vec<String>,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub metadata: Option<String>,
+    pub status: String,
+    pub created_at: Option<std::time::SystemTime>,
+    pub updated_at: Option<std::time::SystemTime>,
+}
+
+/// Edit of a draft. Any approval is withdrawn, it applied to the previous content
+#[derive(AsChangeset)]
+#[diesel(table_name = crate::schema::poll_drafts)]
+#[diesel(treat_none_as_null = true)]
+pub struct PollDraftUpdate {
+    pub name: String,
+    pub description: String,
+    pub options: Vec<String>,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub metadata: Option<String>,
+    pub approved_by: Option<String>,
+    pub updated_at: Option<std::time::SystemTime>,
+}
+
+impl NewPollDraft {
+    pub fn save(&self, conn: &mut PgConnection) -> QueryResult<PollDraft> {
+        // Query the database
+        diesel::insert_into(poll_drafts)
+            .values(self)
+            .get_result::<PollDraft>(conn)
+    }
+}
+
+impl PollDraft {
+    pub fn get(conn: &mut PgConnection, draft_id: i32) -> QueryResult<PollDraft> {
+        // Query the database
+        poll_drafts.find(draft_id).first::<PollDraft>(conn)
+    }
+
+    /// Drafts of an author, or of everyone
+    pub fn list(conn: &mut PgConnection, author: Option<&str>) -> QueryResult<Vec<PollDraft>> {
+        // Query the database
+        let mut query = poll_drafts.order(id.desc()).into_boxed();
+        if let Some(author) = author {
+            query = query.filter(owner.eq(author.to_owned()));
+        }
+        query.load::<PollDraft>(conn)
+    }
+
+    /// Apply an edit, unless the draft was published in the meantime
+    pub fn update(
+        conn: &mut PgConnection,
+        draft_id: i32,
+        changes: &PollDraftUpdate,
+    ) -> QueryResult<Option<PollDraft>> {
+        // Query the database
+        diesel::update(poll_drafts.find(draft_id).filter(status.eq(DRAFT)))
+            .set(changes)
+            .get_result::<PollDraft>(conn)
+            .optional()
+    }
+
+    pub fn delete(conn: &mut PgConnection, draft_id: i32) -> QueryResult<usize> {
+        // Query the database
+        diesel::delete(poll_drafts.find(draft_id).filter(status.eq(DRAFT))).execute(conn)
+    }
+
+    pub fn approve(conn: &mut PgConnection, draft_id: i32, admin: &str) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(poll_drafts.find(draft_id).filter(status.eq(DRAFT)))
+            .set(approved_by.eq(Some(admin)))
+            .execute(conn)
+    }
+
+    /// Move the draft from `from` to `to`, returning whether it was in status `from`.
+    /// Guards against publishing the same draft twice
+    pub fn transition(
+        conn: &mut PgConnection,
+        draft_id: i32,
+        from: &str,
+        to: &str,
+    ) -> QueryResult<bool> {
+        // Query the database
+        ExecuteDsl::execute(transition_query(draft_id, from, to), conn).map(|rows| rows == 1)
+    }
+
+    /// Record the transaction of a draft being published, while its outcome is unknown
+    pub fn set_tx_hash(conn: &mut PgConnection, draft_id: i32, hash: &str) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(poll_drafts.find(draft_id).filter(status.eq(PUBLISHING)))
+            .set((
+                tx_hash.eq(Some(hash)),
+                updated_at.eq(Some(std::time::SystemTime::now())),
+            ))
+            .execute(conn)
+    }
+
+    pub fn set_published(
+        conn: &mut PgConnection,
+        draft_id: i32,
+        created_poll: &str,
+        hash: &str,
+    ) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(poll_drafts.find(draft_id))
+            .set((
+                status.eq(PUBLISHED),
+                poll_id.eq(Some(created_poll)),
+                tx_hash.eq(Some(hash)),
+                updated_at.eq(Some(std::time::SystemTime::now())),
+            ))
+            .execute(conn)
+    }
+
+    /// Mark the drafts still being published by a transaction, now mined, as published
+    pub fn confirm_tx(
+        conn: &mut PgConnection,
+        hash: &str,
+        created_poll: &str,
+    ) -> QueryResult<Vec<PollDraft>> {
+        // Query the database
+        diesel::update(
+            poll_drafts
+                .filter(tx_hash.eq(hash))
+                .filter(status.eq(PUBLISHING)),
+        )
+        .set((
+            status.eq(PUBLISHED),
+            poll_id.eq(Some(created_poll)),
+            updated_at.eq(Some(std::time::SystemTime::now())),
+        ))
+        .get_results(conn)
+    }
+
+    /// Unlock the drafts of a reverted transaction, nothing was created
+    pub fn release_tx(conn: &mut PgConnection, hash: &str) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(
+            poll_drafts
+                .filter(tx_hash.eq(hash))
+                .filter(status.eq(PUBLISHING)),
+        )
+        .set((
+            status.eq(DRAFT),
+            tx_hash.eq(None::<String>),
+            updated_at.eq(Some(std::time::SystemTime::now())),
+        ))
+        .execute(conn)
+    }
+}
+
+// Change of status of a draft, applied only while it is in status `from`
+fn transition_query<'a>(
+    draft_id: i32,
+    from: &'a str,
+    to: &'a str,
+) -> impl ExecuteDsl<PgConnection> + QueryFragment<Pg> + 'a {
+    diesel::update(poll_drafts.find(draft_id).filter(status.eq(from))).set((
+        status.eq(to),
+        updated_at.eq(Some(std::time::SystemTime::now())),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::debug_query;
+
+    use super::*;
+
+    #[test]
+    fn transitions_are_guarded_by_the_current_status() {
+        let sql = debug_query::<Pg, _>(&transition_query(7, DRAFT, PUBLISHING)).to_string();
+        assert!(sql.starts_with(r#"UPDATE "poll_drafts" SET "status" = $1"#));
+        assert!(
+            sql.contains(r#"WHERE (("poll_drafts"."id" = $3) AND ("poll_drafts"."status" = $4))"#)
+        );
+        assert!(sql.contains(r#"binds: ["publishing", "#));
+        assert!(sql.ends_with(r#"7, "draft"]"#));
+    }
+
+    #[test]
+    fn edits_clear_the_approval() {
+        let changes = PollDraftUpdate {
+            name: "Lunch".to_owned(),
+            description: String::new(),
+            options: vec!["Pizza".to_owned(), "Sushi".to_owned()],
+            start_time: 1_000,
+            end_time: 2_000,
+            metadata: None,
+            approved_by: None,
+            updated_at: None,
+        };
+        let query = diesel::update(poll_drafts.find(7).filter(status.eq(DRAFT))).set(&changes);
+        let sql = debug_query::<Pg, _>(&query).to_string();
+
+        // A missing approval is written as NULL, not skipped
+        assert!(sql.contains(r#""approved_by" = $7"#));
+    }
+}
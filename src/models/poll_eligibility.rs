@@ -0,0 +1,61 @@
+use crate::schema::poll_eligibility::dsl::*;
+use diesel::prelude::*;
+
+// Rows inserted per statement, below the limit of bind parameters
+const INSERT_CHUNK_SIZE: usize = 10_000;
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::poll_eligibility)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct EligibleVoter {
+    pub poll_id: String,
+    pub address: String,
+    pub created_at: Option<std::time::SystemTime>,
+}
+
+impl EligibleVoter {
+    /// Replace the list of a poll
+    pub fn replace(
+        conn: &mut PgConnection,
+        poll: &str,
+        voters: &[EligibleVoter],
+    ) -> QueryResult<usize> {
+        // Query the database
+        conn.transaction(|conn| {
+            diesel::delete(poll_eligibility.filter(poll_id.eq(poll))).execute(conn)?;
+
+            let mut inserted = 0;
+            for chunk in voters.chunks(INSERT_CHUNK_SIZE) {
+                inserted += diesel::insert_into(poll_eligibility)
+                    .values(chunk)
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+            }
+            Ok(inserted)
+        })
+    }
+
+    pub fn remove_all(conn: &mut PgConnection, poll: &str) -> QueryResult<usize> {
+        // Query the database
+        diesel::delete(poll_eligibility.filter(poll_id.eq(poll))).execute(conn)
+    }
+
+    /// Whether the poll has a list
+    pub fn is_restricted(conn: &mut PgConnection, poll: &str) -> QueryResult<bool> {
+        // Query the database
+        diesel::select(diesel::dsl::exists(
+            poll_eligibility.filter(poll_id.eq(poll)),
+        ))
+        .get_result(conn)
+    }
+
+    pub fn is_listed(conn: &mut PgConnection, poll: &str, voter: &str) -> QueryResult<bool> {
+        // Query the database
+        diesel::select(diesel::dsl::exists(
+            poll_eligibility
+                .filter(poll_id.eq(poll))
+                .filter(address.eq(voter)),
+        ))
+        .get_result(conn)
+    }
+}
@@ -0,0 +1,34 @@
+use crate::schema::poll_metadata::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::poll_metadata)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PollMetadataRecord {
+    pub hash: String,
+    pub content: String,
+    pub created_at: Option<std::time::SystemTime>,
+}
+
+impl PollMetadataRecord {
+    pub fn find(conn: &mut PgConnection, content_hash: &str) -> QueryResult<Option<Self>> {
+        // Query the database
+        poll_metadata
+            .filter(hash.eq(content_hash))
+            .first::<Self>(conn)
+            .optional()
+    }
+
+    pub fn find_all(conn: &mut PgConnection, hashes: &[String]) -> QueryResult<Vec<Self>> {
+        // Query the database
+        poll_metadata.filter(hash.eq_any(hashes)).load::<Self>(conn)
+    }
+
+    pub fn save(&self, conn: &mut PgConnection) -> QueryResult<usize> {
+        // Query the database (identical content may already be stored)
+        diesel::insert_into(poll_metadata)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+}
@@ -0,0 +1,27 @@
+use crate::schema::poll_owners::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::poll_owners)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PollOwner {
+    pub poll_id: String,
+    pub owner: String,
+    pub tx_hash: String,
+    pub created_at: Option<std::time::SystemTime>,
+}
+
+impl PollOwner {
+    pub fn find(conn: &mut PgConnection, poll: &str) -> QueryResult<Option<PollOwner>> {
+        // Query the database
+        poll_owners.find(poll).first::<PollOwner>(conn).optional()
+    }
+
+    pub fn save(&self, conn: &mut PgConnection) -> QueryResult<usize> {
+        // Query the database (ids are assigned by the contract, a poll is recorded once)
+        diesel::insert_into(poll_owners)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+}
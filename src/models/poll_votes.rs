@@ -0,0 +1,89 @@
+use crate::schema::poll_votes::dsl::*;
+use diesel::prelude::*;
+
+/// Statuses of a vote: being relayed, or relayed
+pub const VOTING: &str = "voting";
+pub const VOTED: &str = "voted";
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::poll_votes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PollVote {
+    pub poll_id: String,
+    pub voter: String,
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub created_at: Option<std::time::SystemTime>,
+    pub updated_at: Option<std::time::SystemTime>,
+}
+
+impl PollVote {
+    /// Lock the vote of `address` before relaying it, returning whether it was free.
+    /// Guards against relaying the same vote twice
+    pub fn begin(conn: &mut PgConnection, poll: &str, address: &str) -> QueryResult<bool> {
+        let now = Some(std::time::SystemTime::now());
+        // Query the database
+        diesel::insert_into(poll_votes)
+            .values(PollVote {
+                poll_id: poll.to_owned(),
+                voter: address.to_owned(),
+                status: VOTING.to_owned(),
+                tx_hash: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map(|rows| rows == 1)
+    }
+
+    /// Unlock a vote that was not relayed, so that the voter can retry
+    pub fn release(conn: &mut PgConnection, poll: &str, address: &str) -> QueryResult<usize> {
+        // Query the database
+        diesel::delete(poll_votes.find((poll, address)).filter(status.eq(VOTING))).execute(conn)
+    }
+
+    /// Record the transaction relaying the vote, with its new status
+    pub fn set_tx_hash(
+        conn: &mut PgConnection,
+        poll: &str,
+        address: &str,
+        hash: &str,
+        to: &str,
+    ) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(poll_votes.find((poll, address)))
+            .set((
+                status.eq(to),
+                tx_hash.eq(Some(hash)),
+                updated_at.eq(Some(std::time::SystemTime::now())),
+            ))
+            .execute(conn)
+    }
+
+    /// Mark the votes still being relayed by a transaction, now mined, as relayed
+    pub fn confirm_tx(conn: &mut PgConnection, hash: &str) -> QueryResult<Vec<PollVote>> {
+        // Query the database
+        diesel::update(
+            poll_votes
+                .filter(tx_hash.eq(hash))
+                .filter(status.eq(VOTING)),
+        )
+        .set((
+            status.eq(VOTED),
+            updated_at.eq(Some(std::time::SystemTime::now())),
+        ))
+        .get_results(conn)
+    }
+
+    /// Unlock the votes of a reverted transaction, so that the voters can retry
+    pub fn release_tx(conn: &mut PgConnection, hash: &str) -> QueryResult<usize> {
+        // Query the database
+        diesel::delete(
+            poll_votes
+                .filter(tx_hash.eq(hash))
+                .filter(status.eq(VOTING)),
+        )
+        .execute(conn)
+    }
+}
@@ -0,0 +1,57 @@
+use crate::schema::relay_jobs::dsl::*;
+use diesel::prelude::*;
+
+/// Statuses of a job: its transaction was not mined yet, succeeded or reverted
+pub const PENDING: &str = "pending";
+pub const CONFIRMED: &str = "confirmed";
+pub const REVERTED: &str = "reverted";
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::relay_jobs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct RelayJob {
+    pub tx_hash: String,
+    pub method: String,
+    pub status: String,
+    pub created_at: Option<std::time::SystemTime>,
+    pub updated_at: Option<std::time::SystemTime>,
+}
+
+impl RelayJob {
+    pub fn new(hash: String, contract_method: &str) -> Self {
+        let now = std::time::SystemTime::now();
+        Self {
+            tx_hash: hash,
+            method: contract_method.to_owned(),
+            status: PENDING.to_owned(),
+            created_at: Some(now),
+            updated_at: Some(now),
+        }
+    }
+
+    pub fn get_pending(conn: &mut PgConnection) -> QueryResult<Vec<RelayJob>> {
+        // Query the database
+        relay_jobs
+            .filter(status.eq(PENDING))
+            .order(created_at.asc())
+            .load::<RelayJob>(conn)
+    }
+
+    pub fn save_all(jobs: &[RelayJob], conn: &mut PgConnection) -> QueryResult<usize> {
+        // Query the database (a job may already be stored by a previous shutdown)
+        diesel::insert_into(relay_jobs)
+            .values(jobs)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    pub fn set_status(&self, new_status: &str, conn: &mut PgConnection) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(relay_jobs.filter(tx_hash.eq(&self.tx_hash)))
+            .set((
+                status.eq(new_status),
+                updated_at.eq(Some(std::time::SystemTime::now())),
+            ))
+            .execute(conn)
+    }
+}
@@ -0,0 +1,36 @@
+use crate::schema::scheduler_audit::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::scheduler_audit)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SchedulerAudit {
+    pub poll_id: String,
+    pub action: String,
+    pub outcome: String,
+    pub attempt: i32,
+    pub tx_hash: Option<String>,
+    pub error: Option<String>,
+    pub created_at: Option<std::time::SystemTime>,
+}
+
+impl SchedulerAudit {
+    pub fn save(&self, conn: &mut PgConnection) -> QueryResult<usize> {
+        // Query the database
+        diesel::insert_into(scheduler_audit)
+            .values(self)
+            .execute(conn)
+    }
+
+    /// Latest entry of every action on every poll, to resume after a restart
+    pub fn get_latest(conn: &mut PgConnection) -> QueryResult<Vec<SchedulerAudit>> {
+        // Query the database
+        scheduler_audit
+            .select((
+                poll_id, action, outcome, attempt, tx_hash, error, created_at,
+            ))
+            .distinct_on((poll_id, action))
+            .order((poll_id, action, id.desc()))
+            .load::<SchedulerAudit>(conn)
+    }
+}
@@ -45,11 +45,10 @@ impl User {
             .execute(conn)
     }
 
-    pub fn revoke_refresh_token(&self, conn: &mut PgConnection) {
+    pub fn revoke_refresh_token(&self, conn: &mut PgConnection) -> QueryResult<usize> {
         // Query the database
         diesel::update(users.filter(primary_account.eq(&self.primary_account)))
             .set(refresh_token.eq(""))
             .execute(conn)
-            .expect("Failed to revoke refresh token");
     }
 }
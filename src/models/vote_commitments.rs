@@ -0,0 +1,155 @@
+use crate::schema::vote_commitments::dsl::*;
+use diesel::prelude::*;
+
+/// Statuses of a commitment: waiting for its reveal, being relayed, or relayed
+pub const COMMITTED: &str = "committed";
+pub const REVEALING: &str = "revealing";
+pub const REVEALED: &str = "revealed";
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::vote_commitments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct VoteCommitment {
+    pub poll_id: String,
+    pub voter: String,
+    pub commitment: String,
+    pub status: String,
+    pub tx_hash: Option<String>,
+    pub created_at: Option<std::time::SystemTime>,
+    pub updated_at: Option<std::time::SystemTime>,
+}
+
+impl VoteCommitment {
+    /// Record a commitment, replacing the previous one of the voter. Commitments are only
+    /// accepted before the reveal phase, so none of them is revealed yet
+    pub fn save(&self, conn: &mut PgConnection) -> QueryResult<VoteCommitment> {
+        // Query the database
+        diesel::insert_into(vote_commitments)
+            .values(self)
+            .on_conflict((poll_id, voter))
+            .do_update()
+            .set((
+                commitment.eq(&self.commitment),
+                updated_at.eq(self.updated_at),
+            ))
+            .get_result::<VoteCommitment>(conn)
+    }
+
+    pub fn find(
+        conn: &mut PgConnection,
+        poll: &str,
+        address: &str,
+    ) -> QueryResult<Option<VoteCommitment>> {
+        // Query the database
+        vote_commitments
+            .find((poll, address))
+            .first::<VoteCommitment>(conn)
+            .optional()
+    }
+
+    /// Number of commitments of a poll, and how many of them are revealed
+    pub fn count(conn: &mut PgConnection, poll: &str) -> QueryResult<(i64, i64)> {
+        // Query the database
+        let committed = vote_commitments
+            .filter(poll_id.eq(poll))
+            .count()
+            .get_result(conn)?;
+        let revealed = vote_commitments
+            .filter(poll_id.eq(poll))
+            .filter(status.eq(REVEALED))
+            .count()
+            .get_result(conn)?;
+        Ok((committed, revealed))
+    }
+
+    /// Move the commitment from `from` to `to`, returning whether it was in status `from`.
+    /// Guards against relaying the same vote twice
+    pub fn transition(
+        conn: &mut PgConnection,
+        poll: &str,
+        address: &str,
+        from: &str,
+        to: &str,
+    ) -> QueryResult<bool> {
+        // Query the database
+        diesel::update(
+            vote_commitments
+                .find((poll, address))
+                .filter(status.eq(from)),
+        )
+        .set((
+            status.eq(to),
+            updated_at.eq(Some(std::time::SystemTime::now())),
+        ))
+        .execute(conn)
+        .map(|rows| rows == 1)
+    }
+
+    /// Record the transaction revealing a vote that is not confirmed yet
+    pub fn set_tx_hash(
+        conn: &mut PgConnection,
+        poll: &str,
+        address: &str,
+        hash: &str,
+    ) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(
+            vote_commitments
+                .find((poll, address))
+                .filter(status.eq(REVEALING)),
+        )
+        .set((
+            tx_hash.eq(Some(hash)),
+            updated_at.eq(Some(std::time::SystemTime::now())),
+        ))
+        .execute(conn)
+    }
+
+    /// Mark the commitments still being revealed by a transaction, now mined, as revealed
+    pub fn confirm_tx(conn: &mut PgConnection, hash: &str) -> QueryResult<Vec<VoteCommitment>> {
+        // Query the database
+        diesel::update(
+            vote_commitments
+                .filter(tx_hash.eq(hash))
+                .filter(status.eq(REVEALING)),
+        )
+        .set((
+            status.eq(REVEALED),
+            updated_at.eq(Some(std::time::SystemTime::now())),
+        ))
+        .get_results(conn)
+    }
+
+    /// Unlock the commitments of a reverted transaction, so that the votes can be revealed
+    /// again
+    pub fn release_tx(conn: &mut PgConnection, hash: &str) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(
+            vote_commitments
+                .filter(tx_hash.eq(hash))
+                .filter(status.eq(REVEALING)),
+        )
+        .set((
+            status.eq(COMMITTED),
+            tx_hash.eq(None::<String>),
+            updated_at.eq(Some(std::time::SystemTime::now())),
+        ))
+        .execute(conn)
+    }
+
+    pub fn set_revealed(
+        conn: &mut PgConnection,
+        poll: &str,
+        address: &str,
+        hash: &str,
+    ) -> QueryResult<usize> {
+        // Query the database
+        diesel::update(vote_commitments.find((poll, address)))
+            .set((
+                status.eq(REVEALED),
+                tx_hash.eq(Some(hash)),
+                updated_at.eq(Some(std::time::SystemTime::now())),
+            ))
+            .execute(conn)
+    }
+}
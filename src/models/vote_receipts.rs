@@ -0,0 +1,36 @@
+use crate::schema::vote_receipts::dsl::*;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::vote_receipts)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct VoteReceiptRecord {
+    pub id: String,
+    pub poll_id: String,
+    pub voter: String,
+    pub option_hash: String,
+    pub tx_hash: String,
+    pub block_number: i64,
+    pub log_index: i64,
+    pub signer: String,
+    pub signature: String,
+    pub created_at: Option<std::time::SystemTime>,
+}
+
+impl VoteReceiptRecord {
+    pub fn find(conn: &mut PgConnection, receipt_id: &str) -> QueryResult<Option<Self>> {
+        // Query the database
+        vote_receipts
+            .find(receipt_id)
+            .first::<Self>(conn)
+            .optional()
+    }
+
+    pub fn save(&self, conn: &mut PgConnection) -> QueryResult<usize> {
+        // Query the database (receipts are deterministic, the same vote may be issued twice)
+        diesel::insert_into(vote_receipts)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+}
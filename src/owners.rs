@@ -0,0 +1,52 @@
+use alloy::primitives::Address;
+use log::error;
+
+use crate::{
+    auth::Claims,
+    contracts::votechain::{Poll, PollRecipt},
+    db,
+    errors::{ApiErrorResponse, AppError},
+    models::poll_owners::PollOwner,
+    AppState,
+};
+
+/// Record the user who created a poll. The contract makes the relay wallet the owner of
+/// every poll it relays, so the creator is only known off-chain
+pub async fn record(app_state: &AppState, creator: Address, receipt: &PollRecipt) {
+    let owner = PollOwner {
+        poll_id: receipt.poll_id.to_string(),
+        owner: creator.to_string(),
+        tx_hash: receipt.transaction_hash.to_string(),
+        created_at: Some(std::time::SystemTime::now()),
+    };
+
+    // The poll exists now: an unrecorded creator only leaves it to the administrators
+    if let Err(e) = db::query(&app_state.db, move |conn| owner.save(conn)).await {
+        error!(
+            "Poll {} created by {} but its creator was not recorded: {}",
+            receipt.poll_id, creator, e
+        );
+    }
+}
+
+/// Allow the creator of a poll and the administrators to manage it. Polls must have been
+/// created through this relay, whose wallet owns them on-chain
+pub async fn ensure_manager(
+    app_state: &AppState,
+    claims: &Claims,
+    poll: &Poll,
+) -> Result<(), AppError> {
+    if poll.owner != app_state.contracts.votechain.relay_address() {
+        return Err(ApiErrorResponse::Forbidden.into());
+    }
+
+    let poll_id = poll.id.to_string();
+    let creator = db::query(&app_state.db, move |conn| PollOwner::find(conn, &poll_id))
+        .await?
+        .and_then(|record| record.owner.parse().ok());
+    match creator {
+        Some(creator) if claims.is_owner_or_admin(creator) => Ok(()),
+        None if claims.is_admin() => Ok(()),
+        _ => Err(ApiErrorResponse::Forbidden.into()),
+    }
+}
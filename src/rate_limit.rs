@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::errors::ApiErrorResponse;
+
+// Clients tracked at once. Beyond, finished windows are dropped, then every window if needed
+const MAX_CLIENTS: usize = 10_000;
+
+/// Fixed window limit of the requests of each client (by peer address) to a public route
+/// that costs more than it should be allowed to. A limit of 0 disables it
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    clients: Mutex<HashMap<IpAddr, Window>>,
+}
+
+struct Window {
+    started: Instant,
+    requests: u32,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a request of `client`, refused with the seconds left in the window once the
+    /// limit is reached
+    pub fn check(&self, client: IpAddr) -> Result<(), ApiErrorResponse> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), ApiErrorResponse> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        if clients.len() >= MAX_CLIENTS && !clients.contains_key(&client) {
+            clients.retain(|_, window| now.duration_since(window.started) < self.window);
+            if clients.len() >= MAX_CLIENTS {
+                clients.clear();
+            }
+        }
+
+        let window = clients.entry(client).or_insert(Window {
+            started: now,
+            requests: 0,
+        });
+        if now.duration_since(window.started) >= self.window {
+            *window = Window {
+                started: now,
+                requests: 0,
+            };
+        }
+        if window.requests >= self.limit {
+            let left = self.window - now.duration_since(window.started);
+            return Err(ApiErrorResponse::RateLimited {
+                retry_after: left.as_secs().max(1),
+            });
+        }
+        window.requests += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+    const BOB: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn refuses_beyond_the_limit_until_the_window_ends() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+        assert!(limiter.check_at(ALICE, now).is_ok());
+        assert!(limiter.check_at(ALICE, now).is_ok());
+        assert!(matches!(
+            limiter.check_at(ALICE, now + Duration::from_secs(20)),
+            Err(ApiErrorResponse::RateLimited { retry_after: 40 })
+        ));
+
+        // Other clients have their own window
+        assert!(limiter.check_at(BOB, now).is_ok());
+
+        assert!(limiter
+            .check_at(ALICE, now + Duration::from_secs(60))
+            .is_ok());
+    }
+
+    #[test]
+    fn zero_disables_the_limit() {
+        let limiter = RateLimiter::new(0, Duration::from_secs(60));
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(limiter.check_at(ALICE, now).is_ok());
+        }
+    }
+}
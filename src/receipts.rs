@@ -0,0 +1,324 @@
+use alloy::{
+    primitives::{keccak256, Address, Bytes, PrimitiveSignature, TxHash, B256, U256},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::CacheKey,
+    contracts::votechain::RelayedVote,
+    db,
+    errors::{ApiErrorResponse, AppError, FieldError},
+    models::vote_receipts::VoteReceiptRecord,
+    AppState,
+};
+
+/// Receipt of a relayed vote. Its id is the digest of the vote, signed by the server
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VoteReceipt {
+    pub id: B256,
+    pub poll_id: U256,
+    pub voter: Address, // Authenticated voter, the transaction is sent by the relay
+    pub option_hash: B256, // keccak256 of the option voted for
+    pub transaction_hash: TxHash,
+    pub block_number: u64,
+    pub log_index: u64,   // Index of the VoteCast event in the block
+    pub signer: Address,  // Signing key of the server
+    pub signature: Bytes, // EIP-191 signature of `id`
+}
+
+impl VoteReceipt {
+    /// `keccak256(abi.encodePacked(poll_id, voter, option_hash, transaction_hash,
+    /// uint64 block_number, uint64 log_index))`
+    pub fn digest(&self) -> B256 {
+        keccak256(
+            [
+                &self.poll_id.to_be_bytes::<32>()[..],
+                self.voter.as_slice(),
+                self.option_hash.as_slice(),
+                self.transaction_hash.as_slice(),
+                &self.block_number.to_be_bytes(),
+                &self.log_index.to_be_bytes(),
+            ]
+            .concat(),
+        )
+    }
+}
+
+/// Key signing the receipts, RECEIPT_SIGNING_KEY or the relay wallet
+pub struct ReceiptSigner {
+    signer: PrivateKeySigner,
+}
+
+impl ReceiptSigner {
+    pub fn new(signer: PrivateKeySigner) -> Self {
+        Self { signer }
+    }
+
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Receipt of a vote relayed for `voter`
+    pub fn sign(
+        &self,
+        voter: Address,
+        vote: &RelayedVote,
+    ) -> Result<VoteReceipt, alloy::signers::Error> {
+        let mut receipt = VoteReceipt {
+            id: B256::ZERO,
+            poll_id: vote.poll_id,
+            voter,
+            option_hash: keccak256(&vote.option),
+            transaction_hash: vote.transaction_hash,
+            block_number: vote.block_number,
+            log_index: vote.log_index,
+            signer: self.address(),
+            signature: Bytes::new(),
+        };
+        receipt.id = receipt.digest();
+        let signature = self.signer.sign_message_sync(receipt.id.as_slice())?;
+        receipt.signature = Bytes::from(signature.as_bytes());
+        Ok(receipt)
+    }
+}
+
+/// Sign and store the receipt of a relayed vote. The vote is cast already: failures are
+/// logged, and the vote is returned without receipt
+pub async fn issue(
+    app_state: &AppState,
+    voter: Address,
+    vote: &RelayedVote,
+) -> Option<VoteReceipt> {
+    let receipt = match app_state.receipts.sign(voter, vote) {
+        Ok(receipt) => receipt,
+        Err(e) => {
+            error!(
+                "Unable to sign the receipt of transaction {}: {}",
+                vote.transaction_hash, e
+            );
+            return None;
+        }
+    };
+
+    let record = VoteReceiptRecord::from(&receipt);
+    let stored = db::query(&app_state.db, move |conn| record.save(conn)).await;
+    match stored {
+        Ok(_) => Some(receipt),
+        Err(e) => {
+            error!("Receipt {} issued but not stored: {}", receipt.id, e);
+            None
+        }
+    }
+}
+
+/// Problems of a receipt: content altered, not signed by this server, or not matching the
+/// vote emitted on-chain by its transaction. Empty for a valid receipt
+pub async fn verify(
+    app_state: &AppState,
+    receipt: &VoteReceipt,
+) -> Result<Vec<FieldError>, AppError> {
+    let mut errors = signature_errors(receipt, app_state.receipts.address());
+
+    // Receipts that were not issued here are not looked up on-chain, so that anyone
+    // calling this public route cannot make it query the node for arbitrary transactions
+    if !errors.is_empty() {
+        return Ok(errors);
+    }
+
+    // Vote emitted on-chain, cached by transaction once found
+    let contract = &app_state.contracts.votechain;
+    let tx_hash = receipt.transaction_hash;
+    let vote = app_state
+        .cache
+        .get_or_load(CacheKey::RelayedVote(tx_hash), || {
+            contract.get_relayed_vote(tx_hash)
+        })
+        .await?;
+    let Some(vote) = vote else {
+        errors.push(FieldError::new(
+            "transaction_hash",
+            "did not cast a vote in the contract",
+        ));
+        return Ok(errors);
+    };
+    if vote.block_number != receipt.block_number {
+        errors.push(FieldError::new(
+            "block_number",
+            "does not match the transaction",
+        ));
+    }
+    if vote.log_index != receipt.log_index {
+        errors.push(FieldError::new(
+            "log_index",
+            "does not match the transaction",
+        ));
+    }
+    if vote.poll_id != receipt.poll_id {
+        errors.push(FieldError::new("poll_id", "does not match the vote cast"));
+    }
+    if keccak256(&vote.option) != receipt.option_hash {
+        errors.push(FieldError::new(
+            "option_hash",
+            "does not match the option voted for",
+        ));
+    }
+
+    Ok(errors)
+}
+
+// Problems of the signature of a receipt: content altered, or not signed by `server`
+fn signature_errors(receipt: &VoteReceipt, server: Address) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if receipt.id != receipt.digest() {
+        errors.push(FieldError::new(
+            "id",
+            "does not match the content of the receipt",
+        ));
+    }
+    if receipt.signer != server {
+        errors.push(FieldError::new(
+            "signer",
+            "is not the signing key of this server",
+        ));
+    }
+    let recovered = PrimitiveSignature::try_from(receipt.signature.as_ref())
+        .ok()
+        .and_then(|signature| signature.recover_address_from_msg(receipt.id).ok());
+    if recovered != Some(server) {
+        errors.push(FieldError::new(
+            "signature",
+            "was not made by the signing key of this server",
+        ));
+    }
+    errors
+}
+
+impl From<&VoteReceipt> for VoteReceiptRecord {
+    fn from(receipt: &VoteReceipt) -> Self {
+        Self {
+            id: receipt.id.to_string(),
+            poll_id: receipt.poll_id.to_string(),
+            voter: receipt.voter.to_string(),
+            option_hash: receipt.option_hash.to_string(),
+            tx_hash: receipt.transaction_hash.to_string(),
+            block_number: receipt.block_number as i64,
+            log_index: receipt.log_index as i64,
+            signer: receipt.signer.to_string(),
+            signature: receipt.signature.to_string(),
+            created_at: Some(std::time::SystemTime::now()),
+        }
+    }
+}
+
+impl TryFrom<VoteReceiptRecord> for VoteReceipt {
+    type Error = AppError;
+
+    // Stored values were checked when saved, failures mean the row was altered
+    fn try_from(record: VoteReceiptRecord) -> Result<Self, Self::Error> {
+        fn parse<T: std::str::FromStr>(value: &str) -> Result<T, ApiErrorResponse> {
+            value
+                .parse()
+                .map_err(|_| ApiErrorResponse::InternalServerError)
+        }
+        let number =
+            |value: i64| u64::try_from(value).map_err(|_| ApiErrorResponse::InternalServerError);
+
+        Ok(Self {
+            id: parse(&record.id)?,
+            poll_id: parse(&record.poll_id)?,
+            voter: parse(&record.voter)?,
+            option_hash: parse(&record.option_hash)?,
+            transaction_hash: parse(&record.tx_hash)?,
+            block_number: number(record.block_number)?,
+            log_index: number(record.log_index)?,
+            signer: parse(&record.signer)?,
+            signature: parse(&record.signature)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote() -> RelayedVote {
+        RelayedVote {
+            transaction_hash: TxHash::repeat_byte(7),
+            block_number: 42,
+            log_index: 3,
+            poll_id: U256::from(1),
+            option: "Pizza".to_owned(),
+            contract_receipt: None,
+        }
+    }
+
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|error| error.field.as_str()).collect()
+    }
+
+    #[test]
+    fn signed_receipts_verify() {
+        let signer = ReceiptSigner::new(PrivateKeySigner::random());
+        let voter = Address::repeat_byte(1);
+        let receipt = signer.sign(voter, &vote()).unwrap();
+
+        assert_eq!(receipt.id, receipt.digest());
+        assert_eq!((receipt.voter, receipt.signer), (voter, signer.address()));
+        assert_eq!(receipt.option_hash, keccak256("Pizza"));
+        assert!(signature_errors(&receipt, signer.address()).is_empty());
+
+        // Receipts read back from JSON, as posted to /receipts/verify, still verify
+        let json = serde_json::to_string(&receipt).unwrap();
+        let parsed: VoteReceipt = serde_json::from_str(&json).unwrap();
+        assert!(signature_errors(&parsed, signer.address()).is_empty());
+    }
+
+    #[test]
+    fn tampered_receipts_do_not_verify() {
+        let signer = ReceiptSigner::new(PrivateKeySigner::random());
+        let receipt = signer.sign(Address::repeat_byte(1), &vote()).unwrap();
+
+        let mut tampered = receipt.clone();
+        tampered.voter = Address::repeat_byte(2);
+        assert_eq!(
+            fields(&signature_errors(&tampered, signer.address())),
+            ["id"]
+        );
+
+        // A consistent id over altered content is not the one signed
+        tampered.id = tampered.digest();
+        assert_eq!(
+            fields(&signature_errors(&tampered, signer.address())),
+            ["signature"]
+        );
+
+        let mut tampered = receipt;
+        tampered.signature = Bytes::from(vec![0; 65]);
+        assert_eq!(
+            fields(&signature_errors(&tampered, signer.address())),
+            ["signature"]
+        );
+    }
+
+    #[test]
+    fn receipts_of_other_signers_do_not_verify() {
+        let server = ReceiptSigner::new(PrivateKeySigner::random());
+        let foreign = ReceiptSigner::new(PrivateKeySigner::random());
+        let receipt = foreign.sign(Address::repeat_byte(1), &vote()).unwrap();
+        assert!(signature_errors(&receipt, foreign.address()).is_empty());
+        assert_eq!(
+            fields(&signature_errors(&receipt, server.address())),
+            ["signer", "signature"]
+        );
+
+        // Claiming the server as signer does not make the signature its own
+        let mut claimed = receipt;
+        claimed.signer = server.address();
+        assert_eq!(
+            fields(&signature_errors(&claimed, server.address())),
+            ["signature"]
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex, OnceLock, PoisonError,
+    },
+    time::Duration,
+};
+
+use actix_web::web;
+use alloy::primitives::TxHash;
+use log::{error, info, warn};
+use tokio::sync::Notify;
+
+use crate::{
+    config,
+    contracts::{errors::ContractError, votechain::PollRecipt},
+    db,
+    errors::AppError,
+    models::{
+        poll_drafts::PollDraft,
+        poll_votes::PollVote,
+        relay_jobs::{RelayJob, CONFIRMED, REVERTED},
+        vote_commitments::VoteCommitment,
+    },
+    owners, receipts, AppState,
+};
+
+const DEFAULT_RECONCILE_INTERVAL_SECS: u64 = 30;
+
+/// Keeps track of the relayed transactions between their submission and their
+/// confirmation, so that shutdown can wait for them instead of forgetting them
+#[derive(Default)]
+pub struct RelayTracker {
+    draining: AtomicBool,                            // Set once shutdown started
+    abandoned: AtomicBool, // Set once the drain timed out, relays stop waiting for receipts
+    wrong_chain: OnceLock<u64>, // Chain id of the node, once found to differ from CHAIN_ID
+    in_flight: AtomicUsize, // Relays not completed yet
+    submitted: Mutex<HashMap<TxHash, &'static str>>, // Sent transactions not mined yet
+    idle: Notify,          // Notified when in_flight drops to 0
+    abandon: Notify,       // Notified when the drain timed out
+}
+
+/// Registration of a relay in progress, released on drop
+pub struct RelayGuard<'a> {
+    tracker: &'a RelayTracker,
+    tx_hash: Option<TxHash>,
+}
+
+impl RelayTracker {
+    /// Register a new relay, unless the server is shutting down
+    pub fn begin(&self) -> Result<RelayGuard<'_>, ContractError> {
+        // Count the relay first, so that a concurrent drain either waits for it or rejects it
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = RelayGuard {
+            tracker: self,
+            tx_hash: None,
+        };
+
+        if let Some(chain_id) = self.wrong_chain.get() {
+            return Err(ContractError::WrongChain(*chain_id));
+        }
+        if self.draining.load(Ordering::SeqCst) {
+            return Err(ContractError::ShuttingDown);
+        }
+        Ok(guard)
+    }
+
+    /// Refuse every relay from now on: the node is on chain `chain_id`, not CHAIN_ID
+    pub fn refuse_chain(&self, chain_id: u64) {
+        let _ = self.wrong_chain.set(chain_id);
+    }
+
+    /// Whether shutdown started: new relays are refused
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// Chain id of the node, if relays are refused because it is not CHAIN_ID
+    pub fn wrong_chain(&self) -> Option<u64> {
+        self.wrong_chain.get().copied()
+    }
+
+    /// Stop accepting new relays and wait (up to `timeout`) for the in-flight ones.
+    /// Returns the transactions that were sent but are still not mined
+    pub async fn drain(&self, timeout: Duration) -> Vec<(TxHash, &'static str)> {
+        self.draining.store(true, Ordering::SeqCst);
+
+        let idle = async {
+            loop {
+                // Subscribe before checking, to not miss the last notification
+                let notified = self.idle.notified();
+                if self.in_flight.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+                notified.await;
+            }
+        };
+
+        if tokio::time::timeout(timeout, idle).await.is_err() {
+            warn!(
+                "{} relayed transaction(s) still in flight after {:?}",
+                self.in_flight.load(Ordering::SeqCst),
+                timeout
+            );
+
+            // Relays waiting for their receipt report their transaction as unconfirmed, so
+            // that their requests record it before the server stops
+            self.abandoned.store(true, Ordering::SeqCst);
+            self.abandon.notify_waiters();
+        }
+
+        self.submitted
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(hash, method)| (*hash, *method))
+            .collect()
+    }
+}
+
+impl RelayGuard<'_> {
+    /// Record the hash of the transaction once it has been sent to the node
+    pub fn submitted(&mut self, tx_hash: TxHash, method: &'static str) {
+        self.tracker
+            .submitted
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(tx_hash, method);
+        self.tx_hash = Some(tx_hash);
+    }
+
+    /// Resolve once shutdown gave up waiting for the in-flight relays
+    pub async fn abandoned(&self) {
+        loop {
+            // Subscribe before checking, to not miss the notification
+            let notified = self.tracker.abandon.notified();
+            if self.tracker.abandoned.load(Ordering::SeqCst) {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for RelayGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(tx_hash) = self.tx_hash {
+            self.tracker
+                .submitted
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .remove(&tx_hash);
+        }
+
+        if self.tracker.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.tracker.idle.notify_waiters();
+        }
+    }
+}
+
+/// Store transactions that were still pending at shutdown in the job table
+pub async fn persist_jobs(app_state: &AppState, pending: Vec<(TxHash, &'static str)>) {
+    let jobs: Vec<RelayJob> = pending
+        .iter()
+        .map(|(hash, method)| RelayJob::new(hash.to_string(), method))
+        .collect();
+
+    match db::query(&app_state.db, move |conn| RelayJob::save_all(&jobs, conn)).await {
+        Ok(count) => info!("Persisted {} pending relayed transaction(s)", count),
+        // Last resort: keep the hashes in the logs so they can be checked by hand
+        Err(e) => {
+            for (hash, method) in pending {
+                error!(
+                    "Unable to persist pending {} transaction {}: {}",
+                    method, hash, e
+                );
+            }
+        }
+    }
+}
+
+/// Store a transaction sent by a request but not confirmed, so that its outcome is
+/// reconciled later
+pub async fn track(app_state: &AppState, tx_hash: TxHash, method: &'static str) {
+    persist_jobs(app_state, vec![(tx_hash, method)]).await;
+}
+
+/// Check the outcome of the pending relayed transactions every
+/// RELAY_RECONCILE_INTERVAL_SECS, and settle the rows locked while they were relayed
+pub async fn reconcile_jobs(app_state: web::Data<AppState>) {
+    let interval = Duration::from_secs(
+        config::parse_or(
+            "RELAY_RECONCILE_INTERVAL_SECS",
+            DEFAULT_RECONCILE_INTERVAL_SECS,
+        )
+        .max(1),
+    );
+
+    loop {
+        reconcile_pending(&app_state).await;
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn reconcile_pending(app_state: &AppState) {
+    let jobs = match db::query(&app_state.db, RelayJob::get_pending).await {
+        Ok(jobs) => jobs,
+        Err(e) => return warn!("Unable to load pending relay jobs: {}", e),
+    };
+
+    for job in jobs {
+        let Ok(hash) = job.tx_hash.parse::<TxHash>() else {
+            warn!("Ignoring relay job with invalid hash {}", job.tx_hash);
+            continue;
+        };
+
+        let status = match app_state.contracts.votechain.transaction_status(hash).await {
+            Ok(Some(true)) => CONFIRMED,
+            Ok(Some(false)) => REVERTED,
+            // Not mined yet, checked again at the next round
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Unable to check relayed transaction {}: {}", hash, e);
+                continue;
+            }
+        };
+
+        // The job stays pending until its rows are settled, to retry at the next round
+        let settled = match job.method.as_str() {
+            "cast_vote" => settle_vote(app_state, hash, status == CONFIRMED).await,
+            "create_poll" => settle_poll(app_state, hash, status == CONFIRMED).await,
+            _ => Ok(()),
+        };
+        if let Err(e) = settled {
+            warn!("Unable to settle relayed transaction {}: {}", hash, e);
+            continue;
+        }
+
+        info!("Relayed {} transaction {} {}", job.method, hash, status);
+        if let Err(e) = db::query(&app_state.db, move |conn| job.set_status(status, conn)).await {
+            warn!("Unable to update relay job {}: {}", hash, e);
+        }
+    }
+}
+
+// Mark the votes and the reveals of a mined vote as relayed and issue their receipts, or
+// unlock them if it reverted
+async fn settle_vote(app_state: &AppState, hash: TxHash, mined: bool) -> Result<(), AppError> {
+    let key = hash.to_string();
+    if !mined {
+        return db::query(&app_state.db, move |conn| {
+            PollVote::release_tx(conn, &key)?;
+            VoteCommitment::release_tx(conn, &key)
+        })
+        .await
+        .map(|_| ());
+    }
+
+    let vote = app_state.contracts.votechain.get_relayed_vote(hash).await?;
+    let voters = db::query(&app_state.db, move |conn| {
+        let votes = PollVote::confirm_tx(conn, &key)?;
+        let reveals = VoteCommitment::confirm_tx(conn, &key)?;
+        Ok::<_, diesel::result::Error>(
+            votes
+                .into_iter()
+                .map(|vote| vote.voter)
+                .chain(reveals.into_iter().map(|reveal| reveal.voter))
+                .collect::<Vec<_>>(),
+        )
+    })
+    .await?;
+
+    let Some(vote) = vote else {
+        return Ok(());
+    };
+    app_state.cache.invalidate(vote.poll_id).await;
+    for voter in voters {
+        match voter.parse() {
+            Ok(voter) => {
+                receipts::issue(app_state, voter, &vote).await;
+            }
+            Err(_) => warn!("Vote of invalid voter {} not given a receipt", voter),
+        }
+    }
+    Ok(())
+}
+
+// Mark the drafts published by a mined poll creation as published and record their
+// authors as owners, or unlock them if it reverted
+async fn settle_poll(app_state: &AppState, hash: TxHash, mined: bool) -> Result<(), AppError> {
+    let key = hash.to_string();
+    let created = match mined {
+        true => app_state.contracts.votechain.get_created_poll(hash).await?,
+        false => None,
+    };
+    let Some(poll_id) = created else {
+        return db::query(&app_state.db, move |conn| PollDraft::release_tx(conn, &key))
+            .await
+            .map(|_| ());
+    };
+
+    let created_poll = poll_id.to_string();
+    let drafts = db::query(&app_state.db, move |conn| {
+        PollDraft::confirm_tx(conn, &key, &created_poll)
+    })
+    .await?;
+
+    let receipt = PollRecipt {
+        poll_id,
+        transaction_hash: hash,
+    };
+    for draft in drafts {
+        match draft.owner.parse() {
+            Ok(author) => owners::record(app_state, author, &receipt).await,
+            Err(_) => warn!("Draft {} of invalid author {}", draft.id, draft.owner),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TX: TxHash = TxHash::repeat_byte(1);
+
+    #[actix_web::test]
+    async fn drain_waits_for_in_flight_relays() {
+        let tracker = RelayTracker::default();
+        let mut guard = tracker.begin().unwrap();
+        guard.submitted(TX, "cast_vote");
+
+        let mined = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(guard);
+        };
+        let (pending, ()) = tokio::join!(tracker.drain(Duration::from_secs(5)), mined);
+        assert!(pending.is_empty());
+
+        // New relays are refused once the drain started
+        assert!(tracker.is_draining());
+        assert!(matches!(tracker.begin(), Err(ContractError::ShuttingDown)));
+    }
+
+    #[actix_web::test]
+    async fn drain_times_out_and_abandons_pending_relays() {
+        let tracker = RelayTracker::default();
+        let mut guard = tracker.begin().unwrap();
+        guard.submitted(TX, "create_poll");
+
+        let pending = tracker.drain(Duration::from_millis(50)).await;
+        assert_eq!(pending, vec![(TX, "create_poll")]);
+
+        // The relay stops waiting for its receipt
+        tokio::time::timeout(Duration::from_secs(1), guard.abandoned())
+            .await
+            .expect("relay not abandoned");
+    }
+}
@@ -0,0 +1,37 @@
+use std::{future::Future, time::Duration};
+
+use log::warn;
+
+// Delay before the first retry, doubled at each attempt
+const INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+// Upper bound of the delay between two attempts
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Run `op` until it succeeds or `attempts` are exhausted, waiting with exponential
+/// backoff between attempts. Returns the last error on failure
+pub async fn with_backoff<T, E, F, Fut>(what: &str, attempts: u32, mut op: F) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut delay = INITIAL_DELAY;
+    let mut attempt = 1;
+
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= attempts => return Err(e),
+            Err(e) => {
+                warn!(
+                    "{} failed (attempt {}/{}): {}. Retrying in {:?}...",
+                    what, attempt, attempts, e, delay
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_DELAY);
+                attempt += 1;
+            }
+        }
+    }
+}
@@ -1,3 +1,7 @@
+pub mod allowlists;
 pub mod auth;
+pub mod drafts;
 pub mod health;
+pub mod metrics;
 pub mod polls;
+pub mod receipts;
@@ -0,0 +1,49 @@
+use actix_web::{post, web, HttpResponse, Responder, Result};
+use std::{sync::Arc, time::SystemTime};
+
+use crate::{
+    db, eligibility,
+    errors::{ApiErrorResponse, AppError},
+    merkle::MerkleTree,
+    models::merkle_allowlists::AllowlistEntry,
+    AppState,
+};
+
+#[post("/allowlists")]
+pub async fn route(
+    body: String,                  // CSV file, one address per line
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    let addresses = eligibility::parse_csv(&body)
+        .map_err(|errors| ApiErrorResponse::ValidationFailed { errors })?;
+
+    // The root is committed in the metadata of the polls using the list. Hashing large
+    // lists takes a while, off the async runtime
+    let leaves = addresses.clone();
+    let tree = web::block(move || MerkleTree::new(&leaves))
+        .await
+        .map_err(|_| ApiErrorResponse::InternalServerError)?;
+    let root = tree.root();
+
+    let now = Some(SystemTime::now());
+    let entries: Vec<AllowlistEntry> = addresses
+        .iter()
+        .map(|address| AllowlistEntry {
+            root: root.to_string(),
+            address: address.to_string(),
+            created_at: now,
+        })
+        .collect();
+    db::query(&app_data.db, move |conn| {
+        AllowlistEntry::save_all(conn, &entries)
+    })
+    .await?;
+
+    // Served by `/poll/{id}/proof/{address}` without rebuilding it
+    app_data.trees.insert(Arc::new(tree));
+
+    Ok(HttpResponse::Created().json(serde_json::json!({
+        "root": root,
+        "addresses": addresses.len(),
+    })))
+}
@@ -0,0 +1,31 @@
+use actix_web::{get, web, Responder, Result};
+use alloy::primitives::B256;
+
+use crate::{
+    db,
+    errors::{ApiErrorResponse, AppError},
+    models::merkle_allowlists::AllowlistEntry,
+    AppState,
+};
+
+#[get("/allowlists/{root}")]
+pub async fn route(
+    path: web::Path<B256>,         // Merkle root taken from the path
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    let root = path.into_inner();
+
+    // Published so that anyone can rebuild the tree and check its root
+    let addresses = db::query(&app_data.db, move |conn| {
+        AllowlistEntry::addresses(conn, &root.to_string())
+    })
+    .await?;
+    if addresses.is_empty() {
+        return Err(ApiErrorResponse::NotFound.into());
+    }
+
+    Ok(web::Json(serde_json::json!({
+        "root": root,
+        "addresses": addresses,
+    })))
+}
@@ -0,0 +1,8 @@
+pub mod approve;
+pub mod create;
+pub mod delete;
+pub mod get;
+pub mod list;
+pub mod preview;
+pub mod publish;
+pub mod update;
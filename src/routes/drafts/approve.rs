@@ -0,0 +1,40 @@
+use actix_web::{post, web, Responder, Result};
+
+use crate::{
+    auth::Claims,
+    db, drafts,
+    errors::{ApiErrorResponse, AppError},
+    models::poll_drafts::PollDraft,
+    AppState,
+};
+
+#[post("/drafts/{id}/approve")]
+pub async fn route(
+    path: web::Path<i32>,          // draft_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    let draft_id = path.into_inner();
+
+    // Approval is a second pair of eyes: an administrator other than the author
+    let admin = drafts::author(&claims)?;
+    let approver = admin.clone();
+    let claims = claims.into_inner();
+    db::query(&app_data.db, move |conn| {
+        let draft = drafts::get_visible(conn, draft_id, &claims)?;
+        if !claims.is_admin() || drafts::is_author(&draft, &claims) {
+            return Err(ApiErrorResponse::Forbidden.into());
+        }
+        if !draft.is_editable() || PollDraft::approve(conn, draft_id, &approver)? == 0 {
+            return Err(AppError::from(ApiErrorResponse::DraftPublished));
+        }
+        Ok(())
+    })
+    .await?;
+
+    Ok(web::Json(serde_json::json!({
+        "message": "The draft has been approved.",
+        "draft_id": draft_id,
+        "approved_by": admin,
+    })))
+}
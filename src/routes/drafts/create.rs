@@ -0,0 +1,25 @@
+use actix_web::{post, web, HttpResponse, Responder, Result};
+
+use crate::{
+    auth::Claims,
+    db,
+    drafts::{self, Draft, DraftRequest},
+    errors::AppError,
+    AppState,
+};
+
+#[post("/drafts")]
+pub async fn route(
+    request: web::Json<DraftRequest>,
+    claims: web::ReqData<Claims>,  // Author of the draft
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    let author = drafts::author(&claims)?;
+
+    let draft = request.into_inner().into_new(author);
+    let draft: Draft = db::query(&app_data.db, move |conn| draft.save(conn))
+        .await?
+        .try_into()?;
+
+    Ok(HttpResponse::Created().json(serde_json::json!({ "draft": draft })))
+}
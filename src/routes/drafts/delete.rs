@@ -0,0 +1,31 @@
+use actix_web::{delete, web, HttpResponse, Responder, Result};
+
+use crate::{
+    auth::Claims,
+    db, drafts,
+    errors::{ApiErrorResponse, AppError},
+    models::poll_drafts::PollDraft,
+    AppState,
+};
+
+#[delete("/drafts/{id}")]
+pub async fn route(
+    path: web::Path<i32>,          // draft_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    let draft_id = path.into_inner();
+
+    // Published drafts are kept as the record of their poll
+    let claims = claims.into_inner();
+    db::query(&app_data.db, move |conn| {
+        drafts::get_editable(conn, draft_id, &claims)?;
+        if PollDraft::delete(conn, draft_id)? == 0 {
+            return Err(AppError::from(ApiErrorResponse::DraftPublished));
+        }
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,18 @@
+use actix_web::{get, web, Responder, Result};
+
+use crate::{auth::Claims, db, drafts, errors::AppError, AppState};
+
+#[get("/drafts/{id}")]
+pub async fn route(
+    path: web::Path<i32>,          // draft_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    let (draft_id, claims) = (path.into_inner(), claims.into_inner());
+    let draft = db::query(&app_data.db, move |conn| {
+        drafts::get_visible(conn, draft_id, &claims)
+    })
+    .await?;
+
+    Ok(web::Json(serde_json::json!({ "draft": draft })))
+}
@@ -0,0 +1,32 @@
+use actix_web::{get, web, Responder, Result};
+
+use crate::{
+    auth::Claims,
+    db,
+    drafts::{self, Draft},
+    errors::AppError,
+    models::poll_drafts::PollDraft,
+    AppState,
+};
+
+#[get("/drafts")]
+pub async fn route(
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    // Administrators see every draft (e.g. to approve them), others only their own
+    let author = match claims.is_admin() {
+        true => None,
+        false => Some(drafts::author(&claims)?),
+    };
+
+    let drafts = db::query(&app_data.db, move |conn| {
+        PollDraft::list(conn, author.as_deref())
+    })
+    .await?
+    .into_iter()
+    .map(Draft::try_from)
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(web::Json(serde_json::json!({ "drafts": drafts })))
+}
@@ -0,0 +1,47 @@
+use actix_web::{get, web, Responder, Result};
+
+use crate::{
+    auth::Claims, db, drafts, errors::AppError, metadata, validation::PollRules, AppState,
+};
+
+#[get("/drafts/{id}/preview")]
+pub async fn route(
+    path: web::Path<i32>,          // draft_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the contracts and database
+) -> Result<impl Responder, AppError> {
+    let (draft_id, claims) = (path.into_inner(), claims.into_inner());
+    let draft = db::query(&app_data.db, move |conn| {
+        drafts::get_visible(conn, draft_id, &claims)
+    })
+    .await?;
+    let poll = draft.as_new_poll();
+
+    // Problems that would prevent publishing now, as the chain clock goes
+    let (_, now) = app_data.contracts.votechain.latest_block().await?;
+    let errors = poll.validate(PollRules::get(), now);
+    let approved = !drafts::approval_required() || draft.approved_by.is_some();
+
+    // Description as it would be stored on-chain
+    let metadata_hash = poll.metadata.map(|content| content.to_record().hash);
+    let description = match &metadata_hash {
+        Some(hash) => metadata::commit(poll.description, hash),
+        None => poll.description.to_owned(),
+    };
+
+    Ok(web::Json(serde_json::json!({
+        "draft_id": draft.id,
+        "poll": {
+            "name": poll.name,
+            "description": description,
+            "options": poll.options,
+            "start_time": poll.start_time,
+            "end_time": poll.end_time,
+            "metadata_hash": metadata_hash,
+            "metadata": poll.metadata,
+        },
+        "errors": errors,
+        "approved": approved,
+        "publishable": draft.is_editable() && approved && errors.is_empty(),
+    })))
+}
@@ -0,0 +1,101 @@
+use actix_web::{post, web, Responder, Result};
+use log::error;
+
+use crate::{
+    auth::Claims,
+    contracts::errors::ContractError,
+    db, drafts,
+    errors::{ApiErrorResponse, AppError},
+    models::poll_drafts::{PollDraft, DRAFT, PUBLISHING},
+    relay,
+    routes::polls::create::create_poll,
+    AppState,
+};
+
+#[post("/drafts/{id}/publish")]
+pub async fn route(
+    path: web::Path<i32>,          // draft_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the contracts and database
+) -> Result<impl Responder, AppError> {
+    let draft_id = path.into_inner();
+
+    // The author or an administrator publishes, once approved when required
+    let caller = claims.into_inner();
+    let draft = db::query(&app_data.db, move |conn| {
+        let draft = drafts::get_visible(conn, draft_id, &caller)?;
+        if !draft.is_editable() {
+            return Err(ApiErrorResponse::DraftPublished.into());
+        }
+        if drafts::approval_required() && draft.approved_by.is_none() {
+            return Err(ApiErrorResponse::ApprovalRequired.into());
+        }
+
+        // Lock the draft, so that concurrent requests cannot create the poll twice
+        if !PollDraft::transition(conn, draft_id, DRAFT, PUBLISHING)? {
+            return Err(AppError::from(ApiErrorResponse::DraftPublished));
+        }
+        Ok(draft)
+    })
+    .await?;
+
+    // Validate and relay the transaction (invalid drafts are reported as 422). The author
+    // of the draft manages the poll, even when an administrator publishes it
+    let author = draft
+        .owner
+        .parse()
+        .map_err(|_| ApiErrorResponse::InternalServerError)?;
+    let receipt = match create_poll(&app_data, draft.as_new_poll(), author).await {
+        Ok(receipt) => receipt,
+        Err(AppError::Rpc(ContractError::Unconfirmed { tx_hash, reason })) => {
+            // The poll may still be created: the draft stays locked with its transaction,
+            // instead of being published twice
+            let hash = tx_hash.to_string();
+            let recorded = db::query(&app_data.db, move |conn| {
+                PollDraft::set_tx_hash(conn, draft_id, &hash)
+            })
+            .await;
+            if let Err(e) = recorded {
+                error!(
+                    "Draft {} sent as transaction {} but not recorded: {}",
+                    draft_id, tx_hash, e
+                );
+            }
+            // Published once mined, by the relay reconciliation
+            relay::track(&app_data, tx_hash, "create_poll").await;
+            return Err(ContractError::Unconfirmed { tx_hash, reason }.into());
+        }
+        Err(e) => {
+            // Nothing was created (invalid draft, transaction not sent or reverted): unlock
+            // the draft to fix it or retry
+            db::query(&app_data.db, move |conn| {
+                PollDraft::transition(conn, draft_id, PUBLISHING, DRAFT)
+            })
+            .await?;
+            return Err(e);
+        }
+    };
+
+    // The poll exists now: failing to record it must not report an error, the draft
+    // stays locked instead of being published twice
+    let poll_id = receipt.poll_id.to_string();
+    let tx_hash = receipt.transaction_hash.to_string();
+    let published = poll_id.clone();
+    let recorded = db::query(&app_data.db, move |conn| {
+        PollDraft::set_published(conn, draft_id, &published, &tx_hash)
+    })
+    .await;
+    if let Err(e) = recorded {
+        error!(
+            "Draft {} published as poll {} but not recorded: {}",
+            draft_id, poll_id, e
+        );
+    }
+
+    Ok(web::Json(serde_json::json!({
+        "message": "The draft has been published.",
+        "draft_id": draft_id,
+        "poll_id": receipt.poll_id,
+        "transaction_hash": receipt.transaction_hash,
+    })))
+}
@@ -0,0 +1,34 @@
+use actix_web::{put, web, Responder, Result};
+
+use crate::{
+    auth::Claims,
+    db,
+    drafts::{self, Draft, DraftRequest},
+    errors::{ApiErrorResponse, AppError},
+    models::poll_drafts::PollDraft,
+    AppState,
+};
+
+#[put("/drafts/{id}")]
+pub async fn route(
+    path: web::Path<i32>, // draft_id taken from the path
+    request: web::Json<DraftRequest>,
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    let draft_id = path.into_inner();
+
+    // Only the author edits a draft, until it is published
+    // Edits withdraw the approval, and fail if the draft was published meanwhile
+    let changes = request.into_inner().into_update();
+    let claims = claims.into_inner();
+    let draft: Draft = db::query(&app_data.db, move |conn| {
+        drafts::get_editable(conn, draft_id, &claims)?;
+        PollDraft::update(conn, draft_id, &changes)?
+            .ok_or(AppError::from(ApiErrorResponse::DraftPublished))
+    })
+    .await?
+    .try_into()?;
+
+    Ok(web::Json(serde_json::json!({ "draft": draft })))
+}
@@ -1,3 +1,6 @@
+pub mod live;
+pub mod ready;
+
 use actix_web::{get, Responder, Result};
 
 #[get("/health")]
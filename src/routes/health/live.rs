@@ -0,0 +1,8 @@
+use actix_web::{get, HttpResponse, Responder};
+
+// Liveness probe: the process is up and serving requests. Dependencies are NOT checked here,
+// restarting the service would not fix them (see /health/ready)
+#[get("/health/live")]
+pub async fn route() -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "alive" }))
+}
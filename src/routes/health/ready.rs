@@ -0,0 +1,272 @@
+use std::{
+    future::Future,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::{get, web, HttpResponse, Responder};
+use alloy::primitives::{utils::parse_ether, U256};
+use diesel::{sql_query, RunQueryDsl};
+use serde::Serialize;
+
+use crate::{config, AppState};
+
+// Defaults of the optional readiness settings
+const DEFAULT_MAX_BLOCK_AGE_SECS: u64 = 120;
+const DEFAULT_RELAY_MIN_BALANCE_ETH: &str = "0.01";
+const DEFAULT_MAX_INDEXER_LAG_BLOCKS: u64 = 50;
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CheckStatus {
+    Pass,
+    Fail,
+    Skipped,
+}
+
+#[derive(Serialize)]
+struct Check {
+    status: CheckStatus,
+    critical: bool,   // Whether a failure makes the service not ready
+    latency_ms: u128, // Time spent running the check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Checks {
+    database: Check,
+    chain_id: Check,
+    latest_block: Check,
+    contract: Check,
+    relay_balance: Check,
+    websocket: Check,
+    indexer: Check,
+    relay: Check,
+}
+
+#[derive(Serialize)]
+struct ReadinessResponse {
+    status: &'static str,
+    checks: Checks,
+}
+
+// Run a check, measuring its latency. The future resolves to Ok(detail) on success
+async fn run<F>(critical: bool, check: F) -> Check
+where
+    F: Future<Output = Result<Option<String>, String>>,
+{
+    let start = Instant::now();
+    let result = check.await;
+    let latency_ms = start.elapsed().as_millis();
+
+    match result {
+        Ok(detail) => Check {
+            status: CheckStatus::Pass,
+            critical,
+            latency_ms,
+            detail,
+        },
+        Err(detail) => Check {
+            status: CheckStatus::Fail,
+            critical,
+            latency_ms,
+            detail: Some(detail),
+        },
+    }
+}
+
+// A non-critical check that was not run
+fn skipped(detail: &str) -> Check {
+    Check {
+        status: CheckStatus::Skipped,
+        critical: false,
+        latency_ms: 0,
+        detail: Some(detail.to_owned()),
+    }
+}
+
+// Readiness probe: returns 503 when any critical dependency is unavailable
+#[get("/health/ready")]
+pub async fn route(app_data: web::Data<AppState>) -> impl Responder {
+    let contract = &app_data.contracts.votechain;
+
+    // Database: run a trivial query on a pooled connection
+    let pool = app_data.db.clone();
+    let database = run(true, async move {
+        let result = web::block(move || {
+            let mut conn = pool.get().map_err(|e| e.to_string())?;
+            sql_query("SELECT 1")
+                .execute(&mut conn)
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        result.map(|_| None)
+    });
+
+    // RPC node: must be reachable and on the configured chain. Relays stay refused once
+    // the node was found on another chain
+    let chain_id = run(true, async {
+        let expected = config::parse_or("CHAIN_ID", 0u64);
+        if let Some(actual) = contract.relay().wrong_chain() {
+            return Err(format!(
+                "expected chain id {}, got {}: relaying disabled until restart",
+                expected, actual
+            ));
+        }
+        let actual = contract.chain_id().await.map_err(|e| e.to_string())?;
+        if actual == expected {
+            Ok(Some(format!("chain id {}", actual)))
+        } else {
+            Err(format!("expected chain id {}, got {}", expected, actual))
+        }
+    });
+
+    // Latest block: a stale head means the node is not syncing
+    let latest_block = run(false, async {
+        let max_age = config::parse_or("MAX_BLOCK_AGE_SECS", DEFAULT_MAX_BLOCK_AGE_SECS);
+        let (number, timestamp) = contract.latest_block().await.map_err(|e| e.to_string())?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let age = now.saturating_sub(timestamp);
+
+        let detail = format!("block {} is {}s old", number, age);
+        if age <= max_age {
+            Ok(Some(detail))
+        } else {
+            Err(format!("{} (max {}s)", detail, max_age))
+        }
+    });
+
+    // Contract: code must be deployed at VOTECHAIN_CONTRACT_ADDRESS
+    let deployed = run(true, async {
+        match contract.is_deployed().await.map_err(|e| e.to_string())? {
+            true => Ok(None),
+            false => Err("no contract code at VOTECHAIN_CONTRACT_ADDRESS".to_owned()),
+        }
+    });
+
+    // Relay wallet: must be able to pay for relayed transactions
+    let relay_balance = run(true, async {
+        let min_balance = std::env::var("RELAY_MIN_BALANCE_ETH")
+            .unwrap_or_else(|_| DEFAULT_RELAY_MIN_BALANCE_ETH.to_owned());
+        let threshold: U256 = parse_ether(&min_balance)
+            .map_err(|_| format!("invalid RELAY_MIN_BALANCE_ETH: {}", min_balance))?;
+        let balance = contract.relay_balance().await.map_err(|e| e.to_string())?;
+
+        let detail = format!("balance {} wei, threshold {} wei", balance, threshold);
+        if balance >= threshold {
+            Ok(Some(detail))
+        } else {
+            Err(detail)
+        }
+    });
+
+    // WebSocket endpoint: only needed for subscriptions
+    let websocket = async {
+        if !contract.has_subscriptions() {
+            return skipped("RPC_WS_URL not configured");
+        }
+        run(false, async {
+            let number = contract
+                .ws_block_number()
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some(format!("block {}", number)))
+        })
+        .await
+    };
+
+    // Event indexer: blocks between the head and the last block processed by `events::watch`
+    let indexer = async {
+        let events = &app_data.events;
+        if events.is_subscribed() {
+            // Pushed logs only reveal the blocks with events, quiet blocks are not a lag
+            let detail = match events.last_block() {
+                Some(block) => format!("subscribed, last event at block {}", block),
+                None => "subscribed, no event yet".to_owned(),
+            };
+            return Check {
+                status: CheckStatus::Pass,
+                critical: false,
+                latency_ms: 0,
+                detail: Some(detail),
+            };
+        }
+        let Some(processed) = events.last_block() else {
+            return skipped("no block processed yet");
+        };
+        run(false, async move {
+            let max_lag = config::parse_or(
+                "READY_MAX_INDEXER_LAG_BLOCKS",
+                DEFAULT_MAX_INDEXER_LAG_BLOCKS,
+            );
+            let head = contract.block_number().await.map_err(|e| e.to_string())?;
+            let lag = head.saturating_sub(processed);
+
+            let detail = format!("block {} processed, {} blocks behind", processed, lag);
+            if lag <= max_lag {
+                Ok(Some(detail))
+            } else {
+                Err(format!("{} (max {})", detail, max_lag))
+            }
+        })
+        .await
+    };
+
+    // Relays: refused once shutdown started, so that the load balancer stops routing here
+    let relay = run(true, async {
+        match contract.relay().is_draining() {
+            true => Err("shutting down: relays are refused".to_owned()),
+            false => Ok(None),
+        }
+    });
+
+    let (database, chain_id, latest_block, contract, relay_balance, websocket, indexer, relay) = tokio::join!(
+        database,
+        chain_id,
+        latest_block,
+        deployed,
+        relay_balance,
+        websocket,
+        indexer,
+        relay
+    );
+
+    let checks = Checks {
+        database,
+        chain_id,
+        latest_block,
+        contract,
+        relay_balance,
+        websocket,
+        indexer,
+        relay,
+    };
+
+    let ready = [
+        &checks.database,
+        &checks.chain_id,
+        &checks.latest_block,
+        &checks.contract,
+        &checks.relay_balance,
+        &checks.websocket,
+        &checks.indexer,
+        &checks.relay,
+    ]
+    .iter()
+    .all(|c| !c.critical || !matches!(c.status, CheckStatus::Fail));
+
+    let response = ReadinessResponse {
+        status: if ready { "ready" } else { "not_ready" },
+        checks,
+    };
+
+    if ready {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
+}
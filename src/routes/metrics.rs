@@ -0,0 +1,27 @@
+use actix_web::{get, web, HttpResponse, Responder};
+
+use crate::{
+    errors::{ApiErrorResponse, AppError},
+    metrics::{self, DB_CONNECTIONS},
+    AppState,
+};
+
+#[get("/metrics")]
+pub async fn route(app_data: web::Data<AppState>) -> Result<impl Responder, AppError> {
+    // Refresh gauges that are cheaper to sample on scrape than to keep up to date
+    let pool = app_data.db.state();
+    let idle = i64::from(pool.idle_connections);
+    DB_CONNECTIONS
+        .with_label_values(&["in_use"])
+        .set(i64::from(pool.connections) - idle);
+    DB_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+    DB_CONNECTIONS
+        .with_label_values(&["max"])
+        .set(i64::from(app_data.db.max_size()));
+
+    let body = metrics::render().map_err(|_| ApiErrorResponse::InternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(metrics::content_type())
+        .body(body))
+}
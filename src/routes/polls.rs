@@ -1,4 +1,19 @@
+pub mod cast_vote;
+pub mod clear_eligibility;
+pub mod commit;
+pub mod commitment;
+pub mod commitments;
 pub mod create;
+pub mod eligibility;
+pub mod end;
+pub mod events;
+pub mod finalize;
 pub mod get_poll;
 pub mod get_polls;
-pub mod cast_vote;
\ No newline at end of file
+pub mod metadata;
+pub mod nonces;
+pub mod proof;
+pub mod results;
+pub mod reveal;
+pub mod set_eligibility;
+pub mod websocket;
@@ -1,62 +1,148 @@
 use actix_web::{post, web, HttpResponse, Responder, Result};
-use alloy::primitives::ruint::aliases::U256;
+use alloy::primitives::{ruint::aliases::U256, B256};
+use log::error;
 use serde::Deserialize;
 
-use crate::errors::ApiErrorResponse;
+use crate::{
+    auth::Claims,
+    cache::CacheKey,
+    commit_reveal,
+    contracts::errors::ContractError,
+    db, eligibility,
+    errors::{ApiErrorResponse, AppError},
+    metadata,
+    models::poll_votes::{PollVote, VOTED, VOTING},
+    receipts, relay,
+};
 
-// Definisci la struttura per i dati di input della richiesta POST
+// Input data of the cast vote request
 #[derive(Deserialize)]
 pub struct CastVoteRequest {
-    pub poll_id: u64,   // ID del sondaggio
-    pub option: String, // Opzione selezionata dall'utente
+    pub poll_id: u64,             // ID of the poll
+    pub option: String,           // Option selected by the user
+    pub proof: Option<Vec<B256>>, // Merkle proof, for polls with a Merkle allowlist
 }
 
 #[post("/poll/{id}")]
 pub async fn route(
-    path: web::Path<u64>,                 // Ottieni il poll_id dalla path
-    data: web::Json<CastVoteRequest>,     // Dati inviati dall'utente come JSON
-    app_data: web::Data<crate::AppState>, // Stato dell'applicazione con i contratti
-) -> Result<impl Responder> {
-    // let poll_id_path = path.into_inner();   // Estrarre poll_id dalla path
-    // let poll_id_body = data.poll_id;       // Estrarre poll_id dal corpo JSON
-    //
-    // // Confronta gli ID per sicurezza
-    // if poll_id_path != poll_id_body {
-    //     return Err(ApiErrorResponse::NotFound.into());
-    // }
-    //
-    // let option = data.option.clone(); // Ottieni l'opzione dal corpo JSON
-    //
-    // // Ottieni il contratto del sondaggio
-    // let contract = &app_data.contracts.votechain;
-    //
-    // // Effettua la chiamata alla funzione cast_vote del contratto
-    // let result = contract
-    //     .cast_vote(
-    //         U256::from(poll_id_path),
-    //         option.clone())
-    //     .await;
-    //
-    // // Gestisci il risultato della chiamata al contratto
-    // match result {
-    //     Ok(_) => Ok(HttpResponse::Ok().json({
-    //         serde_json::json!({
-    //             "message": "Your vote has been successfully cast.",
-    //             "poll_id": poll_id_path,
-    //             "option": option,
-    //         })
-    //     })),
-    //     Err(_) => {
-    //         // Restituisci l'errore in caso di fallimento
-    //         Err(ApiErrorResponse::NotFound.into())
-    //     }
-    // }
+    path: web::Path<u64>,                 // poll_id taken from the path
+    data: web::Json<CastVoteRequest>,     // Data sent by the user as JSON
+    claims: web::ReqData<Claims>,         // Identity of the voter
+    app_data: web::Data<crate::AppState>, // Application state with the contracts
+) -> Result<impl Responder, AppError> {
+    let poll_id_path = path.into_inner(); // poll_id from the path
+    let poll_id_body = data.poll_id; // poll_id from the JSON body
+
+    // Compare both IDs for safety
+    if poll_id_path != poll_id_body {
+        return Err(AppError::Validation(
+            "poll_id does not match the poll in the path".to_owned(),
+        ));
+    }
+
+    let option = data.option.clone(); // Option from the JSON body
+
+    // Get the poll contract
+    let contract = &app_data.contracts.votechain;
+
+    // Reject voters missing from the allowlists of the poll before paying for the relay
+    let voter = claims.address().ok_or(ApiErrorResponse::Forbidden)?;
+    let id = U256::from(poll_id_path);
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(id), || contract.get_poll(id))
+        .await?;
+    let poll = db::query(&app_data.db, move |conn| metadata::attach(conn, poll)).await?;
+
+    // Commit-reveal polls only accept votes through `/commit` and `/reveal`
+    if let Some(settings) = commit_reveal::settings(&poll)? {
+        let (_, now) = contract.latest_block().await?;
+        let phase = commit_reveal::phase(settings, &poll.poll, now);
+        return Err(commit_reveal::refusal(phase).into());
+    }
+
+    if !eligibility::check_vote(&app_data, &poll, voter, data.proof.as_deref())
+        .await?
+        .is_eligible()
+    {
+        return Err(ApiErrorResponse::NotEligible.into());
+    }
+
+    // Lock the vote, so that repeated or concurrent requests cannot relay it twice
+    let (poll_key, voter_key) = (poll_id_path.to_string(), voter.to_string());
+    let (poll_ref, voter_ref) = (poll_key.clone(), voter_key.clone());
+    if !db::query(&app_data.db, move |conn| {
+        PollVote::begin(conn, &poll_ref, &voter_ref)
+    })
+    .await?
+    {
+        return Err(ApiErrorResponse::AlreadyVoted.into());
+    }
+
+    // Call the cast_vote function of the contract. Reverts (e.g. poll closed) are mapped
+    // into the matching API errors
+    let (poll_ref, voter_ref) = (poll_key.clone(), voter_key.clone());
+    let vote = match contract
+        .cast_vote(U256::from(poll_id_path), option.clone())
+        .await
+    {
+        Ok(vote) => vote,
+        // The vote may still be mined: it stays locked, with its transaction
+        Err(ContractError::Unconfirmed { tx_hash, reason }) => {
+            let hash = tx_hash.to_string();
+            let recorded = db::query(&app_data.db, move |conn| {
+                PollVote::set_tx_hash(conn, &poll_ref, &voter_ref, &hash, VOTING)
+            })
+            .await;
+            if let Err(e) = recorded {
+                error!(
+                    "Vote of {} in poll {} sent but not recorded: {}",
+                    voter_key, poll_key, e
+                );
+            }
+            // Settled once mined, by the relay reconciliation
+            relay::track(&app_data, tx_hash, "cast_vote").await;
+            return Err(ContractError::Unconfirmed { tx_hash, reason }.into());
+        }
+        Err(e) => {
+            // Nothing was relayed: unlock the vote to retry
+            db::query(&app_data.db, move |conn| {
+                PollVote::release(conn, &poll_ref, &voter_ref)
+            })
+            .await?;
+            return Err(e.into());
+        }
+    };
+
+    // The cached poll is outdated once the transaction is mined
+    app_data.cache.invalidate(U256::from(poll_id_path)).await;
+
+    // The vote is relayed now: failing to record it must not report an error, it stays
+    // locked instead of being relayed twice
+    let hash = vote.transaction_hash.to_string();
+    let (poll_ref, voter_ref) = (poll_key.clone(), voter_key.clone());
+    let recorded = db::query(&app_data.db, move |conn| {
+        PollVote::set_tx_hash(conn, &poll_ref, &voter_ref, &hash, VOTED)
+    })
+    .await;
+    if let Err(e) = recorded {
+        error!(
+            "Vote of {} in poll {} relayed but not recorded: {}",
+            voter_key, poll_key, e
+        );
+    }
+
+    // Signed receipt, to check later that the vote was counted as cast
+    let receipt = receipts::issue(&app_data, voter, &vote).await;
 
     Ok(HttpResponse::Ok().json({
         serde_json::json!({
             "message": "Your vote has been successfully cast.",
-            "poll_id": 10,
-            "option": "ciao",
+            "poll_id": poll_id_path,
+            "option": option,
+            "transaction_hash": vote.transaction_hash,
+            "receipt": receipt,
+            "contract_receipt": vote.contract_receipt,
         })
     }))
 }
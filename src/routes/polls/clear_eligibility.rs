@@ -0,0 +1,27 @@
+use actix_web::{delete, web, HttpResponse, Responder, Result};
+use alloy::primitives::U256;
+
+use crate::{
+    auth::Claims, db, errors::AppError, models::poll_eligibility::EligibleVoter, owners, AppState,
+};
+
+#[delete("/poll/{id}/eligibility")]
+pub async fn route(
+    path: web::Path<u64>,          // poll_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the contracts and database
+) -> Result<impl Responder, AppError> {
+    let poll_id = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Only the creator of the poll (or an admin) may open it to everyone
+    let poll = contract.get_poll(U256::from(poll_id)).await?;
+    owners::ensure_manager(&app_data, &claims, &poll).await?;
+
+    db::query(&app_data.db, move |conn| {
+        EligibleVoter::remove_all(conn, &poll_id.to_string())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,81 @@
+use actix_web::{post, web, Responder, Result};
+use alloy::primitives::{ruint::aliases::U256, B256};
+use serde::Deserialize;
+
+use crate::{
+    auth::Claims,
+    cache::CacheKey,
+    commit_reveal::{self, Phase},
+    db, eligibility,
+    errors::{ApiErrorResponse, AppError},
+    metadata,
+    models::vote_commitments::{VoteCommitment, COMMITTED},
+    AppState,
+};
+
+#[derive(Deserialize)]
+struct CommitRequest {
+    commitment: B256, // keccak256(abi.encodePacked(poll_id, voter, option, salt))
+    proof: Option<Vec<B256>>, // Merkle proof, for polls with a Merkle allowlist
+}
+
+#[post("/poll/{id}/commit")]
+pub async fn route(
+    path: web::Path<u64>,           // poll_id taken from the path
+    data: web::Json<CommitRequest>, // Commitment sent by the user as JSON
+    claims: web::ReqData<Claims>,   // Identity of the voter
+    app_data: web::Data<AppState>,  // Application state with the contracts and database
+) -> Result<impl Responder, AppError> {
+    let poll_id = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Unknown polls are mapped to 404
+    let voter = claims.address().ok_or(ApiErrorResponse::Forbidden)?;
+    let id = U256::from(poll_id);
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(id), || contract.get_poll(id))
+        .await?;
+    let poll = db::query(&app_data.db, move |conn| metadata::attach(conn, poll)).await?;
+
+    // Only commit-reveal polls take commitments, until the commit deadline
+    let settings = commit_reveal::settings(&poll)?.ok_or(ApiErrorResponse::NotFound)?;
+    let (_, now) = contract.latest_block().await?;
+    let phase = commit_reveal::phase(settings, &poll.poll, now);
+    if phase != Phase::Commit {
+        return Err(commit_reveal::refusal(phase).into());
+    }
+
+    // Eligibility is checked once, when committing
+    if !eligibility::check_vote(&app_data, &poll, voter, data.proof.as_deref())
+        .await?
+        .is_eligible()
+    {
+        return Err(ApiErrorResponse::NotEligible.into());
+    }
+
+    // A new commitment replaces the previous one of the voter
+    let now = Some(std::time::SystemTime::now());
+    let commitment = VoteCommitment {
+        poll_id: poll_id.to_string(),
+        voter: voter.to_string(),
+        commitment: data.commitment.to_string(),
+        status: COMMITTED.to_owned(),
+        tx_hash: None,
+        created_at: now,
+        updated_at: now,
+    };
+    let saved = db::query(&app_data.db, move |conn| commitment.save(conn)).await?;
+
+    // Reveals are relayed as votes, which the contract refuses after the end time: the
+    // reveal window closes with the poll, not after it
+    Ok(web::Json(serde_json::json!({
+        "message": "Your vote has been committed, reveal it between the commit deadline and the end of the poll.",
+        "poll_id": poll_id,
+        "voter": voter,
+        "commitment": saved.commitment,
+        "commit_end_time": settings.commit_end_time,
+        "reveal_start_time": settings.commit_end_time,
+        "reveal_end_time": poll.poll.end_time,
+    })))
+}
@@ -0,0 +1,44 @@
+use actix_web::{get, web, Responder, Result};
+use alloy::primitives::{ruint::aliases::U256, Address};
+
+use crate::{
+    cache::CacheKey,
+    commit_reveal, db,
+    errors::{ApiErrorResponse, AppError},
+    metadata,
+    models::vote_commitments::VoteCommitment,
+    AppState,
+};
+
+#[get("/poll/{id}/commitments/{address}")]
+pub async fn route(
+    path: web::Path<(u64, Address)>, // poll_id and voter taken from the path
+    app_data: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (poll_id, address) = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Unknown polls are mapped to 404
+    let id = U256::from(poll_id);
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(id), || contract.get_poll(id))
+        .await?;
+    let poll = db::query(&app_data.db, move |conn| metadata::attach(conn, poll)).await?;
+    commit_reveal::settings(&poll)?.ok_or(ApiErrorResponse::NotFound)?;
+
+    // Voters who did not commit are reported as such, not as 404
+    let commitment = db::query(&app_data.db, move |conn| {
+        VoteCommitment::find(conn, &poll_id.to_string(), &address.to_string())
+    })
+    .await?;
+
+    Ok(web::Json(serde_json::json!({
+        "poll_id": poll_id,
+        "address": address,
+        "committed": commitment.is_some(),
+        "commitment": commitment.as_ref().map(|c| &c.commitment),
+        "status": commitment.as_ref().map(|c| &c.status),
+        "transaction_hash": commitment.as_ref().and_then(|c| c.tx_hash.as_ref()),
+    })))
+}
@@ -0,0 +1,45 @@
+use actix_web::{get, web, Responder, Result};
+use alloy::primitives::ruint::aliases::U256;
+
+use crate::{
+    cache::CacheKey,
+    commit_reveal, db,
+    errors::{ApiErrorResponse, AppError},
+    metadata,
+    models::vote_commitments::VoteCommitment,
+    AppState,
+};
+
+#[get("/poll/{id}/commitments")]
+pub async fn route(
+    path: web::Path<u64>,          // poll_id taken from the path
+    app_data: web::Data<AppState>, // Application state with the contracts and database
+) -> Result<impl Responder, AppError> {
+    let poll_id = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Unknown polls are mapped to 404
+    let id = U256::from(poll_id);
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(id), || contract.get_poll(id))
+        .await?;
+    let poll = db::query(&app_data.db, move |conn| metadata::attach(conn, poll)).await?;
+    let settings = commit_reveal::settings(&poll)?.ok_or(ApiErrorResponse::NotFound)?;
+
+    // Progress of the poll, without anything about the votes themselves
+    let (committed, revealed) = db::query(&app_data.db, move |conn| {
+        VoteCommitment::count(conn, &poll_id.to_string())
+    })
+    .await?;
+    let (_, now) = contract.latest_block().await?;
+
+    Ok(web::Json(serde_json::json!({
+        "poll_id": poll_id,
+        "phase": commit_reveal::phase(settings, &poll.poll, now),
+        "commit_end_time": settings.commit_end_time,
+        "end_time": poll.poll.end_time,
+        "committed": committed,
+        "revealed": revealed,
+    })))
+}
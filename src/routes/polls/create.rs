@@ -1,51 +1,155 @@
 use actix_web::{post, web, HttpResponse, Responder, Result};
-use alloy::primitives::U256;
+use alloy::primitives::Address;
+use log::warn;
 use serde::Deserialize;
 
-use crate::AppState;
+use crate::{
+    auth::Claims,
+    contracts::{errors::ContractError, votechain::PollRecipt},
+    db, drafts,
+    errors::{ApiErrorResponse, AppError, FieldError},
+    metadata::{self, PollMetadata},
+    models::merkle_allowlists::AllowlistEntry,
+    owners, relay,
+    timestamp::Timestamp,
+    validation::{NewPoll, PollRules},
+    AppState,
+};
 
 #[derive(Deserialize)]
 struct CreatePollRequest {
     name: String,
     description: String,
     options: Vec<String>,
-    start_time: U256,
-    end_time: U256,
+    start_time: Timestamp, // RFC 3339 or unix seconds
+    end_time: Timestamp,
+    metadata: Option<PollMetadata>, // Rich content stored off-chain
 }
 
 #[post("/polls")]
 pub async fn route(
     request: web::Json<CreatePollRequest>,
+    claims: web::ReqData<Claims>, // Creator of the poll
     app_data: web::Data<AppState>,
-) -> Result<impl Responder> {
+) -> Result<impl Responder, AppError> {
+    let creator = claims.address().ok_or(ApiErrorResponse::Forbidden)?;
+
+    // Polls that need an approval go through drafts, administrators may still create them
+    if drafts::approval_required() && !claims.is_admin() {
+        return Err(ApiErrorResponse::Forbidden.into());
+    }
+
     // Extract request data
     let req = request.into_inner();
+    let poll = NewPoll {
+        name: &req.name,
+        description: &req.description,
+        options: &req.options,
+        start_time: req.start_time,
+        end_time: req.end_time,
+        metadata: req.metadata.as_ref(),
+    };
+
+    // Reverts are mapped into the matching API errors. A poll that may still be created is
+    // checked by the relay reconciliation (its creator is then left to the administrators)
+    let receipt = match create_poll(&app_data, poll, creator).await {
+        Ok(receipt) => receipt,
+        Err(AppError::Rpc(ContractError::Unconfirmed { tx_hash, reason })) => {
+            relay::track(&app_data, tx_hash, "create_poll").await;
+            return Err(ContractError::Unconfirmed { tx_hash, reason }.into());
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(HttpResponse::Ok().json(receipt))
+}
+
+/// Validate a poll and create it on-chain on behalf of `creator`, who may then manage it
+/// (also used to publish drafts)
+pub async fn create_poll(
+    app_data: &AppState,
+    poll: NewPoll<'_>,
+    creator: Address,
+) -> Result<PollRecipt, AppError> {
     let contract = &app_data.contracts.votechain;
 
-    // Convert the start and end times to the number of seconds since the Unix epoch.
-    // NOTE: This is necessary because the OffsetDateTime type is not directly compatible with U256.
-    // let unix_start_time = req.start_time.unix_timestamp();
-    // let unix_end_time = req.end_time.unix_timestamp();
+    // Reject invalid polls before paying for a transaction that would revert. Times are
+    // compared with the chain clock, as the contract does
+    let (head, now) = contract.latest_block().await?;
+    let errors = poll.validate(PollRules::get(), now);
+    if !errors.is_empty() {
+        return Err(ApiErrorResponse::ValidationFailed { errors }.into());
+    }
 
-    // Call the contract’s create_poll function.
-    // Assuming `create_poll` returns a Result<(), ContractError> or similar.
-    // Adjust the call to match your actual contract interface.
-    // Example:
-    let tx_result = contract
-        .create_poll(
-            req.name,
-            req.description,
-            req.options,
-            req.start_time,
-            req.end_time,
-        )
-        .await;
+    // Proofs are served from the addresses of the Merkle allowlist, it must be known
+    if let Some(root) = poll.metadata.and_then(|metadata| metadata.allowlist_root) {
+        let known = db::query(&app_data.db, move |conn| {
+            AllowlistEntry::exists(conn, &root.to_string())
+        })
+        .await?;
+        if !known {
+            let errors = vec![FieldError::new(
+                "metadata.allowlist_root",
+                "is unknown, upload the allowlist first",
+            )];
+            return Err(ApiErrorResponse::ValidationFailed { errors }.into());
+        }
+    }
+
+    // Balances are read at the snapshot block, which must be mined and hold the token
+    if let Some(gate) = poll
+        .metadata
+        .and_then(|metadata| metadata.token_gate.as_ref())
+    {
+        let reason = if gate.snapshot_block > head {
+            Some("must not be after the latest block")
+        } else {
+            match contract
+                .token_balance(gate.token, contract.relay_address(), gate.snapshot_block)
+                .await
+            {
+                Ok(_) => None,
+                // Unavailable nodes are not the fault of the request
+                Err(e @ (ContractError::Rpc(_) | ContractError::ShuttingDown)) => {
+                    return Err(e.into())
+                }
+                Err(e) => {
+                    warn!("Token gate of new poll rejected: {}", e);
+                    Some("no token contract answers balanceOf at this block")
+                }
+            }
+        };
 
-    match tx_result {
-        Ok(r) => Ok(HttpResponse::Ok().json(r)),
-        Err(e) => {
-            // Log or handle error as needed
-            Ok(HttpResponse::InternalServerError().body(format!("Failed to create poll: {:?}", e)))
+        if let Some(reason) = reason {
+            let errors = vec![FieldError::new(
+                "metadata.token_gate.snapshot_block",
+                reason,
+            )];
+            return Err(ApiErrorResponse::ValidationFailed { errors }.into());
         }
     }
+
+    // Store the metadata first, the description commits to its hash
+    let description = match poll.metadata {
+        Some(content) => {
+            let record = content.to_record();
+            let hash = record.hash.clone();
+            db::query(&app_data.db, move |conn| record.save(conn)).await?;
+            metadata::commit(poll.description, &hash)
+        }
+        None => poll.description.to_owned(),
+    };
+
+    // Call the contract’s create_poll function
+    let receipt = contract
+        .create_poll(
+            poll.name.to_owned(),
+            description,
+            poll.options.to_vec(),
+            poll.start_time,
+            poll.end_time,
+        )
+        .await?;
+    owners::record(app_data, creator, &receipt).await;
+
+    Ok(receipt)
 }
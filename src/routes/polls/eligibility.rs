@@ -0,0 +1,31 @@
+use actix_web::{get, web, Responder, Result};
+use alloy::primitives::{Address, U256};
+
+use crate::{cache::CacheKey, db, eligibility, errors::AppError, metadata, AppState};
+
+#[get("/poll/{id}/eligibility/{address}")]
+pub async fn route(
+    path: web::Path<(u64, Address)>, // poll_id and voter taken from the path
+    app_data: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (poll_id, address) = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Unknown polls are mapped to 404
+    let id = U256::from(poll_id);
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(id), || contract.get_poll(id))
+        .await?;
+
+    // Its metadata may commit to a Merkle allowlist or a token gate
+    let poll = db::query(&app_data.db, move |conn| metadata::attach(conn, poll)).await?;
+    let eligibility = eligibility::check(&app_data, &poll, address).await?;
+
+    Ok(web::Json(serde_json::json!({
+        "poll_id": poll_id,
+        "address": address,
+        "eligible": eligibility.is_eligible(),
+        "reason": eligibility,
+    })))
+}
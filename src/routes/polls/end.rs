@@ -0,0 +1,30 @@
+use actix_web::{post, web, HttpResponse, Responder, Result};
+use alloy::primitives::ruint::aliases::U256;
+
+use crate::{auth::Claims, errors::AppError, owners, AppState};
+
+#[post("/poll/{id}/end")]
+pub async fn route(
+    path: web::Path<u64>,          // poll_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the contracts
+) -> Result<impl Responder, AppError> {
+    let poll_id = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Only the creator of the poll (or an admin) may end it
+    let poll = contract.get_poll(U256::from(poll_id)).await?;
+    owners::ensure_manager(&app_data, &claims, &poll).await?;
+
+    // Relay the transaction (already ended polls revert and are mapped to 422)
+    let transaction_hash = contract.end_poll(U256::from(poll_id)).await?;
+
+    // The cached poll is outdated once the transaction is mined
+    app_data.cache.invalidate(U256::from(poll_id)).await;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "The poll has been ended.",
+        "poll_id": poll_id,
+        "transaction_hash": transaction_hash,
+    })))
+}
@@ -0,0 +1,62 @@
+use std::{convert::Infallible, time::Duration};
+
+use actix_web::{
+    get,
+    web::{self, Bytes},
+    HttpResponse, Responder, Result,
+};
+use alloy::primitives::ruint::aliases::U256;
+use futures::{stream, StreamExt};
+
+use crate::{
+    auth::Claims,
+    errors::AppError,
+    events::{self, Listener},
+    AppState,
+};
+
+// Comment sent when idle, so that proxies do not close the connection
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Encode a message in the SSE wire format
+fn frame(name: &str, data: &str) -> Bytes {
+    Bytes::from(format!("event: {}\ndata: {}\n\n", name, data))
+}
+
+#[get("/poll/{id}/events")]
+pub async fn route(
+    path: web::Path<u64>,          // poll_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller, the stream ends with the token
+    app_data: web::Data<AppState>, // Application state with the contracts
+) -> Result<impl Responder, AppError> {
+    let poll_id = U256::from(path.into_inner());
+
+    // Unknown polls are mapped to 404 before opening the stream
+    let snapshot = events::snapshot(&app_data, poll_id).await?;
+    let listener = Listener::new(&app_data, poll_id, &claims);
+    let keep_alive = tokio::time::interval_at(
+        tokio::time::Instant::now() + KEEP_ALIVE_INTERVAL,
+        KEEP_ALIVE_INTERVAL,
+    );
+
+    let updates = stream::unfold(
+        (listener, keep_alive),
+        |(mut listener, mut keep_alive)| async move {
+            let frame = tokio::select! {
+                received = listener.next() => {
+                    let received = received?;
+                    frame(received.name(), &received.to_json())
+                }
+                _ = keep_alive.tick() => Bytes::from_static(b": keep-alive\n\n"),
+            };
+            Some((Ok::<_, Infallible>(frame), (listener, keep_alive)))
+        },
+    );
+
+    let body = stream::once(async move { Ok(frame("snapshot", &snapshot)) }).chain(updates);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body))
+}
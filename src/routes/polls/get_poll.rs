@@ -1,45 +1,42 @@
-use actix_web::{get, web, HttpResponse, Responder, Result};
+use actix_web::{get, web, HttpRequest, Responder, Result};
 use alloy::primitives::ruint::aliases::U256;
 use serde::Serialize;
 
-use crate::{contracts::votechain::Poll, errors::ApiErrorResponse};
+use crate::{
+    cache::{self, CacheKey},
+    db,
+    errors::AppError,
+    metadata::{self, PollView},
+};
 
 #[derive(Serialize)]
 #[serde(untagged)]
 enum GetPollApiResponse {
-    Success { poll: Poll },
+    Success { poll: PollView },
 }
 
 #[get("/poll/{id}")]
 pub async fn route(
+    req: HttpRequest,
     path: web::Path<u64>,
     app_data: web::Data<crate::AppState>,
-) -> Result<impl Responder> {
-    // // Get VoteChain contract from app_data
-    // let contract = &app_data.contracts.votechain;
-    // let poll_id = path.into_inner();
-    //
-    // // Fetch poll with passed ID
-    // let poll = contract.get_poll(U256::from(poll_id)).await;
+) -> Result<impl Responder, AppError> {
+    // Get VoteChain contract from app_data
+    let contract = &app_data.contracts.votechain;
+    let poll_id = U256::from(path.into_inner());
 
-    let poll = Poll {
-        name: "Poll 1".to_string(),
-        description: "Poll 1".to_string(),
-        start_time: U256::from(0),
-        end_time: U256::from(0),
-        winner: "Winner".to_string(),
-        is_ended: true,
-    };
+    // Fetch poll with passed ID, unless cached (unknown polls are mapped to 404)
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(poll_id), || contract.get_poll(poll_id))
+        .await?;
 
-    // // Retunr poll if it exists, otherwise return 404
-    // match poll {
-    //     Ok(e) => {
-    //         let poll = e;
-    //         Ok(HttpResponse::Ok().json(GetPollApiResponse::Success { poll }))
-    //     }
-    //     Err(_) => Err(ApiErrorResponse::NotFound.into()),
-    // }
-    //
+    // Attach its off-chain metadata
+    let poll = db::query(&app_data.db, move |conn| metadata::attach(conn, poll)).await?;
 
-    Ok(HttpResponse::Ok().json(GetPollApiResponse::Success { poll }))
+    // Clients sending the ETag they got get a 304 while the poll is unchanged
+    Ok(cache::json_with_etag(
+        &req,
+        &GetPollApiResponse::Success { poll },
+    ))
 }
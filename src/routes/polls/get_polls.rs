@@ -1,35 +1,29 @@
 use actix_web::{get, web, Responder, Result};
 use serde::Serialize;
 
-use crate::contracts::votechain::Poll;
+use crate::{
+    db,
+    errors::AppError,
+    metadata::{self, PollView},
+};
 
 #[derive(Serialize)]
 #[serde(untagged)]
 enum GetPollsResponse {
-    Success { polls: Vec<Poll> },
-    Error { error: String },
+    Success { polls: Vec<PollView> },
 }
 
 #[get("/polls")]
-async fn route(app_data: web::Data<crate::AppState>) -> Result<impl Responder> {
-    // // Extract contract from app data
-    // let contract = &app_data.contracts.votechain;
-    //
-    // // Fetch all available polls from the blockchain
-    // let polls = contract.get_available_polls().await;
-    //
-    // // Check whether the request was successful
-    // if polls.is_err() {
-    //     return Ok(web::Json(GetPollsResponse::Error {
-    //         error: polls.err().unwrap(),
-    //     }));
-    // }
-    //
-    // // Extract polls from the result
-    // let polls = polls.unwrap();
-    //
-    // // Return the polls
-    // Ok(web::Json(GetPollsResponse::Success { polls }))
+async fn route(app_data: web::Data<crate::AppState>) -> Result<impl Responder, AppError> {
+    // Extract contract from app data
+    let contract = &app_data.contracts.votechain;
 
-    Ok(web::Json(GetPollsResponse::Success { polls: vec![] }))
+    // Fetch all available polls from the blockchain
+    let polls = contract.get_available_polls().await?;
+
+    // Attach their off-chain metadata
+    let polls = db::query(&app_data.db, move |conn| metadata::merge(conn, polls)).await?;
+
+    // Return the polls
+    Ok(web::Json(GetPollsResponse::Success { polls }))
 }
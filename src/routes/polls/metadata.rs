@@ -0,0 +1,42 @@
+use actix_web::{
+    get,
+    http::header::{ETag, EntityTag},
+    web, HttpResponse, Responder, Result,
+};
+use alloy::primitives::U256;
+
+use crate::{
+    cache::CacheKey,
+    db,
+    errors::{ApiErrorResponse, AppError},
+    models::poll_metadata::PollMetadataRecord,
+    AppState,
+};
+
+#[get("/poll/{id}/metadata")]
+pub async fn route(
+    path: web::Path<u64>,
+    app_data: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let contract = &app_data.contracts.votechain;
+    let poll_id = U256::from(path.into_inner());
+
+    // The hash committed on-chain (unknown polls are mapped to 404)
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(poll_id), || contract.get_poll(poll_id))
+        .await?;
+    let hash = poll.metadata_hash.ok_or(ApiErrorResponse::NotFound)?;
+
+    let record = db::query(&app_data.db, move |conn| {
+        PollMetadataRecord::find(conn, &hash.to_string())
+    })
+    .await?
+    .ok_or(ApiErrorResponse::NotFound)?;
+
+    // Exact bytes that were hashed, so that clients can check them against the chain
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(EntityTag::new_strong(record.hash)))
+        .content_type("application/json")
+        .body(record.content))
+}
@@ -0,0 +1,52 @@
+use actix_web::{get, web, Responder, Result};
+use alloy::primitives::{Address, U256};
+use serde::Deserialize;
+
+use crate::{errors::AppError, AppState};
+
+// Nonces checked by a single request
+const MAX_NONCES: usize = 100;
+
+#[derive(Deserialize)]
+struct NoncesQuery {
+    nonces: String, // Comma separated decimal nonces
+}
+
+#[get("/poll/{id}/nonces/{address}")]
+pub async fn route(
+    path: web::Path<(u64, Address)>, // poll_id and voter taken from the path
+    query: web::Query<NoncesQuery>,
+    app_data: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (poll_id, address) = path.into_inner();
+
+    let nonces = query
+        .nonces
+        .split(',')
+        .map(|nonce| U256::from_str_radix(nonce.trim(), 10))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| AppError::Validation("nonces must be comma separated integers".to_owned()))?;
+    if nonces.len() > MAX_NONCES {
+        return Err(AppError::Validation(format!(
+            "at most {} nonces can be checked at once",
+            MAX_NONCES
+        )));
+    }
+
+    // Read at the same block, in a few calls
+    let used = app_data
+        .contracts
+        .votechain
+        .used_nonces(address, U256::from(poll_id), &nonces)
+        .await?;
+
+    Ok(web::Json(serde_json::json!({
+        "poll_id": poll_id,
+        "address": address,
+        "nonces": nonces
+            .iter()
+            .zip(used)
+            .map(|(nonce, used)| serde_json::json!({ "nonce": nonce.to_string(), "used": used }))
+            .collect::<Vec<_>>(),
+    })))
+}
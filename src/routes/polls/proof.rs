@@ -0,0 +1,58 @@
+use actix_web::{get, web, Responder, Result};
+use alloy::primitives::{Address, U256};
+use std::sync::Arc;
+
+use crate::{
+    cache::CacheKey,
+    db, eligibility,
+    errors::{ApiErrorResponse, AppError},
+    merkle::MerkleTree,
+    metadata,
+    models::merkle_allowlists::AllowlistEntry,
+    AppState,
+};
+
+#[get("/poll/{id}/proof/{address}")]
+pub async fn route(
+    path: web::Path<(u64, Address)>, // poll_id and voter taken from the path
+    app_data: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let (poll_id, address) = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Unknown polls are mapped to 404
+    let id = U256::from(poll_id);
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(id), || contract.get_poll(id))
+        .await?;
+
+    // Polls without a Merkle allowlist need no proof
+    let poll = db::query(&app_data.db, move |conn| metadata::attach(conn, poll)).await?;
+    let root = eligibility::allowlist_root(&poll)?.ok_or(ApiErrorResponse::NotFound)?;
+
+    // Tree built once per allowlist, rebuilt from the stored addresses when not cached
+    let tree = match app_data.trees.get(root) {
+        Some(tree) => tree,
+        None => {
+            let tree = db::query(&app_data.db, move |conn| {
+                let addresses = AllowlistEntry::addresses(conn, &root.to_string())?
+                    .iter()
+                    .filter_map(|address| address.parse().ok())
+                    .collect::<Vec<Address>>();
+                Ok::<_, AppError>(Arc::new(MerkleTree::new(&addresses)))
+            })
+            .await?;
+            app_data.trees.insert(tree.clone());
+            tree
+        }
+    };
+    let proof = tree.proof(address).ok_or(ApiErrorResponse::NotEligible)?;
+
+    Ok(web::Json(serde_json::json!({
+        "poll_id": poll_id,
+        "address": address,
+        "root": root,
+        "proof": proof,
+    })))
+}
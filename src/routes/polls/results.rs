@@ -0,0 +1,200 @@
+use actix_web::{get, web, HttpRequest, Responder, Result};
+use alloy::primitives::{ruint::aliases::U256, U512};
+use serde::Serialize;
+
+use crate::{
+    cache::{self, CacheKey},
+    contracts::votechain::PollResults,
+    errors::AppError,
+    AppState,
+};
+
+#[derive(Serialize)]
+struct OptionResult {
+    option: String,
+    votes: U256,
+    percentage: f64, // Share of the turnout, rounded to 2 decimals
+}
+
+#[derive(Serialize)]
+struct PollResultsResponse {
+    poll_id: u64,
+    status: &'static str, // open / ended / finalized
+    options: Vec<OptionResult>,
+    turnout: U256,
+    leader: Option<String>, // Option with the most votes, null on ties or without votes
+    winner: Option<String>, // Final winner, once the poll is finalized
+    block_number: u64,      // Block the results were read at
+}
+
+// Share of `votes` in `total`, in percent with 2 decimals
+fn percentage(votes: U256, total: U256) -> f64 {
+    if total.is_zero() {
+        return 0.0;
+    }
+    // Multiplied at full width, huge tallies do not saturate
+    let basis_points: u64 =
+        (U512::from(votes) * U512::from(10_000) / U512::from(total)).saturating_to();
+    basis_points as f64 / 100.0
+}
+
+impl PollResultsResponse {
+    // Turnout, shares, leader and status of results read at a block
+    fn new(poll_id: u64, results: PollResults) -> Self {
+        let turnout = results
+            .tallies
+            .iter()
+            .fold(U256::ZERO, |total, tally| total.saturating_add(tally.votes));
+
+        // Leader: the single option with the most votes
+        let most_votes = results.tallies.iter().map(|tally| tally.votes).max();
+        let mut leaders = results
+            .tallies
+            .iter()
+            .filter(|tally| Some(tally.votes) == most_votes && !tally.votes.is_zero());
+        let leader = match (leaders.next(), leaders.next()) {
+            (Some(tally), None) => Some(tally.option.clone()),
+            _ => None,
+        };
+
+        let status = match (&results.winner, results.poll.is_ended) {
+            (Some(_), _) => "finalized",
+            (None, true) => "ended",
+            (None, false) => "open",
+        };
+
+        let options = results
+            .tallies
+            .into_iter()
+            .map(|tally| OptionResult {
+                percentage: percentage(tally.votes, turnout),
+                option: tally.option,
+                votes: tally.votes,
+            })
+            .collect();
+
+        Self {
+            poll_id,
+            status,
+            options,
+            turnout,
+            leader,
+            winner: results.winner,
+            block_number: results.block_number,
+        }
+    }
+}
+
+#[get("/poll/{id}/results")]
+pub async fn route(
+    req: HttpRequest,
+    path: web::Path<u64>,
+    app_data: web::Data<AppState>,
+) -> Result<impl Responder, AppError> {
+    let poll_id = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Poll, votes and winner are read at the same block, unless cached (unknown polls are
+    // mapped to 404)
+    let id = U256::from(poll_id);
+    let results = app_data
+        .cache
+        .get_or_load(CacheKey::Results(id), || contract.get_results(id))
+        .await?;
+
+    Ok(cache::json_with_etag(
+        &req,
+        &PollResultsResponse::new(poll_id, results),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Address;
+
+    use super::*;
+    use crate::{
+        contracts::votechain::{Poll, Tally},
+        timestamp::Timestamp,
+    };
+
+    fn results(votes: &[(&str, u64)], is_ended: bool, winner: Option<&str>) -> PollResults {
+        PollResults {
+            block_number: 42,
+            poll: Poll {
+                id: U256::from(1),
+                name: "Lunch".to_owned(),
+                description: String::new(),
+                start_time: Timestamp::from_secs(1_000).unwrap(),
+                end_time: Timestamp::from_secs(2_000).unwrap(),
+                winner: winner.unwrap_or_default().to_owned(),
+                is_ended,
+                owner: Address::ZERO,
+                metadata_hash: None,
+            },
+            tallies: votes
+                .iter()
+                .map(|(option, votes)| Tally {
+                    option: (*option).to_owned(),
+                    votes: U256::from(*votes),
+                })
+                .collect(),
+            winner: winner.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn percentage_is_truncated_to_two_decimals() {
+        assert_eq!(percentage(U256::from(1), U256::from(3)), 33.33);
+        assert_eq!(percentage(U256::from(2), U256::from(3)), 66.66);
+        assert_eq!(percentage(U256::from(3), U256::from(3)), 100.0);
+        assert_eq!(percentage(U256::ZERO, U256::ZERO), 0.0);
+
+        // Tallies near the top of the range do not overflow
+        assert_eq!(percentage(U256::MAX, U256::MAX), 100.0);
+    }
+
+    #[test]
+    fn shares_and_turnout() {
+        let response =
+            PollResultsResponse::new(1, results(&[("Pizza", 3), ("Sushi", 1)], false, None));
+        assert_eq!(response.turnout, U256::from(4));
+        let shares: Vec<f64> = response.options.iter().map(|o| o.percentage).collect();
+        assert_eq!(shares, vec![75.0, 25.0]);
+        assert_eq!(response.status, "open");
+    }
+
+    #[test]
+    fn leader_is_the_single_option_with_the_most_votes() {
+        let leader =
+            |votes: &[(&str, u64)]| PollResultsResponse::new(1, results(votes, false, None)).leader;
+
+        assert_eq!(
+            leader(&[("Pizza", 3), ("Sushi", 1)]),
+            Some("Pizza".to_owned())
+        );
+        // No votes, or a tie at the top
+        assert_eq!(leader(&[("Pizza", 0), ("Sushi", 0)]), None);
+        assert_eq!(leader(&[]), None);
+        assert_eq!(leader(&[("Pizza", 2), ("Sushi", 2), ("Tacos", 1)]), None);
+        // Ties below the top do not matter
+        assert_eq!(
+            leader(&[("Pizza", 3), ("Sushi", 1), ("Tacos", 1)]),
+            Some("Pizza".to_owned())
+        );
+    }
+
+    #[test]
+    fn status_follows_the_poll_and_keeps_its_block() {
+        let ended = PollResultsResponse::new(1, results(&[("Pizza", 1)], true, None));
+        assert_eq!(ended.status, "ended");
+        assert_eq!(ended.winner, None);
+
+        let finalized = PollResultsResponse::new(1, results(&[("Pizza", 1)], true, Some("Pizza")));
+        assert_eq!(finalized.status, "finalized");
+        assert_eq!(finalized.winner.as_deref(), Some("Pizza"));
+
+        // Results are reported at the block they were read at
+        assert_eq!(finalized.block_number, 42);
+    }
+}
@@ -0,0 +1,137 @@
+use actix_web::{post, web, Responder, Result};
+use alloy::primitives::{ruint::aliases::U256, B256};
+use log::error;
+use serde::Deserialize;
+
+use crate::{
+    auth::Claims,
+    cache::CacheKey,
+    commit_reveal::{self, Phase},
+    contracts::errors::ContractError,
+    db,
+    errors::{ApiErrorResponse, AppError, FieldError},
+    metadata,
+    models::vote_commitments::{VoteCommitment, COMMITTED, REVEALING},
+    receipts, relay, AppState,
+};
+
+#[derive(Deserialize)]
+struct RevealRequest {
+    option: String, // Option committed to
+    salt: B256,     // Salt of the commitment
+}
+
+#[post("/poll/{id}/reveal")]
+pub async fn route(
+    path: web::Path<u64>,           // poll_id taken from the path
+    data: web::Json<RevealRequest>, // Vote revealed by the user as JSON
+    claims: web::ReqData<Claims>,   // Identity of the voter
+    app_data: web::Data<AppState>,  // Application state with the contracts and database
+) -> Result<impl Responder, AppError> {
+    let poll_id = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Unknown polls are mapped to 404
+    let voter = claims.address().ok_or(ApiErrorResponse::Forbidden)?;
+    let id = U256::from(poll_id);
+    let poll = app_data
+        .cache
+        .get_or_load(CacheKey::Poll(id), || contract.get_poll(id))
+        .await?;
+    let poll = db::query(&app_data.db, move |conn| metadata::attach(conn, poll)).await?;
+
+    // Votes are revealed between the commit deadline and the end of the poll
+    let settings = commit_reveal::settings(&poll)?.ok_or(ApiErrorResponse::NotFound)?;
+    let (_, now) = contract.latest_block().await?;
+    let phase = commit_reveal::phase(settings, &poll.poll, now);
+    if phase != Phase::Reveal {
+        return Err(commit_reveal::refusal(phase).into());
+    }
+
+    // The vote must match the commitment of the voter
+    let (poll_key, voter_key) = (poll_id.to_string(), voter.to_string());
+    let expected = commit_reveal::commitment(id, voter, &data.option, data.salt);
+    let (poll_ref, voter_ref) = (poll_key.clone(), voter_key.clone());
+    db::query(&app_data.db, move |conn| {
+        let committed =
+            VoteCommitment::find(conn, &poll_ref, &voter_ref)?.ok_or(ApiErrorResponse::NotFound)?;
+        if committed.status != COMMITTED {
+            return Err(AppError::from(ApiErrorResponse::AlreadyVoted));
+        }
+        if committed.commitment.parse::<B256>().ok() != Some(expected) {
+            let errors = vec![FieldError::new(
+                "salt",
+                "option and salt do not match the commitment",
+            )];
+            return Err(ApiErrorResponse::ValidationFailed { errors }.into());
+        }
+
+        // Lock the commitment, so that concurrent reveals cannot relay the vote twice
+        if !VoteCommitment::transition(conn, &poll_ref, &voter_ref, COMMITTED, REVEALING)? {
+            return Err(ApiErrorResponse::AlreadyVoted.into());
+        }
+        Ok(())
+    })
+    .await?;
+
+    // Reverts (e.g. unknown option) are mapped into the matching API errors
+    let (poll_ref, voter_ref) = (poll_key.clone(), voter_key.clone());
+    let vote = match contract.cast_vote(id, data.option.clone()).await {
+        Ok(vote) => vote,
+        // The vote may still be mined: the commitment stays locked, with its transaction
+        Err(ContractError::Unconfirmed { tx_hash, reason }) => {
+            let hash = tx_hash.to_string();
+            let recorded = db::query(&app_data.db, move |conn| {
+                VoteCommitment::set_tx_hash(conn, &poll_ref, &voter_ref, &hash)
+            })
+            .await;
+            if let Err(e) = recorded {
+                error!(
+                    "Vote of {} in poll {} sent but not recorded: {}",
+                    voter_key, poll_key, e
+                );
+            }
+            // Revealed once mined, by the relay reconciliation
+            relay::track(&app_data, tx_hash, "cast_vote").await;
+            return Err(ContractError::Unconfirmed { tx_hash, reason }.into());
+        }
+        Err(e) => {
+            // Unlock the commitment to retry
+            db::query(&app_data.db, move |conn| {
+                VoteCommitment::transition(conn, &poll_ref, &voter_ref, REVEALING, COMMITTED)
+            })
+            .await?;
+            return Err(e.into());
+        }
+    };
+
+    // The cached poll is outdated once the transaction is mined
+    app_data.cache.invalidate(id).await;
+
+    // The vote is relayed now: failing to record it must not report an error, the
+    // commitment stays locked instead of being revealed twice
+    let tx_hash = vote.transaction_hash.to_string();
+    let (poll_ref, voter_ref) = (poll_key.clone(), voter_key.clone());
+    let recorded = db::query(&app_data.db, move |conn| {
+        VoteCommitment::set_revealed(conn, &poll_ref, &voter_ref, &tx_hash)
+    })
+    .await;
+    if let Err(e) = recorded {
+        error!(
+            "Vote of {} in poll {} relayed but not recorded: {}",
+            voter_key, poll_key, e
+        );
+    }
+
+    // Signed receipt, to check later that the vote was counted as cast
+    let receipt = receipts::issue(&app_data, voter, &vote).await;
+
+    Ok(web::Json(serde_json::json!({
+        "message": "Your vote has been revealed and cast.",
+        "poll_id": poll_id,
+        "option": data.option,
+        "transaction_hash": vote.transaction_hash,
+        "receipt": receipt,
+        "contract_receipt": vote.contract_receipt,
+    })))
+}
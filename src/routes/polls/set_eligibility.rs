@@ -0,0 +1,50 @@
+use actix_web::{put, web, Responder, Result};
+use alloy::primitives::U256;
+use std::time::SystemTime;
+
+use crate::{
+    auth::Claims,
+    db, eligibility,
+    errors::{ApiErrorResponse, AppError},
+    models::poll_eligibility::EligibleVoter,
+    owners, AppState,
+};
+
+#[put("/poll/{id}/eligibility")]
+pub async fn route(
+    path: web::Path<u64>,          // poll_id taken from the path
+    body: String,                  // CSV file, one address per line
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the contracts and database
+) -> Result<impl Responder, AppError> {
+    let poll_id = path.into_inner();
+    let contract = &app_data.contracts.votechain;
+
+    // Only the creator of the poll (or an admin) may restrict it
+    let poll = contract.get_poll(U256::from(poll_id)).await?;
+    owners::ensure_manager(&app_data, &claims, &poll).await?;
+
+    let addresses = eligibility::parse_csv(&body)
+        .map_err(|errors| ApiErrorResponse::ValidationFailed { errors })?;
+
+    // The new list replaces the previous one
+    let now = Some(SystemTime::now());
+    let voters: Vec<EligibleVoter> = addresses
+        .iter()
+        .map(|address| EligibleVoter {
+            poll_id: poll_id.to_string(),
+            address: address.to_string(),
+            created_at: now,
+        })
+        .collect();
+    let count = db::query(&app_data.db, move |conn| {
+        EligibleVoter::replace(conn, &poll_id.to_string(), &voters)
+    })
+    .await?;
+
+    Ok(web::Json(serde_json::json!({
+        "message": "The eligibility list has been updated.",
+        "poll_id": poll_id,
+        "addresses": count,
+    })))
+}
@@ -0,0 +1,70 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Result};
+use actix_ws::Message;
+use alloy::primitives::ruint::aliases::U256;
+use futures::StreamExt;
+
+use crate::{
+    auth::Claims,
+    errors::{ApiErrorResponse, AppError},
+    events::{self, Listener},
+    AppState,
+};
+
+#[get("/poll/{id}/ws")]
+pub async fn route(
+    req: HttpRequest,
+    body: web::Payload,
+    path: web::Path<u64>,          // poll_id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller, the socket closes with the token
+    app_data: web::Data<AppState>, // Application state with the contracts
+) -> Result<HttpResponse, AppError> {
+    let poll_id = U256::from(path.into_inner());
+
+    // Unknown polls are mapped to 404 before upgrading the connection
+    let snapshot = events::snapshot(&app_data, poll_id).await?;
+    let mut listener = Listener::new(&app_data, poll_id, &claims);
+
+    let (response, mut session, mut messages) =
+        actix_ws::handle(&req, body).map_err(|e| ApiErrorResponse::InvalidRequest {
+            location: "header",
+            reason: e.to_string(),
+        })?;
+
+    // Push the events until the client leaves, the token expires or the server stops
+    actix_web::rt::spawn(async move {
+        if session.text(snapshot).await.is_err() {
+            return;
+        }
+
+        loop {
+            tokio::select! {
+                received = listener.next() => match received {
+                    Some(received) => {
+                        if session.text(received.to_json()).await.is_err() {
+                            return;
+                        }
+                    }
+                    None => break,
+                },
+                message = messages.next() => match message {
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Close(reason))) => {
+                        let _ = session.close(reason).await;
+                        return;
+                    }
+                    // Clients are not expected to send anything else
+                    Some(Ok(_)) => {}
+                    Some(Err(_)) | None => return,
+                },
+            }
+        }
+
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
@@ -0,0 +1,33 @@
+use actix_web::{get, web, Responder, Result};
+use alloy::primitives::B256;
+
+use crate::{
+    auth::Claims,
+    db,
+    errors::{ApiErrorResponse, AppError},
+    models::vote_receipts::VoteReceiptRecord,
+    receipts::VoteReceipt,
+    AppState,
+};
+
+#[get("/receipts/{id}")]
+pub async fn route(
+    path: web::Path<B256>,         // Receipt id taken from the path
+    claims: web::ReqData<Claims>,  // Identity of the caller
+    app_data: web::Data<AppState>, // Application state with the database
+) -> Result<impl Responder, AppError> {
+    let receipt_id = path.into_inner().to_string();
+    let record = db::query(&app_data.db, move |conn| {
+        VoteReceiptRecord::find(conn, &receipt_id)
+    })
+    .await?
+    .ok_or(ApiErrorResponse::NotFound)?;
+    let receipt = VoteReceipt::try_from(record)?;
+
+    // The option hash gives the vote away, receipts are shown to their voter only
+    if claims.address() != Some(receipt.voter) && !claims.is_admin() {
+        return Err(ApiErrorResponse::Forbidden.into());
+    }
+
+    Ok(web::Json(serde_json::json!({ "receipt": receipt })))
+}
@@ -0,0 +1,31 @@
+use actix_web::{post, web, HttpRequest, Responder, Result};
+
+use crate::{
+    errors::AppError,
+    receipts::{self, VoteReceipt},
+    AppState,
+};
+
+#[post("/receipts/verify")]
+pub async fn route(
+    req: HttpRequest,
+    receipt: web::Json<VoteReceipt>, // Receipt as returned when voting
+    app_data: web::Data<AppState>,   // Application state with the contracts
+) -> Result<impl Responder, AppError> {
+    // Verifying may query the chain, each client is limited to RECEIPT_VERIFY_RATE_LIMIT
+    // receipts per minute
+    if let Some(peer) = req.peer_addr() {
+        app_data.verify_limiter.check(peer.ip())?;
+    }
+
+    // Public, so that anyone holding a receipt can check it. Every problem found is
+    // reported, an invalid receipt is not an error of the request
+    let errors = receipts::verify(&app_data, &receipt).await?;
+
+    Ok(web::Json(serde_json::json!({
+        "id": receipt.id,
+        "valid": errors.is_empty(),
+        "signer": app_data.receipts.address(),
+        "errors": errors,
+    })))
+}
@@ -0,0 +1,596 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    providers::{ProviderBuilder, RootProvider, WsConnect},
+    pubsub::PubSubFrontend,
+    rpc::json_rpc::{RequestPacket, ResponsePacket, SerializedRequest},
+    transports::{
+        http::{reqwest::Url, Http},
+        BoxTransport, Transport, TransportError, TransportErrorKind, TransportFut,
+    },
+};
+use futures::future::join_all;
+use log::{info, warn};
+use tower::Service;
+
+use crate::metrics;
+
+// Score penalty of each consecutive failure, in (virtual) milliseconds of latency
+const FAILURE_PENALTY_MS: u64 = 10_000;
+
+// Weight of the last sample in the latency moving average (out of 10)
+const LATENCY_WEIGHT: u64 = 3;
+
+// Delay before retrying all endpoints again, multiplied by the round number
+const ROUND_DELAY: Duration = Duration::from_millis(250);
+
+// Methods submitting a transaction: a node failing after receiving one may still broadcast
+// it, so they are sent once, to the pinned node, and never failed over
+const NON_IDEMPOTENT_METHODS: [&str; 2] = ["eth_sendRawTransaction", "eth_sendTransaction"];
+
+// Methods sent to the same (pinned) node as the transactions: the nonce filler reads the
+// pending transaction count before each send, which only the receiving node knows for sure
+// until the previous transaction propagates
+const PINNED_METHODS: [&str; 1] = ["eth_getTransactionCount"];
+
+// No endpoint pinned yet
+const UNPINNED: usize = usize::MAX;
+
+/// Provider used for subscriptions over WebSocket
+pub type WsProvider = RootProvider<PubSubFrontend>;
+
+// An RPC node with its health statistics
+struct Endpoint {
+    label: String,           // Host of the node, used in logs (URLs may embed API keys)
+    transport: BoxTransport, // Transport to the node (HTTP)
+    failures: AtomicU32,     // Consecutive transport failures
+    latency_ms: AtomicU64,   // Moving average of the response latency
+}
+
+impl Endpoint {
+    fn new(url: Url) -> Self {
+        let host = url.host_str().unwrap_or("unknown");
+        let label = match url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_owned(),
+        };
+        Self::with_transport(label, Http::new(url).boxed())
+    }
+
+    fn with_transport(label: String, transport: BoxTransport) -> Self {
+        Self {
+            label,
+            transport,
+            failures: AtomicU32::new(0),
+            latency_ms: AtomicU64::new(0),
+        }
+    }
+
+    // Health score of the endpoint, the lower the better
+    fn score(&self) -> u64 {
+        let failures = u64::from(self.failures.load(Ordering::Relaxed));
+        self.latency_ms.load(Ordering::Relaxed) + failures * FAILURE_PENALTY_MS
+    }
+
+    fn record_success(&self, latency_ms: u64) {
+        self.failures.store(0, Ordering::Relaxed);
+        let average = self.latency_ms.load(Ordering::Relaxed);
+        let updated = (average * (10 - LATENCY_WEIGHT) + latency_ms * LATENCY_WEIGHT) / 10;
+        self.latency_ms.store(updated, Ordering::Relaxed);
+        metrics::RPC_ENDPOINT_SCORE
+            .with_label_values(&[&self.label])
+            .set(self.score() as i64);
+    }
+
+    fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        metrics::RPC_ENDPOINT_FAILURES
+            .with_label_values(&[&self.label])
+            .inc();
+        metrics::RPC_ENDPOINT_SCORE
+            .with_label_values(&[&self.label])
+            .set(self.score() as i64);
+    }
+
+    // Send the request to this node, updating its health statistics
+    async fn send(&self, req: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let start = Instant::now();
+        let result = self.transport.clone().call(req).await;
+
+        match &result {
+            // JSON-RPC errors (e.g. reverts) are valid answers of a healthy node
+            Ok(_) | Err(TransportError::ErrorResp(_)) => {
+                self.record_success(start.elapsed().as_millis() as u64)
+            }
+            Err(_) => self.record_failure(),
+        }
+
+        result
+    }
+}
+
+/// Transport spreading requests over several RPC nodes. Requests go to the healthiest node
+/// and fail over to the next one on transport errors. With a quorum above 1, each request
+/// is sent to all nodes and succeeds only if enough of them return the same response.
+/// Transactions and nonce reads are pinned to a single node, changed only when it fails
+#[derive(Clone)]
+pub struct FailoverTransport {
+    endpoints: Arc<Vec<Endpoint>>,
+    pinned: Arc<AtomicUsize>, // Index of the node receiving the transactions
+    rounds: u32,              // Times all endpoints are tried before giving up
+    quorum: usize,            // Identical responses required (1 disables quorum reads)
+}
+
+impl FailoverTransport {
+    pub fn new(urls: Vec<Url>, rounds: u32) -> Self {
+        Self::with_endpoints(urls.into_iter().map(Endpoint::new).collect(), rounds)
+    }
+
+    /// Transport to a single in-process node
+    #[cfg(test)]
+    pub fn single(transport: BoxTransport) -> Self {
+        Self::with_endpoints(
+            vec![Endpoint::with_transport("mock".to_owned(), transport)],
+            1,
+        )
+    }
+
+    fn with_endpoints(endpoints: Vec<Endpoint>, rounds: u32) -> Self {
+        Self {
+            endpoints: Arc::new(endpoints),
+            pinned: Arc::new(AtomicUsize::new(UNPINNED)),
+            rounds: rounds.max(1),
+            quorum: 1,
+        }
+    }
+
+    /// Transport sharing the same nodes (and health statistics), requiring `quorum`
+    /// identical responses. The quorum is capped at the number of nodes
+    pub fn with_quorum(&self, quorum: usize) -> Self {
+        Self {
+            endpoints: self.endpoints.clone(),
+            pinned: self.pinned.clone(),
+            rounds: self.rounds,
+            quorum: quorum.clamp(1, self.endpoints.len().max(1)),
+        }
+    }
+
+    // Endpoints sorted from the healthiest to the least healthy
+    fn ranked(&self) -> Vec<&Endpoint> {
+        let mut endpoints: Vec<&Endpoint> = self.endpoints.iter().collect();
+        endpoints.sort_by_key(|e| e.score());
+        endpoints
+    }
+
+    // Indexes of the endpoints to try for a pinned request: the pinned one first, then the
+    // others from the healthiest
+    fn pinned_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.endpoints.len()).collect();
+        order.sort_by_key(|i| self.endpoints[*i].score());
+        let pinned = self.pinned.load(Ordering::Relaxed);
+        if let Some(position) = order.iter().position(|i| *i == pinned) {
+            order[..=position].rotate_right(1);
+        }
+        order
+    }
+
+    // Send transactions and nonce reads to the pinned node. When it fails, the next
+    // healthiest node is pinned: nonces are then read from its pending count, which resyncs
+    // them with the transactions it knows. Transactions are never sent twice
+    async fn send_pinned(self, req: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let mut last_error = TransportErrorKind::custom_str("no RPC endpoint configured");
+        let mut order = self.pinned_order();
+        if !is_idempotent(&req) {
+            order.truncate(1);
+        }
+
+        for index in order {
+            let endpoint = &self.endpoints[index];
+            match endpoint.send(req.clone()).await {
+                Ok(res) => {
+                    self.pin(index);
+                    return Ok(res);
+                }
+                Err(e @ TransportError::ErrorResp(_)) => {
+                    self.pin(index);
+                    return Err(e);
+                }
+                Err(e) => {
+                    warn!(
+                        "RPC node {} failed a relay request: {}. Unpinning it...",
+                        endpoint.label, e
+                    );
+                    let _ = self.pinned.compare_exchange(
+                        index,
+                        UNPINNED,
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    );
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    fn pin(&self, index: usize) {
+        let previous = self.pinned.swap(index, Ordering::Relaxed);
+        if previous != index {
+            info!(
+                "Relaying transactions through RPC node {}",
+                self.endpoints[index].label
+            );
+        }
+    }
+
+    async fn send_with_failover(
+        self,
+        req: RequestPacket,
+    ) -> Result<ResponsePacket, TransportError> {
+        let mut last_error = TransportErrorKind::custom_str("no RPC endpoint configured");
+
+        for round in 0..self.rounds {
+            if round > 0 {
+                tokio::time::sleep(ROUND_DELAY * round).await;
+            }
+
+            // Rank again at each round, scores changed with the failures
+            for endpoint in self.ranked() {
+                match endpoint.send(req.clone()).await {
+                    Ok(res) => return Ok(res),
+                    Err(e @ TransportError::ErrorResp(_)) => return Err(e),
+                    Err(e) => {
+                        warn!("RPC node {} failed: {}. Failing over...", endpoint.label, e);
+                        last_error = e;
+                    }
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    async fn send_with_quorum(self, req: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let responses = join_all(self.endpoints.iter().map(|e| e.send(req.clone()))).await;
+
+        // Group identical responses together. JSON-RPC errors (e.g. reverts) are answers
+        // too: nodes agreeing on the same error vote for it, transport failures do not vote
+        let mut votes: HashMap<String, (usize, Result<ResponsePacket, TransportError>)> =
+            HashMap::new();
+        for res in responses {
+            let key = match &res {
+                Ok(res) => fingerprint(res),
+                Err(TransportError::ErrorResp(payload)) => serde_json::to_string(payload)
+                    .ok()
+                    .map(|e| format!("error:{}", e)),
+                Err(_) => None,
+            };
+            if let Some(key) = key {
+                votes.entry(key).or_insert((0, res)).0 += 1;
+            }
+        }
+
+        votes
+            .into_values()
+            .find(|(count, _)| *count >= self.quorum)
+            .map(|(_, res)| res)
+            .unwrap_or_else(|| {
+                Err(TransportErrorKind::custom_str(&format!(
+                    "RPC quorum of {} identical responses not reached",
+                    self.quorum
+                )))
+            })
+    }
+}
+
+// Whether the request can be sent again, to another node, without side effects
+fn is_idempotent(req: &RequestPacket) -> bool {
+    !has_method(req, &NON_IDEMPOTENT_METHODS)
+}
+
+// Whether the request must go to the node receiving the transactions
+fn is_pinned(req: &RequestPacket) -> bool {
+    has_method(req, &NON_IDEMPOTENT_METHODS) || has_method(req, &PINNED_METHODS)
+}
+
+fn has_method(req: &RequestPacket, methods: &[&str]) -> bool {
+    let matches = |r: &SerializedRequest| methods.contains(&r.method());
+    match req {
+        RequestPacket::Single(r) => matches(r),
+        RequestPacket::Batch(rs) => rs.iter().any(matches),
+    }
+}
+
+// Comparable representation of a response (ids are the same for every node)
+fn fingerprint(res: &ResponsePacket) -> Option<String> {
+    match res {
+        ResponsePacket::Single(r) => serde_json::to_string(r).ok(),
+        ResponsePacket::Batch(rs) => serde_json::to_string(rs).ok(),
+    }
+}
+
+impl Service<RequestPacket> for FailoverTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Readiness is checked per endpoint when sending
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let this = self.clone();
+        if this.quorum > 1 {
+            Box::pin(this.send_with_quorum(req))
+        } else if is_pinned(&req) {
+            Box::pin(this.send_pinned(req))
+        } else {
+            Box::pin(this.send_with_failover(req))
+        }
+    }
+}
+
+/// Parse a comma separated list of RPC URLs
+pub fn parse_urls(value: &str) -> Result<Vec<Url>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(|url| Url::parse(url).map_err(|e| format!("invalid RPC endpoint {}: {}", url, e)))
+        .collect()
+}
+
+/// WebSocket endpoint used for subscriptions. The connection is opened on first use and
+/// opened again once it was reset after the socket dropped
+pub struct WsEndpoint {
+    url: String,
+    provider: tokio::sync::Mutex<Option<WsProvider>>,
+}
+
+impl WsEndpoint {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            provider: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Provider connected to the endpoint, connecting first if needed
+    pub async fn provider(&self) -> Result<WsProvider, TransportError> {
+        let mut provider = self.provider.lock().await;
+        if let Some(provider) = provider.as_ref() {
+            return Ok(provider.clone());
+        }
+
+        let connected = ProviderBuilder::new()
+            .on_ws(WsConnect::new(&self.url))
+            .await?;
+        *provider = Some(connected.clone());
+        Ok(connected)
+    }
+
+    /// Drop the connection (e.g. the socket dropped), the next use connects again
+    pub async fn reset(&self) {
+        *self.provider.lock().await = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use alloy::rpc::json_rpc::{Id, Request};
+
+    use super::*;
+
+    // Answer of a mock node
+    #[derive(Clone, Copy)]
+    enum Reply {
+        Result(&'static str), // JSON result
+        Error(i64),           // JSON-RPC error code
+        Down,                 // Transport failure
+    }
+
+    // RPC node answering every request with the same reply, recording the methods called
+    #[derive(Clone)]
+    struct MockNode {
+        reply: Arc<Mutex<Reply>>,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockNode {
+        fn new(reply: Reply) -> Self {
+            Self {
+                reply: Arc::new(Mutex::new(reply)),
+                calls: Arc::default(),
+            }
+        }
+
+        fn set(&self, reply: Reply) {
+            *self.reply.lock().unwrap() = reply;
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl Service<RequestPacket> for MockNode {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: RequestPacket) -> Self::Future {
+            if let RequestPacket::Single(r) = &req {
+                self.calls.lock().unwrap().push(r.method().to_owned());
+            }
+            let reply = *self.reply.lock().unwrap();
+            Box::pin(async move {
+                let body = match reply {
+                    Reply::Result(result) => {
+                        format!(r#"{{"jsonrpc":"2.0","id":1,"result":{}}}"#, result)
+                    }
+                    Reply::Error(code) => format!(
+                        r#"{{"jsonrpc":"2.0","id":1,"error":{{"code":{},"message":"execution reverted"}}}}"#,
+                        code
+                    ),
+                    Reply::Down => {
+                        return Err(TransportErrorKind::custom_str("connection refused"))
+                    }
+                };
+                Ok(serde_json::from_str(&body).unwrap())
+            })
+        }
+    }
+
+    fn transport(nodes: &[&MockNode]) -> FailoverTransport {
+        let endpoints = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                Endpoint::with_transport(format!("node-{}", i), (*node).clone().boxed())
+            })
+            .collect();
+        FailoverTransport::with_endpoints(endpoints, 1)
+    }
+
+    fn request(method: &'static str) -> RequestPacket {
+        RequestPacket::Single(Request::new(method, Id::Number(1), ()).serialize().unwrap())
+    }
+
+    async fn send(
+        transport: &FailoverTransport,
+        method: &'static str,
+    ) -> Result<String, TransportError> {
+        let res = transport.clone().call(request(method)).await?;
+        Ok(fingerprint(&res).unwrap())
+    }
+
+    #[test]
+    fn health_score_ranks_failing_and_slow_nodes_last() {
+        let (a, b) = (MockNode::new(Reply::Down), MockNode::new(Reply::Down));
+        let transport = transport(&[&a, &b]);
+        let (first, second) = (&transport.endpoints[0], &transport.endpoints[1]);
+
+        // Moving average of the latency
+        first.record_success(100);
+        assert_eq!(first.score(), 30);
+        first.record_success(100);
+        assert_eq!(first.score(), 51);
+        assert_eq!(transport.ranked()[0].label, "node-1");
+
+        // Each consecutive failure costs a penalty, a success resets them
+        second.record_failure();
+        second.record_failure();
+        assert_eq!(second.score(), 2 * FAILURE_PENALTY_MS);
+        assert_eq!(transport.ranked()[0].label, "node-0");
+        second.record_success(0);
+        assert_eq!(second.score(), 0);
+    }
+
+    #[actix_web::test]
+    async fn reads_fail_over_on_transport_errors() {
+        let (a, b) = (
+            MockNode::new(Reply::Down),
+            MockNode::new(Reply::Result("\"0x2\"")),
+        );
+        let transport = transport(&[&a, &b]);
+
+        let result = send(&transport, "eth_blockNumber").await.unwrap();
+        assert!(result.contains("0x2"));
+        assert_eq!(a.calls(), ["eth_blockNumber"]);
+        assert_eq!(transport.endpoints[0].failures.load(Ordering::Relaxed), 1);
+
+        // JSON-RPC errors are answers, not failures
+        b.set(Reply::Error(3));
+        let result = send(&transport, "eth_call").await.unwrap();
+        assert!(result.contains("execution reverted"));
+        assert_eq!(a.calls().len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn transactions_are_never_failed_over() {
+        let (a, b) = (
+            MockNode::new(Reply::Down),
+            MockNode::new(Reply::Result("\"0x1\"")),
+        );
+        let transport = transport(&[&a, &b]);
+
+        assert!(send(&transport, "eth_sendRawTransaction").await.is_err());
+        assert_eq!(a.calls(), ["eth_sendRawTransaction"]);
+        assert!(b.calls().is_empty());
+    }
+
+    #[actix_web::test]
+    async fn transactions_and_nonces_are_pinned() {
+        let (a, b) = (
+            MockNode::new(Reply::Result("\"0x1\"")),
+            MockNode::new(Reply::Result("\"0x1\"")),
+        );
+        let transport = transport(&[&a, &b]);
+
+        send(&transport, "eth_sendRawTransaction").await.unwrap();
+        assert_eq!(a.calls(), ["eth_sendRawTransaction"]);
+
+        // Other reads go to the healthiest node, nonces stay with the pinned one
+        transport.endpoints[0].record_success(1_000);
+        send(&transport, "eth_blockNumber").await.unwrap();
+        send(&transport, "eth_getTransactionCount").await.unwrap();
+        assert_eq!(
+            a.calls(),
+            ["eth_sendRawTransaction", "eth_getTransactionCount"]
+        );
+        assert_eq!(b.calls(), ["eth_blockNumber"]);
+
+        // Once the pinned node fails, the nonce is read from the next one, which is pinned
+        a.set(Reply::Down);
+        send(&transport, "eth_getTransactionCount").await.unwrap();
+        send(&transport, "eth_sendRawTransaction").await.unwrap();
+        assert_eq!(
+            b.calls(),
+            [
+                "eth_blockNumber",
+                "eth_getTransactionCount",
+                "eth_sendRawTransaction"
+            ]
+        );
+    }
+
+    #[actix_web::test]
+    async fn quorum_votes_on_identical_answers() {
+        let a = MockNode::new(Reply::Result("\"0x1\""));
+        let b = MockNode::new(Reply::Result("\"0x1\""));
+        let c = MockNode::new(Reply::Result("\"0x2\""));
+        let nodes = transport(&[&a, &b, &c]);
+
+        let result = send(&nodes.with_quorum(2), "eth_call").await.unwrap();
+        assert!(result.contains("0x1"));
+        assert!(send(&nodes.with_quorum(3), "eth_call").await.is_err());
+
+        // Nodes agreeing on an error vote for it
+        a.set(Reply::Error(3));
+        b.set(Reply::Error(3));
+        let result = send(&nodes.with_quorum(2), "eth_call").await.unwrap();
+        assert!(result.contains("execution reverted"));
+
+        // Transport failures do not vote
+        a.set(Reply::Down);
+        b.set(Reply::Down);
+        c.set(Reply::Down);
+        assert!(send(&nodes.with_quorum(2), "eth_call").await.is_err());
+    }
+}
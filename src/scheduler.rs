@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant, SystemTime},
+};
+
+use actix_web::web;
+use alloy::primitives::{TxHash, U256};
+use log::{error, info, warn};
+
+use crate::{
+    config,
+    contracts::{errors::ContractError, votechain::Poll},
+    db,
+    models::scheduler_audit::SchedulerAudit,
+    AppState,
+};
+
+// Defaults of the optional scheduler settings
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const DEFAULT_GRACE_PERIOD_SECS: u64 = 60;
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+// Upper bound of the delay between two attempts of the same action
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Action {
+    End,
+    Finalize,
+}
+
+impl Action {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Action::End => "end_poll",
+            Action::Finalize => "finalize_poll",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [Action::End, Action::Finalize]
+            .into_iter()
+            .find(|action| action.as_str() == name)
+    }
+}
+
+// Failed attempts of an action on a poll
+struct Retry {
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+/// Background task ending and finalizing polls once their end time (plus a grace period)
+/// has passed. Actions are relayed by the server wallet, retried with backoff on failure
+/// and recorded in the `scheduler_audit` table
+pub struct Scheduler {
+    app_state: web::Data<AppState>,
+    interval: Duration,
+    grace_period: u64,
+    max_attempts: u32,
+    retries: HashMap<(U256, Action), Retry>,
+    finalized: HashSet<U256>, // Finalized polls whose winner is empty (e.g. no votes)
+}
+
+impl Scheduler {
+    pub fn new(app_state: web::Data<AppState>) -> Self {
+        Self {
+            app_state,
+            interval: Duration::from_secs(config::parse_or(
+                "SCHEDULER_INTERVAL_SECS",
+                DEFAULT_INTERVAL_SECS,
+            )),
+            grace_period: config::parse_or(
+                "SCHEDULER_GRACE_PERIOD_SECS",
+                DEFAULT_GRACE_PERIOD_SECS,
+            ),
+            max_attempts: config::parse_or("SCHEDULER_MAX_ATTEMPTS", DEFAULT_MAX_ATTEMPTS),
+            retries: HashMap::new(),
+            finalized: HashSet::new(),
+        }
+    }
+
+    /// Check the polls every SCHEDULER_INTERVAL_SECS until the server shuts down
+    pub async fn run(mut self) {
+        info!(
+            "Poll scheduler started (every {:?}, grace period {}s)",
+            self.interval, self.grace_period
+        );
+
+        self.restore().await;
+
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            match self.tick().await {
+                Ok(()) => {}
+                // Relays are refused once shutdown started
+                Err(ContractError::ShuttingDown) => break,
+                Err(e) => warn!("Poll scheduler unable to check polls: {}", e),
+            }
+        }
+
+        info!("Poll scheduler stopped.");
+    }
+
+    // Resume the retries and the finalized polls from the audit table, so that a restart
+    // neither retries abandoned actions nor finalizes polls again
+    async fn restore(&mut self) {
+        let entries = match db::query(&self.app_state.db, SchedulerAudit::get_latest).await {
+            Ok(entries) => entries,
+            Err(e) => return warn!("Scheduler: unable to load the audit table: {}", e),
+        };
+
+        for entry in entries {
+            let (Ok(poll_id), Some(action)) = (entry.poll_id.parse(), Action::parse(&entry.action))
+            else {
+                continue;
+            };
+
+            match entry.outcome.as_str() {
+                "success" if action == Action::Finalize => {
+                    self.finalized.insert(poll_id);
+                }
+                "failed" | "abandoned" => {
+                    let attempts = entry.attempt.try_into().unwrap_or(0);
+                    let elapsed = entry
+                        .created_at
+                        .and_then(|at| at.elapsed().ok())
+                        .unwrap_or_default();
+                    let wait = self.retry_delay(attempts).saturating_sub(elapsed);
+                    self.retries.insert(
+                        (poll_id, action),
+                        Retry {
+                            attempts,
+                            next_attempt: Instant::now() + wait,
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Submit the actions due for every poll
+    async fn tick(&mut self) -> Result<(), ContractError> {
+        let contract = &self.app_state.contracts.votechain;
+
+        // End times are compared with the chain clock, as the contract does
+        let (_, now) = contract.latest_block().await?;
+        let polls = contract.get_available_polls().await?;
+
+        // Polls created elsewhere cannot be ended by the relay wallet
+        let relay = contract.relay_address();
+        for poll in polls.iter().filter(|poll| poll.owner == relay) {
+            for action in self.due_actions(poll, now) {
+                // Finalizing requires the poll to be ended first
+                if !self.submit(poll.id, action).await? {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Actions to submit for a poll, in order
+    fn due_actions(&self, poll: &Poll, now: u64) -> Vec<Action> {
+        let deadline = poll.end_time.as_secs().saturating_add(self.grace_period);
+
+        let actions = if !poll.is_ended && deadline <= now {
+            vec![Action::End, Action::Finalize]
+        } else if poll.is_ended && poll.winner.is_empty() && !self.finalized.contains(&poll.id) {
+            vec![Action::Finalize]
+        } else {
+            vec![]
+        };
+
+        // Skip actions waiting for their next attempt, or abandoned
+        actions
+            .into_iter()
+            .filter(|action| match self.retries.get(&(poll.id, *action)) {
+                Some(retry) => {
+                    retry.attempts < self.max_attempts && retry.next_attempt <= Instant::now()
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    // Relay an action, returning whether it succeeded
+    async fn submit(&mut self, poll_id: U256, action: Action) -> Result<bool, ContractError> {
+        let contract = &self.app_state.contracts.votechain;
+        let attempt = self
+            .retries
+            .get(&(poll_id, action))
+            .map_or(1, |retry| retry.attempts + 1);
+
+        info!(
+            "Scheduler: {} on poll {} (attempt {})",
+            action.as_str(),
+            poll_id,
+            attempt
+        );
+        let result = match action {
+            Action::End => contract.end_poll(poll_id).await,
+            Action::Finalize => contract.finalize_poll(poll_id).await,
+        };
+
+        match result {
+            Ok(tx_hash) => {
+                self.retries.remove(&(poll_id, action));
+                if action == Action::Finalize {
+                    self.finalized.insert(poll_id);
+                }
+                self.audit(poll_id, action, "success", attempt, Some(tx_hash), None)
+                    .await;
+                Ok(true)
+            }
+            // Relays refused by the server are not attempts
+            Err(e @ (ContractError::ShuttingDown | ContractError::WrongChain(_))) => Err(e),
+            Err(e) => {
+                let outcome = if attempt >= self.max_attempts {
+                    error!(
+                        "Scheduler: giving up {} on poll {} after {} attempts: {}",
+                        action.as_str(),
+                        poll_id,
+                        attempt,
+                        e
+                    );
+                    "abandoned"
+                } else {
+                    warn!(
+                        "Scheduler: {} on poll {} failed: {}",
+                        action.as_str(),
+                        poll_id,
+                        e
+                    );
+                    "failed"
+                };
+
+                self.retries.insert(
+                    (poll_id, action),
+                    Retry {
+                        attempts: attempt,
+                        next_attempt: Instant::now() + self.retry_delay(attempt),
+                    },
+                );
+
+                self.audit(poll_id, action, outcome, attempt, None, Some(e.to_string()))
+                    .await;
+                Ok(false)
+            }
+        }
+    }
+
+    // Exponential backoff, starting at the scheduler interval
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        self.interval
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(MAX_RETRY_DELAY)
+    }
+
+    // Record an action in the audit table (failures to do so are only logged)
+    async fn audit(
+        &self,
+        poll_id: U256,
+        action: Action,
+        outcome: &str,
+        attempt: u32,
+        tx_hash: Option<TxHash>,
+        error: Option<String>,
+    ) {
+        let entry = SchedulerAudit {
+            poll_id: poll_id.to_string(),
+            action: action.as_str().to_owned(),
+            outcome: outcome.to_owned(),
+            attempt: attempt.try_into().unwrap_or(i32::MAX),
+            tx_hash: tx_hash.map(|hash| hash.to_string()),
+            error,
+            created_at: Some(SystemTime::now()),
+        };
+
+        if let Err(e) = db::query(&self.app_state.db, move |conn| entry.save(conn)).await {
+            warn!(
+                "Scheduler: unable to audit {} on poll {}: {}",
+                action.as_str(),
+                poll_id,
+                e
+            );
+        }
+    }
+}
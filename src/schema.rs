@@ -1,5 +1,91 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    merkle_allowlists (root, address) {
+        root -> Text,
+        address -> Text,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    poll_drafts (id) {
+        id -> Int4,
+        owner -> Text,
+        name -> Text,
+        description -> Text,
+        options -> Array<Text>,
+        start_time -> Int8,
+        end_time -> Int8,
+        metadata -> Nullable<Text>,
+        status -> Text,
+        approved_by -> Nullable<Text>,
+        poll_id -> Nullable<Text>,
+        tx_hash -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    poll_eligibility (poll_id, address) {
+        poll_id -> Text,
+        address -> Text,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    poll_metadata (hash) {
+        hash -> Text,
+        content -> Text,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    poll_owners (poll_id) {
+        poll_id -> Text,
+        owner -> Text,
+        tx_hash -> Text,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    poll_votes (poll_id, voter) {
+        poll_id -> Text,
+        voter -> Text,
+        status -> Text,
+        tx_hash -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    relay_jobs (tx_hash) {
+        tx_hash -> Text,
+        method -> Text,
+        status -> Text,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    scheduler_audit (id) {
+        id -> Int4,
+        poll_id -> Text,
+        action -> Text,
+        outcome -> Text,
+        attempt -> Int4,
+        tx_hash -> Nullable<Text>,
+        error -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     users (primary_account) {
         primary_account -> Text,
@@ -8,3 +94,44 @@ diesel::table! {
         created_at -> Nullable<Timestamp>,
     }
 }
+
+diesel::table! {
+    vote_commitments (poll_id, voter) {
+        poll_id -> Text,
+        voter -> Text,
+        commitment -> Text,
+        status -> Text,
+        tx_hash -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    vote_receipts (id) {
+        id -> Text,
+        poll_id -> Text,
+        voter -> Text,
+        option_hash -> Text,
+        tx_hash -> Text,
+        block_number -> Int8,
+        log_index -> Int8,
+        signer -> Text,
+        signature -> Text,
+        created_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::allow_tables_to_appear_in_same_query!(
+    merkle_allowlists,
+    poll_drafts,
+    poll_eligibility,
+    poll_metadata,
+    poll_owners,
+    poll_votes,
+    relay_jobs,
+    scheduler_audit,
+    users,
+    vote_commitments,
+    vote_receipts,
+);
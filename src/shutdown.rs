@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use actix_web::{dev::ServerHandle, web};
+use log::{info, warn};
+
+use crate::{config, relay, AppState};
+
+// Default time given to in-flight relayed transactions to be mined
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+
+/// Time given to the remaining HTTP requests once relays are drained
+pub const HTTP_SHUTDOWN_TIMEOUT_SECS: u64 = 5;
+
+// Resolve on SIGTERM or SIGINT (Ctrl+C)
+async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = sigterm.recv() => info!("Received SIGTERM"),
+                    _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+                }
+                return;
+            }
+            Err(e) => warn!("Unable to listen for SIGTERM: {}", e),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Unable to listen for SIGINT: {}", e);
+        std::future::pending::<()>().await;
+    }
+    info!("Received SIGINT");
+}
+
+/// Wait for a termination signal, then stop relaying new transactions, wait for the
+/// in-flight ones (up to SHUTDOWN_DRAIN_TIMEOUT_SECS), persist those still pending to
+/// the job table and stop the server
+pub async fn on_signal(server: ServerHandle, app_state: web::Data<AppState>) {
+    signal().await;
+
+    let timeout = Duration::from_secs(config::parse_or(
+        "SHUTDOWN_DRAIN_TIMEOUT_SECS",
+        DEFAULT_DRAIN_TIMEOUT_SECS,
+    ));
+    info!(
+        "Shutting down: draining relayed transactions (timeout {:?})...",
+        timeout
+    );
+
+    let pending = app_state.contracts.votechain.relay().drain(timeout).await;
+    if pending.is_empty() {
+        info!("All relayed transactions completed.");
+    } else {
+        relay::persist_jobs(&app_state, pending).await;
+    }
+
+    info!("Stopping HTTP server...");
+    server.stop(true).await;
+}
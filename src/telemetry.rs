@@ -0,0 +1,77 @@
+use opentelemetry::{global, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator, runtime::TokioCurrentThread, trace::TracerProvider,
+    Resource,
+};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+// Standard OpenTelemetry setting with the collector endpoint (e.g. http://localhost:4318)
+const OTLP_ENDPOINT_KEY: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+// Name of this service in the traces, unless overridden with OTEL_SERVICE_NAME
+const DEFAULT_SERVICE_NAME: &str = "votechain-api";
+
+/// Initialize logging and tracing. Spans are exported via OTLP/HTTP only when
+/// OTEL_EXPORTER_OTLP_ENDPOINT is set; the returned provider must be shut down on exit
+/// to flush pending spans.
+pub fn init() -> Option<TracerProvider> {
+    // Accept W3C traceparent / tracestate headers from our callers
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    // Keep honoring RUST_LOG, as env_logger did
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer());
+
+    let provider = match std::env::var(OTLP_ENDPOINT_KEY) {
+        Ok(endpoint) if !endpoint.is_empty() => match build_provider() {
+            Ok(provider) => Some(provider),
+            Err(e) => {
+                eprintln!("[!] Unable to create OTLP exporter for {}: {}", endpoint, e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    match &provider {
+        Some(provider) => {
+            let tracer = provider.tracer(DEFAULT_SERVICE_NAME);
+            registry
+                .with(tracing_opentelemetry::layer().with_tracer(tracer))
+                .init();
+            global::set_tracer_provider(provider.clone());
+        }
+        None => registry.init(),
+    }
+
+    provider
+}
+
+// Build a tracer provider exporting spans in batches to the OTLP collector
+fn build_provider() -> Result<TracerProvider, opentelemetry::trace::TraceError> {
+    // NOTE: the endpoint is read by the exporter itself from OTEL_EXPORTER_OTLP_ENDPOINT
+    let exporter = SpanExporter::builder().with_http().build()?;
+
+    let service_name =
+        std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_owned());
+
+    Ok(TracerProvider::builder()
+        .with_batch_exporter(exporter, TokioCurrentThread)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            service_name,
+        )]))
+        .build())
+}
+
+/// Flush pending spans and stop the exporter
+pub fn shutdown(provider: Option<TracerProvider>) {
+    if let Some(provider) = provider {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("[!] Failed to flush traces: {}", e);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::U256;
+use derive_more::{Display, Error};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+// Last second that can be written as RFC 3339 (9999-12-31T23:59:59Z)
+const MAX_SECS: u64 = 253_402_300_799;
+
+#[derive(Debug, Display, Error)]
+pub enum TimestampError {
+    #[display["timestamp must be between 1970-01-01T00:00:00Z and 9999-12-31T23:59:59Z"]]
+    OutOfRange,
+
+    #[display["invalid timestamp, expected RFC 3339 or unix seconds: {_0}"]]
+    Invalid(#[error(not(source))] String),
+}
+
+/// Point in time, stored as unix seconds like the contract does. Accepted from clients as
+/// RFC 3339 (with an offset) or unix seconds, and always returned as RFC 3339 in UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub fn from_secs(secs: u64) -> Result<Self, TimestampError> {
+        match secs <= MAX_SECS {
+            true => Ok(Self(secs)),
+            false => Err(TimestampError::OutOfRange),
+        }
+    }
+
+    pub fn as_secs(self) -> u64 {
+        self.0
+    }
+
+    /// Value passed to the contract
+    pub fn to_u256(self) -> U256 {
+        U256::from(self.0)
+    }
+
+    fn parse(value: &str) -> Result<Self, TimestampError> {
+        if let Ok(secs) = value.parse::<u64>() {
+            return Self::from_secs(secs);
+        }
+
+        let datetime = OffsetDateTime::parse(value, &Rfc3339)
+            .map_err(|e| TimestampError::Invalid(e.to_string()))?;
+        u64::try_from(datetime.unix_timestamp())
+            .map_err(|_| TimestampError::OutOfRange)
+            .and_then(Self::from_secs)
+    }
+}
+
+// Value returned by the contract
+impl TryFrom<U256> for Timestamp {
+    type Error = TimestampError;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        u64::try_from(value)
+            .map_err(|_| TimestampError::OutOfRange)
+            .and_then(Self::from_secs)
+    }
+}
+
+// Time recorded by the server
+impl TryFrom<SystemTime> for Timestamp {
+    type Error = TimestampError;
+
+    fn try_from(value: SystemTime) -> Result<Self, Self::Error> {
+        value
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| TimestampError::OutOfRange)
+            .and_then(|duration| Self::from_secs(duration.as_secs()))
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Always in range, see `from_secs`
+        let datetime =
+            OffsetDateTime::from_unix_timestamp(self.0 as i64).map_err(|_| fmt::Error)?;
+        let formatted = datetime.format(&Rfc3339).map_err(|_| fmt::Error)?;
+        f.write_str(&formatted)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(TimestampVisitor)
+    }
+}
+
+struct TimestampVisitor;
+
+impl de::Visitor<'_> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an RFC 3339 timestamp or unix seconds")
+    }
+
+    fn visit_u64<E: de::Error>(self, secs: u64) -> Result<Timestamp, E> {
+        Timestamp::from_secs(secs).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, secs: i64) -> Result<Timestamp, E> {
+        u64::try_from(secs)
+            .map_err(|_| E::custom(TimestampError::OutOfRange))
+            .and_then(|secs| self.visit_u64(secs))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Timestamp, E> {
+        Timestamp::parse(value).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_json(json: &str) -> Result<Timestamp, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn parses_unix_seconds() {
+        assert_eq!(from_json("1793520000").unwrap().as_secs(), 1_793_520_000);
+        assert_eq!(
+            from_json("\"1793520000\"").unwrap().as_secs(),
+            1_793_520_000
+        );
+        assert_eq!(from_json("0").unwrap().as_secs(), 0);
+    }
+
+    #[test]
+    fn parses_rfc3339_with_offset() {
+        let timestamp = from_json("\"2026-11-01T09:00:00+01:00\"").unwrap();
+        assert_eq!(timestamp.as_secs(), 1_793_520_000);
+        assert_eq!(timestamp.to_string(), "2026-11-01T08:00:00Z");
+        assert_eq!(
+            serde_json::to_string(&timestamp).unwrap(),
+            "\"2026-11-01T08:00:00Z\""
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range() {
+        assert!(from_json("-1").is_err());
+        assert!(from_json("\"1969-12-31T23:59:59Z\"").is_err());
+        assert!(Timestamp::from_secs(MAX_SECS).is_ok());
+        assert!(Timestamp::from_secs(MAX_SECS + 1).is_err());
+        assert!(Timestamp::try_from(U256::MAX).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(from_json("\"2026-11-01 09:00\"").is_err());
+        assert!(from_json("\"2026-11-01T09:00:00\"").is_err()); // Offset is required
+        assert!(from_json("\"tomorrow\"").is_err());
+        assert!(from_json("1.5").is_err());
+    }
+}
@@ -0,0 +1,433 @@
+use std::{collections::HashSet, sync::OnceLock};
+
+use crate::{
+    config,
+    errors::FieldError,
+    metadata::{self, PollMetadata},
+    timestamp::Timestamp,
+};
+
+// Defaults of the optional poll creation limits
+const DEFAULT_NAME_MAX_LENGTH: usize = 100;
+const DEFAULT_DESCRIPTION_MAX_LENGTH: usize = 1000;
+const DEFAULT_OPTION_MAX_LENGTH: usize = 100;
+const DEFAULT_MIN_OPTIONS: usize = 2;
+const DEFAULT_MAX_OPTIONS: usize = 20;
+const DEFAULT_MIN_DURATION_SECS: u64 = 60;
+const DEFAULT_MAX_HORIZON_SECS: u64 = 365 * 24 * 60 * 60;
+const DEFAULT_BODY_MAX_LENGTH: usize = 20_000;
+const DEFAULT_MAX_TAGS: usize = 10;
+const DEFAULT_MAX_LINKS: usize = 10;
+
+// Fixed limits of the metadata fields
+const TAG_MAX_LENGTH: usize = 50;
+const LABEL_MAX_LENGTH: usize = 100;
+const URL_MAX_LENGTH: usize = 2048;
+
+/// Limits applied to new polls, so that requests the contract would revert (or that make
+/// no sense) are rejected before paying for a transaction
+pub struct PollRules {
+    pub name_max_length: usize,        // in characters
+    pub description_max_length: usize, // in characters
+    pub option_max_length: usize,      // in characters
+    pub min_options: usize,
+    pub max_options: usize,
+    pub min_duration_secs: u64, // between start and end
+    pub max_horizon_secs: u64,  // between now and end
+    pub body_max_length: usize, // of the metadata, in characters
+    pub max_tags: usize,
+    pub max_links: usize,
+}
+
+// Rules read once from the POLL_* settings
+static RULES: OnceLock<PollRules> = OnceLock::new();
+
+impl PollRules {
+    pub fn get() -> &'static PollRules {
+        RULES.get_or_init(|| PollRules {
+            name_max_length: config::parse_or("POLL_NAME_MAX_LENGTH", DEFAULT_NAME_MAX_LENGTH),
+            description_max_length: config::parse_or(
+                "POLL_DESCRIPTION_MAX_LENGTH",
+                DEFAULT_DESCRIPTION_MAX_LENGTH,
+            ),
+            option_max_length: config::parse_or(
+                "POLL_OPTION_MAX_LENGTH",
+                DEFAULT_OPTION_MAX_LENGTH,
+            ),
+            min_options: config::parse_or("POLL_MIN_OPTIONS", DEFAULT_MIN_OPTIONS),
+            max_options: config::parse_or("POLL_MAX_OPTIONS", DEFAULT_MAX_OPTIONS),
+            min_duration_secs: config::parse_or(
+                "POLL_MIN_DURATION_SECS",
+                DEFAULT_MIN_DURATION_SECS,
+            ),
+            max_horizon_secs: config::parse_or("POLL_MAX_HORIZON_SECS", DEFAULT_MAX_HORIZON_SECS),
+            body_max_length: config::parse_or("POLL_BODY_MAX_LENGTH", DEFAULT_BODY_MAX_LENGTH),
+            max_tags: config::parse_or("POLL_MAX_TAGS", DEFAULT_MAX_TAGS),
+            max_links: config::parse_or("POLL_MAX_LINKS", DEFAULT_MAX_LINKS),
+        })
+    }
+}
+
+/// Fields of a poll to create
+pub struct NewPoll<'a> {
+    pub name: &'a str,
+    pub description: &'a str,
+    pub options: &'a [String],
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    pub metadata: Option<&'a PollMetadata>,
+}
+
+impl NewPoll<'_> {
+    /// Every problem found in the poll, checked against the chain time `now`
+    pub fn validate(&self, rules: &PollRules, now: u64) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        check_text(&mut errors, "name", self.name, rules.name_max_length);
+        // The stored description ends with the metadata hash, which counts in the limit
+        let description_max_length = match self.metadata {
+            Some(_) => rules
+                .description_max_length
+                .saturating_sub(metadata::COMMITMENT_LENGTH),
+            None => rules.description_max_length,
+        };
+        if self.description.chars().count() > description_max_length {
+            errors.push(FieldError::new(
+                "description",
+                format!("must be at most {} characters", description_max_length),
+            ));
+        }
+        // Reserved for the hash of the metadata, which must not be forged
+        if self.description.contains(metadata::COMMITMENT_PREFIX) {
+            errors.push(FieldError::new(
+                "description",
+                format!("must not contain `{}`", metadata::COMMITMENT_PREFIX),
+            ));
+        }
+
+        // Options
+        let count = self.options.len();
+        if count < rules.min_options || count > rules.max_options {
+            errors.push(FieldError::new(
+                "options",
+                format!(
+                    "must contain between {} and {} options",
+                    rules.min_options, rules.max_options
+                ),
+            ));
+        }
+        let mut seen = HashSet::new();
+        for (i, option) in self.options.iter().enumerate() {
+            let field = format!("options[{}]", i);
+            check_text(&mut errors, &field, option, rules.option_max_length);
+
+            // Votes are matched on the exact option, near duplicates would split them
+            if !seen.insert(option.trim().to_lowercase()) {
+                errors.push(FieldError::new(&field, "duplicates another option"));
+            }
+        }
+
+        // Times
+        let (start, end) = (self.start_time.as_secs(), self.end_time.as_secs());
+        if start < now {
+            errors.push(FieldError::new("start_time", "must not be in the past"));
+        }
+        if end <= start {
+            errors.push(FieldError::new("end_time", "must be after start_time"));
+        } else if end - start < rules.min_duration_secs {
+            errors.push(FieldError::new(
+                "end_time",
+                format!(
+                    "must be at least {} seconds after start_time",
+                    rules.min_duration_secs
+                ),
+            ));
+        }
+        if end > now.saturating_add(rules.max_horizon_secs) {
+            errors.push(FieldError::new(
+                "end_time",
+                format!(
+                    "must be at most {} seconds in the future",
+                    rules.max_horizon_secs
+                ),
+            ));
+        }
+
+        if let Some(metadata) = self.metadata {
+            self.validate_metadata(metadata, rules, &mut errors);
+        }
+
+        errors
+    }
+
+    fn validate_metadata(
+        &self,
+        metadata: &PollMetadata,
+        rules: &PollRules,
+        errors: &mut Vec<FieldError>,
+    ) {
+        if let Some(body) = &metadata.body {
+            if body.chars().count() > rules.body_max_length {
+                errors.push(FieldError::new(
+                    "metadata.body",
+                    format!("must be at most {} characters", rules.body_max_length),
+                ));
+            }
+        }
+        if let Some(cover_image) = &metadata.cover_image {
+            check_url(errors, "metadata.cover_image", cover_image);
+        }
+
+        // Descriptions of the options
+        let mut described = HashSet::new();
+        for (i, option) in metadata.options.iter().enumerate() {
+            let field = format!("metadata.options[{}]", i);
+            if !self.options.contains(&option.option) {
+                errors.push(FieldError::new(
+                    &format!("{}.option", field),
+                    "must be one of the options of the poll",
+                ));
+            } else if !described.insert(&option.option) {
+                errors.push(FieldError::new(
+                    &format!("{}.option", field),
+                    "is already described",
+                ));
+            }
+            if let Some(description) = &option.description {
+                if description.chars().count() > rules.description_max_length {
+                    errors.push(FieldError::new(
+                        &format!("{}.description", field),
+                        format!(
+                            "must be at most {} characters",
+                            rules.description_max_length
+                        ),
+                    ));
+                }
+            }
+            if let Some(image) = &option.image {
+                check_url(errors, &format!("{}.image", field), image);
+            }
+        }
+
+        if let Some(gate) = &metadata.token_gate {
+            if gate.token.is_zero() {
+                errors.push(FieldError::new(
+                    "metadata.token_gate.token",
+                    "must be a token contract",
+                ));
+            }
+            if gate.min_balance.is_zero() {
+                errors.push(FieldError::new(
+                    "metadata.token_gate.min_balance",
+                    "must be at least 1",
+                ));
+            }
+        }
+
+        // Both phases must last. Reveals are relayed as votes, which the contract refuses
+        // after end_time, so the reveal phase ends with the poll
+        if let Some(commit_reveal) = &metadata.commit_reveal {
+            if commit_reveal.commit_end_time <= self.start_time
+                || commit_reveal.commit_end_time >= self.end_time
+            {
+                errors.push(FieldError::new(
+                    "metadata.commit_reveal.commit_end_time",
+                    "must be between start_time and end_time (votes are revealed before end_time, the contract refuses them after)",
+                ));
+            }
+        }
+
+        // Tags and links
+        if metadata.tags.len() > rules.max_tags {
+            errors.push(FieldError::new(
+                "metadata.tags",
+                format!("must contain at most {} tags", rules.max_tags),
+            ));
+        }
+        for (i, tag) in metadata.tags.iter().enumerate() {
+            check_text(
+                errors,
+                &format!("metadata.tags[{}]", i),
+                tag,
+                TAG_MAX_LENGTH,
+            );
+        }
+        if metadata.links.len() > rules.max_links {
+            errors.push(FieldError::new(
+                "metadata.links",
+                format!("must contain at most {} links", rules.max_links),
+            ));
+        }
+        for (i, link) in metadata.links.iter().enumerate() {
+            let field = format!("metadata.links[{}]", i);
+            check_text(
+                errors,
+                &format!("{}.label", field),
+                &link.label,
+                LABEL_MAX_LENGTH,
+            );
+            check_url(errors, &format!("{}.url", field), &link.url);
+        }
+    }
+}
+
+// Required text of bounded length
+fn check_text(errors: &mut Vec<FieldError>, field: &str, value: &str, max_length: usize) {
+    if value.trim().is_empty() {
+        errors.push(FieldError::new(field, "must not be empty"));
+    } else if value.chars().count() > max_length {
+        errors.push(FieldError::new(
+            field,
+            format!("must be at most {} characters", max_length),
+        ));
+    }
+}
+
+// Absolute http(s) URL, rendered by clients
+fn check_url(errors: &mut Vec<FieldError>, field: &str, value: &str) {
+    let scheme = value.split_once("://").map(|(scheme, _)| scheme);
+    if !matches!(scheme, Some("http" | "https")) || value.contains(char::is_whitespace) {
+        errors.push(FieldError::new(field, "must be an http(s) URL"));
+    } else if value.len() > URL_MAX_LENGTH {
+        errors.push(FieldError::new(
+            field,
+            format!("must be at most {} characters", URL_MAX_LENGTH),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn rules() -> PollRules {
+        PollRules {
+            name_max_length: 10,
+            description_max_length: 200,
+            option_max_length: 10,
+            min_options: 2,
+            max_options: 3,
+            min_duration_secs: 60,
+            max_horizon_secs: 3600,
+            body_max_length: 20,
+            max_tags: 1,
+            max_links: 1,
+        }
+    }
+
+    fn at(secs: u64) -> Timestamp {
+        Timestamp::from_secs(secs).unwrap()
+    }
+
+    fn options(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn poll<'a>(options: &'a [String], metadata: Option<&'a PollMetadata>) -> NewPoll<'a> {
+        NewPoll {
+            name: "Lunch",
+            description: "Where do we eat?",
+            options,
+            start_time: at(NOW + 10),
+            end_time: at(NOW + 600),
+            metadata,
+        }
+    }
+
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|error| error.field.as_str()).collect()
+    }
+
+    #[test]
+    fn accepts_valid_poll() {
+        let options = options(&["Pizza", "Sushi"]);
+        assert!(poll(&options, None).validate(&rules(), NOW).is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_texts_and_options() {
+        let options = options(&["Pizza", " pizza ", "", "Tacos"]);
+        let mut poll = poll(&options, None);
+        poll.name = "  ";
+        let errors = poll.validate(&rules(), NOW);
+        assert_eq!(
+            fields(&errors),
+            ["name", "options", "options[1]", "options[2]"]
+        );
+    }
+
+    #[test]
+    fn rejects_forged_commitment_in_description() {
+        let options = options(&["Pizza", "Sushi"]);
+        let description = format!("{}0x00", metadata::COMMITMENT_PREFIX);
+        let mut poll = poll(&options, None);
+        poll.description = &description;
+        assert_eq!(fields(&poll.validate(&rules(), NOW)), ["description"]);
+    }
+
+    #[test]
+    fn counts_metadata_hash_in_description_limit() {
+        let options = options(&["Pizza", "Sushi"]);
+        let metadata: PollMetadata = serde_json::from_str("{}").unwrap();
+        let description = "a".repeat(200 - metadata::COMMITMENT_LENGTH + 1);
+        let mut poll = poll(&options, None);
+        poll.description = &description;
+        assert!(poll.validate(&rules(), NOW).is_empty());
+
+        poll.metadata = Some(&metadata);
+        let errors = poll.validate(&rules(), NOW);
+        assert_eq!(fields(&errors), ["description"]);
+        assert_eq!(errors[0].reason, "must be at most 114 characters");
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        let options = options(&["Pizza", "Sushi"]);
+        let mut poll = poll(&options, None);
+        poll.start_time = at(NOW - 1);
+        poll.end_time = at(NOW - 1);
+        assert_eq!(
+            fields(&poll.validate(&rules(), NOW)),
+            ["start_time", "end_time"]
+        );
+
+        poll.start_time = at(NOW);
+        poll.end_time = at(NOW + 59);
+        assert_eq!(fields(&poll.validate(&rules(), NOW)), ["end_time"]);
+
+        poll.end_time = at(NOW + 3601);
+        assert_eq!(fields(&poll.validate(&rules(), NOW)), ["end_time"]);
+    }
+
+    #[test]
+    fn rejects_invalid_metadata() {
+        let options = options(&["Pizza", "Sushi"]);
+        let metadata: PollMetadata = serde_json::from_str(
+            r#"{
+                "body": "This body is way too long",
+                "cover_image": "ftp://example.com/cover.png",
+                "options": [
+                    { "option": "Pizza" },
+                    { "option": "Pizza" },
+                    { "option": "Tacos", "image": "https://example.com/tacos.png" }
+                ],
+                "tags": ["food", "lunch"],
+                "links": [{ "label": "Menu", "url": "https://example.com/menu" }]
+            }"#,
+        )
+        .unwrap();
+        let errors = poll(&options, Some(&metadata)).validate(&rules(), NOW);
+        assert_eq!(
+            fields(&errors),
+            [
+                "metadata.body",
+                "metadata.cover_image",
+                "metadata.options[1].option",
+                "metadata.options[2].option",
+                "metadata.tags",
+            ]
+        );
+    }
+}